@@ -0,0 +1,368 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::emulator::Emulator8080;
+use crate::env::{Env, EnvConfig, ObservationKind, PlayerAction, STATE_SIZE};
+use crate::machine::SpaceInvaders;
+use crate::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// the machines are stepped on worker threads
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<Emulator8080>();
+    assert_send::<SpaceInvaders>();
+    assert_send::<Env>();
+};
+
+/// Observations of every instance, stacked one after the other.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackedObservations {
+    /// `len` screens of 224x256 pixels, see `Observation::Screen`.
+    Screen(Vec<u8>),
+    /// `len` state vectors of `STATE_SIZE` values.
+    State(Vec<f32>),
+}
+
+/// The result of `BatchRunner::step`, borrowed from the runner.
+#[derive(Debug)]
+pub struct BatchStep<'a> {
+    pub observations: &'a StackedObservations,
+    /// Score gained by every instance.
+    pub rewards: &'a [f32],
+    /// Which instances reached game over. They have already been reset,
+    /// so their observation is the start of the next game.
+    pub dones: &'a [bool],
+}
+
+/// Runs many independent games in lockstep, spread over threads.
+///
+/// Every instance is an `Env` with its own machine; instance `i` gets the
+/// seed `config.seed + i`. The results do not depend on the number of
+/// threads.
+///
+/// The instances are split in one chunk per thread. The threads live as
+/// long as the runner and every chunk has its own output buffers, which
+/// are copied into the stacked ones after a step, so stepping does not
+/// allocate.
+pub struct BatchRunner {
+    /// The chunks, while no worker has them.
+    chunks: Vec<Chunk>,
+    workers: Vec<Worker>,
+    /// Instances per chunk, the last one may have fewer.
+    chunk_size: usize,
+    len: usize,
+    observations: StackedObservations,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+}
+
+impl BatchRunner {
+    /// Creates a runner with an instance for each of `machines`, using
+    /// as many threads as the host has cores.
+    pub fn new(machines: Vec<SpaceInvaders>, config: EnvConfig) -> Self {
+        let count = machines.len();
+        let envs = machines
+            .into_iter()
+            .enumerate()
+            .map(|(index, machine)| {
+                let seed = config.seed.wrapping_add(index as u64);
+                Env::new(machine, EnvConfig { seed, ..config })
+            })
+            .collect();
+        let mut runner = Self {
+            chunks: Vec::new(),
+            workers: Vec::new(),
+            chunk_size: 0,
+            len: count,
+            observations: StackedObservations::new(config.observation, count),
+            rewards: vec![0.0; count],
+            dones: vec![false; count],
+        };
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        runner.start(envs, threads);
+        runner
+    }
+
+    /// Creates a runner with `count` machines running `rom`.
+    pub fn from_rom(rom: &[u8], count: usize, config: EnvConfig) -> Self {
+        let machine = SpaceInvaders::new(rom);
+        BatchRunner::new(vec![machine; count], config)
+    }
+
+    /// Number of instances.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sets the number of worker threads, at least 1.
+    ///
+    /// The threads are started again, so this is meant to be called
+    /// before stepping.
+    pub fn set_threads(&mut self, threads: usize) {
+        let envs = self.stop();
+        self.start(envs, threads.max(1));
+    }
+
+    /// The instance at `index`, e.g. to read its `GameState`.
+    pub fn env(&self, index: usize) -> &Env {
+        &self.chunks[index / self.chunk_size].envs[index % self.chunk_size]
+    }
+
+    /// Starts a new game on every instance and returns the observations.
+    pub fn reset(&mut self) -> &StackedObservations {
+        self.run(Command::Reset);
+        &self.observations
+    }
+
+    /// Plays `actions[i]` on instance `i` for one step.
+    pub fn step(&mut self, actions: &[PlayerAction]) -> BatchStep<'_> {
+        assert_eq!(actions.len(), self.len(), "need one action per instance");
+        for (chunk, actions) in self.chunks.iter_mut().zip(actions.chunks(self.chunk_size)) {
+            chunk.actions.copy_from_slice(actions);
+        }
+        self.run(Command::Step);
+        BatchStep {
+            observations: &self.observations,
+            rewards: &self.rewards,
+            dones: &self.dones,
+        }
+    }
+
+    /// Splits `envs` in a chunk per thread and starts the threads.
+    fn start(&mut self, envs: Vec<Env>, threads: usize) {
+        self.chunk_size = envs.len().div_ceil(threads).max(1);
+        let kind = self.observations.kind();
+        let mut envs = envs.into_iter();
+        while envs.len() > 0 {
+            let envs: Vec<Env> = envs.by_ref().take(self.chunk_size).collect();
+            self.chunks.push(Chunk {
+                actions: vec![PlayerAction::Noop; envs.len()],
+                rewards: vec![0.0; envs.len()],
+                dones: vec![false; envs.len()],
+                observations: StackedObservations::new(kind, envs.len()),
+                envs,
+            });
+            self.workers.push(Worker::spawn());
+        }
+    }
+
+    /// Stops the threads and returns the instances.
+    fn stop(&mut self) -> Vec<Env> {
+        self.workers.clear();
+        self.chunks.drain(..).flat_map(|chunk| chunk.envs).collect()
+    }
+
+    /// Hands every chunk to its worker for `command`, waits for them and
+    /// stacks their outputs.
+    fn run(&mut self, command: Command) {
+        for (worker, chunk) in self.workers.iter().zip(self.chunks.drain(..)) {
+            worker.slot.put(Task::Run(command, chunk));
+        }
+        for (index, worker) in self.workers.iter().enumerate() {
+            let chunk = worker.slot.take_done();
+            let start = index * self.chunk_size;
+            let end = start + chunk.envs.len();
+            self.rewards[start..end].copy_from_slice(&chunk.rewards);
+            self.dones[start..end].copy_from_slice(&chunk.dones);
+            self.observations.copy_chunk(start, &chunk.observations);
+            self.chunks.push(chunk);
+        }
+    }
+}
+
+/// What a worker does with its chunk.
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    Reset,
+    Step,
+}
+
+/// The instances of one worker with their actions and outputs.
+struct Chunk {
+    envs: Vec<Env>,
+    actions: Vec<PlayerAction>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+    observations: StackedObservations,
+}
+
+/// What is in the slot of a worker.
+enum Task {
+    Idle,
+    /// A chunk for the worker to run.
+    Run(Command, Chunk),
+    /// A chunk the worker has run.
+    Done(Chunk),
+    /// The worker panicked running its chunk.
+    Panicked,
+    Stop,
+}
+
+/// Where the runner and a worker hand a chunk to each other.
+///
+/// A mutex and a condition variable rather than channels, since they do
+/// not allocate when a thread starts waiting.
+struct Slot {
+    task: Mutex<Task>,
+    changed: Condvar,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<'_, Task> {
+        // nothing panics while holding the lock
+        self.task.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn put(&self, task: Task) {
+        *self.lock() = task;
+        self.changed.notify_all();
+    }
+
+    /// Waits for a task `ready` accepts and takes it, leaving the slot
+    /// idle.
+    fn wait(&self, ready: impl Fn(&Task) -> bool) -> Task {
+        let mut guard = self.lock();
+        while !ready(&guard) {
+            guard = self
+                .changed
+                .wait(guard)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+        std::mem::replace(&mut *guard, Task::Idle)
+    }
+
+    /// Waits for the chunk the worker has run.
+    fn take_done(&self) -> Chunk {
+        match self.wait(|task| matches!(task, Task::Done(_) | Task::Panicked)) {
+            Task::Done(chunk) => chunk,
+            _ => panic!("batch worker panicked"),
+        }
+    }
+}
+
+/// Tells the runner when its worker panics, so that it does not wait for
+/// the chunk forever.
+struct PanicGuard<'a>(&'a Slot);
+
+impl Drop for PanicGuard<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.put(Task::Panicked);
+        }
+    }
+}
+
+/// A thread running the commands for one chunk. The chunk is moved to the
+/// thread with the command and back with the result.
+struct Worker {
+    slot: Arc<Slot>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    fn spawn() -> Self {
+        let slot = Arc::new(Slot {
+            task: Mutex::new(Task::Idle),
+            changed: Condvar::new(),
+        });
+        let worker_slot = Arc::clone(&slot);
+        let thread = thread::spawn(move || {
+            let slot = &*worker_slot;
+            let _guard = PanicGuard(slot);
+            loop {
+                let task = slot.wait(|task| matches!(task, Task::Run(..) | Task::Stop));
+                let Task::Run(command, mut chunk) = task else {
+                    return;
+                };
+                chunk.run(command);
+                slot.put(Task::Done(chunk));
+            }
+        });
+        Self {
+            slot,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.slot.put(Task::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl StackedObservations {
+    /// Zeroed observations of `kind` for `count` instances.
+    fn new(kind: ObservationKind, count: usize) -> Self {
+        match kind {
+            ObservationKind::Screen => {
+                StackedObservations::Screen(vec![0; count * SCREEN_WIDTH * SCREEN_HEIGHT])
+            }
+            ObservationKind::State => StackedObservations::State(vec![0.0; count * STATE_SIZE]),
+        }
+    }
+
+    fn kind(&self) -> ObservationKind {
+        match self {
+            StackedObservations::Screen(_) => ObservationKind::Screen,
+            StackedObservations::State(_) => ObservationKind::State,
+        }
+    }
+
+    /// Copies the observations of `chunk` over those of the instances
+    /// from `start` on.
+    fn copy_chunk(&mut self, start: usize, chunk: &StackedObservations) {
+        match (self, chunk) {
+            (StackedObservations::Screen(pixels), StackedObservations::Screen(chunk)) => {
+                let offset = start * SCREEN_WIDTH * SCREEN_HEIGHT;
+                pixels[offset..offset + chunk.len()].copy_from_slice(chunk);
+            }
+            (StackedObservations::State(values), StackedObservations::State(chunk)) => {
+                let offset = start * STATE_SIZE;
+                values[offset..offset + chunk.len()].copy_from_slice(chunk);
+            }
+            _ => unreachable!("chunks have the observation kind of the runner"),
+        }
+    }
+}
+
+impl Chunk {
+    /// Resets or steps the instances, see `BatchRunner::reset` and
+    /// `BatchRunner::step`.
+    fn run(&mut self, command: Command) {
+        for (index, env) in self.envs.iter_mut().enumerate() {
+            match command {
+                Command::Step => {
+                    let (reward, done) = env.act(self.actions[index]);
+                    self.rewards[index] = reward;
+                    self.dones[index] = done;
+                    if done {
+                        env.restart();
+                    }
+                }
+                Command::Reset => {
+                    env.restart();
+                    self.rewards[index] = 0.0;
+                    self.dones[index] = false;
+                }
+            }
+            match &mut self.observations {
+                StackedObservations::Screen(pixels) => {
+                    let size = SCREEN_WIDTH * SCREEN_HEIGHT;
+                    env.screen_into(&mut pixels[index * size..(index + 1) * size]);
+                }
+                StackedObservations::State(values) => {
+                    env.state_into(&mut values[index * STATE_SIZE..(index + 1) * STATE_SIZE]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::env;
+use std::process::ExitCode;
+
+use space_invaders_emulator::cli::{
+    self, wants_help, DebugOptions, DEBUG_OPTIONS, MACHINE_OPTIONS,
+};
+use space_invaders_emulator::debugger;
+
+const USAGE: &str = "\
+Usage: debugger <rom> [options]
+
+Runs the game under a debugger controlled from a prompt, see `h` there.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if wants_help(&args) {
+        print!(
+            "{}\n{}\n{}\n{}",
+            USAGE,
+            DEBUG_OPTIONS,
+            MACHINE_OPTIONS,
+            debugger::COMMANDS
+        );
+        return ExitCode::SUCCESS;
+    }
+    cli::exit(DebugOptions::parse(&args).and_then(|options| options.run()))
+}
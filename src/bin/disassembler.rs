@@ -0,0 +1,22 @@
+use std::env;
+use std::process::ExitCode;
+
+use space_invaders_emulator::{disassembler, io_spainem};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: disassembler <file>");
+        return ExitCode::from(2);
+    }
+    match io_spainem::read_from_file(&args[1]) {
+        Ok(buffer) => {
+            disassembler::disassemble(&buffer);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(3)
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Command line plumbing shared by the binaries: argument parsing, the
+//! options that set up the machine and the errors with their exit codes.
+
+use std::fmt;
+use std::io;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::debugger::Debugger;
+use crate::dip::DipSwitches;
+use crate::games::{self, GameDefinition};
+use crate::golden::InputScript;
+use crate::io_spainem::{self, FileError, HighScoreError, RomError};
+use crate::machine::{SpaceInvaders, Watchdog};
+use crate::patch;
+
+/// Options every command that runs the machine accepts.
+pub const MACHINE_OPTIONS: &str = "\
+Machine options:
+  --game <name>         Game on the ROM, invaders by default, see `games`
+  --patch <file>        Apply an IPS or BPS patch to the ROM, can be repeated
+  --config <file>       Config file with [input] and [dip] sections
+  --dip <name>=<value>  Set a DIP switch of the game, see `games`
+  --ships <3..6>        Ships per game
+  --bonus <1000|1500>   Score of the extra ship
+  --coin-info <on|off>  Show the coin information in attract mode
+  --watchdog            Reset the CPU when the game stops kicking the watchdog
+";
+
+/// Error that ends a command, with the exit code it maps to.
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line is wrong.
+    Usage(String),
+    /// A file could not be read or written.
+    File(FileError),
+    /// A file was read but its contents are not valid, with the path in
+    /// the message.
+    Invalid(String),
+    /// The command ran but failed.
+    Failed(String),
+}
+
+impl CliError {
+    /// Error for the invalid contents of `path`.
+    pub fn invalid(path: &str, err: impl fmt::Display) -> Self {
+        CliError::Invalid(format!("{}: {}", path, err))
+    }
+
+    /// The exit code of the process.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Failed(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::File(_) => 3,
+            CliError::Invalid(_) => 4,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Invalid(message) | CliError::Failed(message) => {
+                write!(f, "{}", message)
+            }
+            CliError::File(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<FileError> for CliError {
+    fn from(err: FileError) -> Self {
+        CliError::File(err)
+    }
+}
+
+impl From<RomError> for CliError {
+    fn from(err: RomError) -> Self {
+        match err {
+            RomError::File(err) => CliError::File(err),
+            err => CliError::Invalid(err.to_string()),
+        }
+    }
+}
+
+impl From<HighScoreError> for CliError {
+    fn from(err: HighScoreError) -> Self {
+        match err {
+            HighScoreError::File(err) => CliError::File(err),
+            err => CliError::Invalid(err.to_string()),
+        }
+    }
+}
+
+/// The arguments of a command, read one at a time.
+pub struct Args<'a>(std::slice::Iter<'a, String>);
+
+impl<'a> Args<'a> {
+    pub fn new(args: &'a [String]) -> Self {
+        Args(args.iter())
+    }
+
+    /// The value following `option`.
+    pub fn value(&mut self, option: &str) -> Result<&'a str, CliError> {
+        self.next()
+            .ok_or_else(|| CliError::Usage(format!("{} is missing its value", option)))
+    }
+
+    /// The value following `option`, parsed as a number.
+    pub fn number<T: FromStr>(&mut self, option: &str) -> Result<T, CliError> {
+        let value = self.value(option)?;
+        value
+            .parse()
+            .map_err(|_| CliError::Usage(format!("{} expects a number, got `{}`", option, value)))
+    }
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.0.next().map(String::as_str)
+    }
+}
+
+/// Checks that `arg`, which no option took, is not an option itself.
+pub fn positional(arg: &str) -> Result<String, CliError> {
+    if arg.starts_with("--") {
+        return Err(CliError::Usage(format!("unknown option `{}`", arg)));
+    }
+    Ok(arg.to_owned())
+}
+
+/// Takes the `N` positional arguments a command expects.
+pub fn expect_positionals<const N: usize>(
+    values: Vec<String>,
+    expected: &str,
+) -> Result<[String; N], CliError> {
+    values.try_into().map_err(|values: Vec<String>| {
+        CliError::Usage(format!(
+            "expected {}, got {} argument{}",
+            expected,
+            values.len(),
+            if values.len() == 1 { "" } else { "s" }
+        ))
+    })
+}
+
+/// Whether `args` ask for the help of a command.
+pub fn wants_help(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "--help" || arg == "-h")
+}
+
+/// The sections of the config file: the keys of `InputMap::from_config`
+/// and the switches of `DipSwitches::from_config`.
+pub const CONFIG_SECTIONS: &[&str] = &["input", "dip"];
+
+/// Reads and parses the config file at `file_path`.
+pub fn load_config(file_path: &str) -> Result<Config, CliError> {
+    Config::parse(&io_spainem::read_text(file_path)?)
+        .and_then(|config| config.check_sections(CONFIG_SECTIONS).map(|()| config))
+        .map_err(|err| CliError::invalid(file_path, err))
+}
+
+/// Reads and parses the input script at `file_path`, or returns an empty
+/// one if there is none.
+pub fn load_script(file_path: Option<&str>) -> Result<InputScript, CliError> {
+    match file_path {
+        Some(file_path) => InputScript::parse(&io_spainem::read_text(file_path)?)
+            .map_err(|err| CliError::invalid(file_path, err)),
+        None => Ok(InputScript::new()),
+    }
+}
+
+/// Applies the patch at `patch_path` to `rom`.
+pub fn apply_patch(patch_path: &str, rom: &[u8]) -> Result<Vec<u8>, CliError> {
+    patch::apply(&io_spainem::read_from_file(patch_path)?, rom)
+        .map_err(|err| CliError::invalid(patch_path, err))
+}
+
+/// The game called `name`, Space Invaders if `None`.
+pub fn find_game(name: Option<&str>) -> Result<&'static GameDefinition, CliError> {
+    let Some(name) = name else {
+        return Ok(&games::INVADERS);
+    };
+    games::find(name).ok_or_else(|| {
+        CliError::Usage(format!(
+            "unknown game `{}`, run `space-invaders-emulator games` for the list",
+            name
+        ))
+    })
+}
+
+/// Options that set up the machine, shared by the commands that run it.
+#[derive(Default)]
+pub struct MachineOptions {
+    pub rom_path: String,
+    pub game: Option<String>,
+    /// IPS or BPS patches, applied in order.
+    pub patches: Vec<String>,
+    pub config: Option<String>,
+    /// DIP switches set on the command line, as (name, value), applied
+    /// over the config file.
+    pub dip_switches: Vec<(String, String)>,
+    pub watchdog: bool,
+}
+
+impl MachineOptions {
+    /// Takes `arg` and its value if it is one of `MACHINE_OPTIONS`, and
+    /// returns whether it was.
+    pub fn parse_option(&mut self, arg: &str, args: &mut Args) -> Result<bool, CliError> {
+        let dip_switch = match arg {
+            "--game" => {
+                self.game = Some(args.value(arg)?.to_owned());
+                return Ok(true);
+            }
+            "--patch" => {
+                self.patches.push(args.value(arg)?.to_owned());
+                return Ok(true);
+            }
+            "--dip" => {
+                let value = args.value(arg)?;
+                let (name, value) = value.split_once('=').ok_or_else(|| {
+                    CliError::Usage(format!("--dip expects <name>=<value>, got `{}`", value))
+                })?;
+                self.dip_switches
+                    .push((name.trim().to_owned(), value.trim().to_owned()));
+                return Ok(true);
+            }
+            "--config" => {
+                self.config = Some(args.value(arg)?.to_owned());
+                return Ok(true);
+            }
+            "--watchdog" => {
+                self.watchdog = true;
+                return Ok(true);
+            }
+            "--ships" => "ships",
+            "--bonus" => "bonus",
+            "--coin-info" => "coin_info",
+            _ => return Ok(false),
+        };
+        let value = args.value(arg)?;
+        self.dip_switches
+            .push((dip_switch.to_owned(), value.to_owned()));
+        Ok(true)
+    }
+
+    /// Loads the config file, or an empty one if none was given.
+    pub fn load_config(&self) -> Result<Config, CliError> {
+        match &self.config {
+            Some(file_path) => load_config(file_path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The game given with --game.
+    pub fn game(&self) -> Result<&'static GameDefinition, CliError> {
+        find_game(self.game.as_deref())
+    }
+
+    /// The DIP switches of `game` from `config`, overridden by those given
+    /// on the command line.
+    pub fn dip_switches(
+        &self,
+        game: &GameDefinition,
+        config: &Config,
+    ) -> Result<DipSwitches, CliError> {
+        let mut dip_switches = DipSwitches::from_config(game.dip_switches, config)
+            .map_err(|err| CliError::invalid(self.config.as_deref().unwrap_or_default(), err))?;
+        for (name, value) in &self.dip_switches {
+            dip_switches
+                .set(name, value)
+                .map_err(|err| CliError::Usage(err.to_string()))?;
+        }
+        Ok(dip_switches)
+    }
+
+    /// Creates the machine with the ROM and the DIP switches from the
+    /// config file and the command line.
+    pub fn create_machine(&self) -> Result<SpaceInvaders, CliError> {
+        let game = self.game()?;
+        let dip_switches = self.dip_switches(game, &self.load_config()?)?;
+        let mut rom = io_spainem::read_rom(&self.rom_path, game)?;
+        for patch_path in &self.patches {
+            rom = apply_patch(patch_path, &rom)?;
+            if rom.len() > game.rom_size() {
+                return Err(CliError::Invalid(format!(
+                    "{}: patched ROM is {} bytes, more than the {} bytes of program ROM",
+                    patch_path,
+                    rom.len(),
+                    game.rom_size()
+                )));
+            }
+        }
+        let mut machine = SpaceInvaders::with_game(game, &rom);
+        machine.io.dip_switches = dip_switches;
+        if self.watchdog {
+            machine.watchdog = Some(Watchdog::default());
+        }
+        Ok(machine)
+    }
+}
+
+/// Options of the debugger, besides `MACHINE_OPTIONS`.
+pub const DEBUG_OPTIONS: &str = "\
+Options:
+  --input <script>  Feed the inputs from an input script
+  --strict          Stop at undocumented opcodes instead of running them
+";
+
+/// Options of the debugger, see `DEBUG_OPTIONS`.
+#[derive(Default)]
+pub struct DebugOptions {
+    pub machine: MachineOptions,
+    pub input_script: Option<String>,
+    pub strict: bool,
+}
+
+impl DebugOptions {
+    /// Parses the arguments of the debugger: a ROM and the options.
+    pub fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut options = DebugOptions::default();
+        let mut positionals = Vec::new();
+        let mut args = Args::new(args);
+        while let Some(arg) = args.next() {
+            if options.machine.parse_option(arg, &mut args)? {
+                continue;
+            }
+            match arg {
+                "--input" => options.input_script = Some(args.value(arg)?.to_owned()),
+                "--strict" => options.strict = true,
+                _ => positionals.push(positional(arg)?),
+            }
+        }
+        let [rom_path] = expect_positionals(positionals, "a ROM")?;
+        options.machine.rom_path = rom_path;
+        Ok(options)
+    }
+
+    /// Runs the debugger on stdin and stdout.
+    pub fn run(&self) -> Result<(), CliError> {
+        let script = load_script(self.input_script.as_deref())?;
+        let mut machine = self.machine.create_machine()?;
+        machine.cpu.strict = self.strict;
+        let mut debugger = Debugger::new(machine, script, io::stdout());
+        debugger
+            .run(io::stdin().lock())
+            .map_err(|err| CliError::Failed(format!("debugger failed: {}", err)))
+    }
+}
+
+/// Reports the result of a command on stderr and returns the exit code
+/// for it.
+pub fn exit(result: Result<(), CliError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            if let CliError::Usage(_) = err {
+                eprintln!("Run with --help for usage.");
+            }
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
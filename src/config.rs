@@ -0,0 +1,128 @@
+use std::fmt;
+use std::fs;
+
+/// Error returned when a config file can not be parsed or holds an
+/// invalid setting.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    /// 1 based line number, 0 when the error is not tied to a line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// One `name = value` line of a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// 1 based line number, for error messages.
+    pub line: usize,
+    /// The section the line is in, empty before the first section header.
+    pub section: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl Entry {
+    /// An error about this entry.
+    pub fn error(&self, message: &str) -> ConfigError {
+        ConfigError {
+            line: self.line,
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// The settings of a config file, in the order they appear.
+///
+/// The format is INI-like: `[section]` headers followed by `name = value`
+/// lines, with `#` starting a comment:
+///
+/// ```text
+/// [input]
+/// coin = c
+/// p1_fire = space, w
+/// ```
+///
+/// What the names mean is up to the module reading each section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    entries: Vec<Entry>,
+}
+
+impl Config {
+    /// Parses the format described on the type.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| ConfigError {
+                line: index + 1,
+                message: message.to_owned(),
+            };
+            if let Some(header) = line.strip_prefix('[') {
+                let name = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("section header is missing `]`"))?;
+                section = name.trim().to_ascii_lowercase();
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `<name> = <value>`"))?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error("setting has no name"));
+            }
+            config.entries.push(Entry {
+                line: index + 1,
+                section: section.clone(),
+                name: name.to_ascii_lowercase(),
+                value: value.trim().to_owned(),
+            });
+        }
+        Ok(config)
+    }
+
+    /// Reads and parses the config file at `file_path`.
+    pub fn load(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Config::parse(&fs::read_to_string(file_path)?)?)
+    }
+
+    /// Checks that every entry is in one of `sections`, so that a
+    /// misspelt section header is reported instead of ignored.
+    pub fn check_sections(&self, sections: &[&str]) -> Result<(), ConfigError> {
+        match self
+            .entries
+            .iter()
+            .find(|entry| !sections.contains(&entry.section.as_str()))
+        {
+            Some(entry) if entry.section.is_empty() => {
+                Err(entry.error(&format!("`{}` is not in a section", entry.name)))
+            }
+            Some(entry) => Err(entry.error(&format!("unknown section `[{}]`", entry.section))),
+            None => Ok(()),
+        }
+    }
+
+    /// The entries of `section`, in file order.
+    pub fn section<'a>(&'a self, section: &'a str) -> impl Iterator<Item = &'a Entry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.section == section)
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::disassembler;
+use crate::emulator::{CpuError, CpuVariant, Emulator8080, StepOutcome};
+use crate::golden::InputScript;
+use crate::machine::SpaceInvaders;
+
+/// Help for the commands of `Debugger::command`.
+pub const COMMANDS: &str = "\
+Commands (numbers are decimal, addresses hex):
+  s [n]           Step n instructions, 1 by default
+  f [n]           Run n frames, 1 by default
+  c [frames]      Continue until a breakpoint, for at most 600 frames
+  b [addr]        Toggle a breakpoint at addr, or list the breakpoints
+  r               Show the registers
+  m <addr> [len]  Dump len bytes of memory, 64 by default
+  d [addr] [n]    Disassemble n instructions from addr, 10 from pc by default
+  q               Quit
+";
+
+/// The instruction at `address` in the memory of `cpu` and its length,
+/// wrapping around at the end of memory.
+pub fn instruction_at(cpu: &Emulator8080, address: u16) -> (String, u16) {
+    let bytes = [0, 1, 2].map(|offset| cpu.read_byte(address.wrapping_add(offset)));
+    match cpu.variant() {
+        CpuVariant::Intel8080 => disassembler::instruction_text(&bytes, 0),
+        CpuVariant::Intel8085 => disassembler::instruction_text_8085(&bytes, 0),
+    }
+}
+
+/// Writes the trace line of the instruction `cpu` is about to run: the
+/// frame, the address, the instruction, A, the flags, BC, DE, HL and SP.
+pub fn write_trace_line(out: &mut impl Write, frame: u64, cpu: &Emulator8080) -> io::Result<()> {
+    let (text, _) = instruction_at(cpu, cpu.pc);
+    writeln!(
+        out,
+        "{:>6} {:04x}  {:<18} a={:02x} f={:02x} bc={:02x}{:02x} de={:02x}{:02x} hl={:02x}{:02x} sp={:04x}",
+        frame,
+        cpu.pc,
+        text,
+        cpu.ra,
+        cpu.psw(),
+        cpu.rb,
+        cpu.rc,
+        cpu.rd,
+        cpu.re,
+        cpu.rh,
+        cpu.rl,
+        cpu.sp
+    )
+}
+
+/// Error returned by `trace`.
+#[derive(Debug)]
+pub enum TraceError {
+    /// The trace could not be written.
+    Io(io::Error),
+    /// The CPU stopped during `frame`.
+    Cpu { frame: u64, err: CpuError },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(f, "{}", err),
+            TraceError::Cpu { frame, err } => write!(f, "frame {}: {}", frame, err),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> Self {
+        TraceError::Io(err)
+    }
+}
+
+/// Runs `machine` for `from` frames, then writes a trace line for every
+/// instruction of the next `frames` frames to `out`, feeding the inputs
+/// from `script` all along. Script frames count from the frame the machine
+/// is at when called.
+///
+/// Bus faults are written after the line of their instruction; other CPU
+/// errors end the trace.
+pub fn trace(
+    machine: &mut SpaceInvaders,
+    script: &InputScript,
+    from: u64,
+    frames: u64,
+    out: &mut impl Write,
+) -> Result<(), TraceError> {
+    let start = machine.frame;
+    while machine.frame < start + from {
+        script.apply(machine, machine.frame - start);
+        machine.run_frame();
+    }
+    while machine.frame < start + from + frames {
+        script.apply(machine, machine.frame - start);
+        write_trace_line(out, machine.frame, &machine.cpu)?;
+        match machine.step() {
+            // the instruction ran; the game draws past video RAM late in
+            // long games, and patched ROMs can fault anywhere
+            Err(err @ CpuError::BusFault { .. }) => writeln!(out, "{}", err)?,
+            Err(err) => {
+                out.flush()?;
+                return Err(TraceError::Cpu {
+                    frame: machine.frame,
+                    err,
+                });
+            }
+            Ok(_) => {}
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Debugger running a machine under the commands in `COMMANDS`, writing
+/// its replies to `out`.
+pub struct Debugger<W> {
+    pub machine: SpaceInvaders,
+    pub script: InputScript,
+    /// The frame the machine was at when the debugger was created, where
+    /// the script starts.
+    start_frame: u64,
+    out: W,
+}
+
+impl<W: Write> Debugger<W> {
+    /// Creates a debugger for `machine`, fed the inputs from `script` from
+    /// its current frame on.
+    pub fn new(machine: SpaceInvaders, script: InputScript, out: W) -> Self {
+        Self {
+            start_frame: machine.frame,
+            machine,
+            script,
+            out,
+        }
+    }
+
+    /// Shows the registers, then runs the commands read from `input` after
+    /// a prompt until `q` or the end of the input.
+    pub fn run(&mut self, input: impl BufRead) -> io::Result<()> {
+        self.print_registers()?;
+        let mut lines = input.lines();
+        loop {
+            write!(self.out, "({:04x}) ", self.machine.cpu.pc)?;
+            self.out.flush()?;
+            let Some(line) = lines.next() else {
+                return writeln!(self.out);
+            };
+            if !self.command(&line?)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Runs one instruction, returning false if it stopped at a breakpoint
+    /// or an error instead. With `resume` a breakpoint at pc is passed.
+    fn step(&mut self, resume: bool) -> io::Result<bool> {
+        let frame = self.machine.frame - self.start_frame;
+        self.script.apply(&mut self.machine, frame);
+        match self.machine.step() {
+            Ok(StepOutcome::Breakpoint { .. }) if resume => self.step(false),
+            Ok(StepOutcome::Breakpoint { address }) => {
+                writeln!(self.out, "breakpoint at {:04x}", address)?;
+                Ok(false)
+            }
+            Ok(_) => Ok(true),
+            Err(err) => {
+                writeln!(self.out, "{}", err)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Runs up to `frames` frames, stopping at a breakpoint. The
+    /// instruction at pc runs even if it has a breakpoint, so a stopped
+    /// program can be continued.
+    fn run_frames(&mut self, frames: u64) -> io::Result<()> {
+        let end = self.machine.frame + frames;
+        let mut first = true;
+        while self.machine.frame < end {
+            if !self.step(first)? {
+                break;
+            }
+            first = false;
+        }
+        Ok(())
+    }
+
+    fn print_registers(&mut self) -> io::Result<()> {
+        write_trace_line(&mut self.out, self.machine.frame, &self.machine.cpu)
+    }
+
+    fn dump_memory(&mut self, address: u16, len: usize) -> io::Result<()> {
+        for row in (0..len).step_by(16) {
+            let start = address.wrapping_add(row as u16);
+            write!(self.out, "{:04x}:", start)?;
+            for offset in 0..16.min(len - row) {
+                let byte = self
+                    .machine
+                    .cpu
+                    .read_byte(start.wrapping_add(offset as u16));
+                write!(self.out, " {:02x}", byte)?;
+            }
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn disassemble(&mut self, mut address: u16, count: usize) -> io::Result<()> {
+        for _ in 0..count {
+            let (text, len) = instruction_at(&self.machine.cpu, address);
+            writeln!(self.out, "{:04x}:    {}", address, text)?;
+            address = address.wrapping_add(len);
+        }
+        Ok(())
+    }
+
+    /// Runs one command typed at the prompt, returning false to quit.
+    pub fn command(&mut self, line: &str) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let numbers: Vec<&str> = words.collect();
+        let decimal = |index: usize, default| match numbers.get(index) {
+            Some(word) => word.parse().ok(),
+            None => Some(default),
+        };
+        let address = |index: usize| {
+            numbers
+                .get(index)
+                .and_then(|word| u16::from_str_radix(word.trim_start_matches("0x"), 16).ok())
+        };
+
+        match command {
+            "" => {}
+            "s" => match decimal(0, 1) {
+                Some(count) => {
+                    for index in 0..count {
+                        if !self.step(index == 0)? {
+                            break;
+                        }
+                    }
+                    self.print_registers()?;
+                }
+                None => writeln!(self.out, "Usage: s [n]")?,
+            },
+            "f" => match decimal(0, 1) {
+                Some(frames) => {
+                    self.run_frames(frames)?;
+                    self.print_registers()?;
+                }
+                None => writeln!(self.out, "Usage: f [n]")?,
+            },
+            "c" => match decimal(0, 600) {
+                Some(frames) => {
+                    self.run_frames(frames)?;
+                    self.print_registers()?;
+                }
+                None => writeln!(self.out, "Usage: c [frames]")?,
+            },
+            "b" => match (numbers.is_empty(), address(0)) {
+                (true, _) => {
+                    for breakpoint in self.machine.cpu.breakpoints() {
+                        writeln!(self.out, "{:04x}", breakpoint)?;
+                    }
+                }
+                (false, Some(address)) => {
+                    if self.machine.cpu.clear_breakpoint(address) {
+                        writeln!(self.out, "breakpoint at {:04x} removed", address)?;
+                    } else {
+                        self.machine.cpu.set_breakpoint(address);
+                        writeln!(self.out, "breakpoint at {:04x} set", address)?;
+                    }
+                }
+                (false, None) => writeln!(self.out, "Usage: b [addr]")?,
+            },
+            "r" => self.print_registers()?,
+            "m" => match (address(0), decimal(1, 64)) {
+                (Some(address), Some(len)) => self.dump_memory(address, len as usize)?,
+                _ => writeln!(self.out, "Usage: m <addr> [len]")?,
+            },
+            "d" => {
+                let start = if numbers.is_empty() {
+                    Some(self.machine.cpu.pc)
+                } else {
+                    address(0)
+                };
+                match (start, decimal(1, 10)) {
+                    (Some(start), Some(count)) => self.disassemble(start, count as usize)?,
+                    _ => writeln!(self.out, "Usage: d [addr] [n]")?,
+                }
+            }
+            "q" => return Ok(false),
+            "h" | "help" => write!(self.out, "{}", COMMANDS)?,
+            _ => writeln!(self.out, "Unknown command `{}`, type h for help", command)?,
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use crate::config::{Config, ConfigError};
+use crate::games;
+
+/// Error returned for an unknown DIP switch setting or a value it can
+/// not take.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DipError {
+    UnknownSetting(String),
+    InvalidValue {
+        setting: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for DipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DipError::UnknownSetting(name) => write!(f, "unknown DIP switch `{}`", name),
+            DipError::InvalidValue {
+                setting,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value `{}` for {}, expected {}",
+                value, setting, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DipError {}
+
+/// One setting of a game's DIP switches: the bits of input port 2 it
+/// uses and the values it can take.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DipSetting {
+    /// The name used on the command line and in the config file.
+    pub name: &'static str,
+    pub mask: u8,
+    /// Every value as (text form, bits), the one with all switches off
+    /// first.
+    pub values: &'static [(&'static str, u8)],
+    /// The values for error messages, e.g. `3 to 6`.
+    pub expected: &'static str,
+}
+
+/// The DIP switches of the cabinet, read by the game through input port 2.
+///
+/// The switches are part of the cabinet rather than the machine state, so
+/// save states do not include them. Movies do, as `port2_bits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DipSwitches {
+    settings: &'static [DipSetting],
+    bits: u8,
+}
+
+impl DipSwitches {
+    /// The switches of a game with `settings`, all off.
+    pub fn new(settings: &'static [DipSetting]) -> Self {
+        Self { settings, bits: 0 }
+    }
+
+    /// The settings the switches make up.
+    pub fn settings(&self) -> &'static [DipSetting] {
+        self.settings
+    }
+
+    /// The text form of the value of the setting called `name`.
+    pub fn get(&self, name: &str) -> Option<&'static str> {
+        let setting = self.settings.iter().find(|setting| setting.name == name)?;
+        setting
+            .values
+            .iter()
+            .find(|&&(_, bits)| bits == self.bits & setting.mask)
+            .map(|&(value, _)| value)
+    }
+
+    /// Sets the setting called `name` from its text form, as used by the
+    /// command line and the config file, e.g. `ships` to `5` on Space
+    /// Invaders.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), DipError> {
+        let setting = self
+            .settings
+            .iter()
+            .find(|setting| setting.name == name)
+            .ok_or_else(|| DipError::UnknownSetting(name.to_owned()))?;
+        let &(_, bits) = setting
+            .values
+            .iter()
+            .find(|&&(text, _)| text == value)
+            .ok_or_else(|| DipError::InvalidValue {
+                setting: setting.name,
+                value: value.to_owned(),
+                expected: setting.expected,
+            })?;
+        self.bits = (self.bits & !setting.mask) | bits;
+        Ok(())
+    }
+
+    /// The switches of a game with `settings`, with the `[dip]` section
+    /// of `config` applied:
+    ///
+    /// ```text
+    /// [dip]
+    /// ships = 5
+    /// bonus = 1000
+    /// coin_info = off
+    /// ```
+    pub fn from_config(
+        settings: &'static [DipSetting],
+        config: &Config,
+    ) -> Result<Self, ConfigError> {
+        let mut switches = DipSwitches::new(settings);
+        for entry in config.section("dip") {
+            switches
+                .set(&entry.name, &entry.value)
+                .map_err(|err| entry.error(&err.to_string()))?;
+        }
+        Ok(switches)
+    }
+
+    /// The bits the switches set on input port 2.
+    pub fn port2_bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Sets the switches from bits returned by `port2_bits`, ignoring the
+    /// bits no setting uses.
+    pub fn set_port2_bits(&mut self, bits: u8) {
+        let mask = self
+            .settings
+            .iter()
+            .fold(0, |mask, setting| mask | setting.mask);
+        self.bits = bits & mask;
+    }
+}
+
+impl Default for DipSwitches {
+    /// The switches of Space Invaders, all off: 3 ships, extra ship at
+    /// 1500 points, coin information shown.
+    fn default() -> Self {
+        DipSwitches::new(games::INVADERS.dip_switches)
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::{self, Write};
+
 // bytes to string
 macro_rules! b2s {
     ($( $x:expr ), *) => {{
@@ -9,1036 +11,1154 @@ macro_rules! b2s {
     }};
 }
 
-pub fn disassemble_8080_op(buffer: &Vec<u8>, pc: usize) -> u16 {
+/// Prints the instruction at `pc` with its address and returns its length
+/// in bytes.
+pub fn disassemble_8080_op(buffer: &[u8], pc: usize) -> u16 {
+    let (text, op_bytes) = instruction_text(buffer, pc);
+    println!("{:#04x}:    {}", pc, text);
+    op_bytes
+}
+
+/// The mnemonic and operands of the instruction at `pc`, and its length in
+/// bytes.
+pub fn instruction_text(buffer: &[u8], pc: usize) -> (String, u16) {
+    let mut text = String::new();
+    let op_bytes = write_instruction(&mut text, buffer, pc).expect("writing to a String succeeds");
+    (text, op_bytes)
+}
+
+/// Like `instruction_text`, for the 8085: RIM, SIM and the undocumented
+/// 8085 instructions replace the 8080's aliases.
+pub fn instruction_text_8085(buffer: &[u8], pc: usize) -> (String, u16) {
+    let (text, op_bytes) = match buffer[pc] {
+        0x08 => ("*DSUB".to_owned(), 1),
+        0x10 => ("*ARHL".to_owned(), 1),
+        0x18 => ("*RDEL".to_owned(), 1),
+        0x20 => ("RIM".to_owned(), 1),
+        0x28 => (format!("*LDHI #{}", b2s!(buffer[pc + 1])), 2),
+        0x30 => ("SIM".to_owned(), 1),
+        0x38 => (format!("*LDSI #{}", b2s!(buffer[pc + 1])), 2),
+        0xcb => ("*RSTV".to_owned(), 1),
+        0xd9 => ("*SHLX".to_owned(), 1),
+        0xdd => (format!("*JNK  {}", b2s!(buffer[pc + 2], buffer[pc + 1])), 3),
+        0xed => ("*LHLX".to_owned(), 1),
+        0xfd => (format!("*JK   {}", b2s!(buffer[pc + 2], buffer[pc + 1])), 3),
+        _ => return instruction_text(buffer, pc),
+    };
+    (text, op_bytes)
+}
+
+fn write_instruction(out: &mut impl Write, buffer: &[u8], pc: usize) -> Result<u16, fmt::Error> {
     let code = buffer[pc];
     let mut op_bytes: u16 = 1;
-    print!("{:#04x}:    ", pc);
 
     match code {
         0x00 => {
             // no-op
-            println!("NOP");
+            write!(out, "NOP")?;
         }
         0x01 => {
             // B <- byte3, C <- byte2
-            println!("LXI   B, #${}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "LXI   B, #${}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x02 => {
             // (BC) <- A
-            println!("STAX  B");
+            write!(out, "STAX  B")?;
         }
         0x03 => {
             // BC <- BC + 1
-            println!("INX   B");
+            write!(out, "INX   B")?;
         }
         0x04 => {
             // B <- B + 1
-            println!("INR   B");
+            write!(out, "INR   B")?;
         }
         0x05 => {
             // B <- B - 1
-            println!("DCR   B");
+            write!(out, "DCR   B")?;
         }
         0x06 => {
             // B <- byte2
-            println!("MVI   B, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   B, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x07 => {
             // A << 1; bit0 = prev bit7, CY=prev bit7
-            println!("RLC");
+            write!(out, "RLC")?;
         }
         0x09 => {
             // HL <- HL + BC
-            println!("DAD   B");
+            write!(out, "DAD   B")?;
         }
         0x0a => {
             // A <- (BC)
-            println!("LDAX  B");
+            write!(out, "LDAX  B")?;
         }
         0x0b => {
             // BC <- BC - 1
-            println!("DCX   B");
+            write!(out, "DCX   B")?;
         }
         0x0c => {
             // C <- C + 1
-            println!("INR   C");
+            write!(out, "INR   C")?;
         }
         0x0d => {
             // C <- C - 1
-            println!("DCR   C");
+            write!(out, "DCR   C")?;
         }
         0x0e => {
             // C <- byte2
-            println!("MVI   C, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   C, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x0f => {
             // A >> 1, bit7 = prev bit0, CY=prev bit0
-            println!("RRC");
+            write!(out, "RRC")?;
         }
         0x11 => {
             // D <- byte3, E <- byte2
-            println!("LXI   D, {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "LXI   D, {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x12 => {
             // (DE) <- A
-            println!("STAX  D");
+            write!(out, "STAX  D")?;
         }
         0x13 => {
             // DE <- DE + 1
-            println!("INX   D");
+            write!(out, "INX   D")?;
         }
         0x14 => {
             // D <- D + 1
-            println!("INR   D");
+            write!(out, "INR   D")?;
         }
         0x15 => {
             // D <- D - 1
-            println!("DCR   D");
+            write!(out, "DCR   D")?;
         }
         0x16 => {
             // D <- byte2
-            println!("MVI   D, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   D, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x17 => {
             // A = A << 1; bit0 = prev CY; CY = prev bit7
-            println!("RAL");
+            write!(out, "RAL")?;
         }
         0x19 => {
             //HL = HL + DE
-            println!("DAD   D");
+            write!(out, "DAD   D")?;
         }
         0x1a => {
             // A <- (DE)
-            println!("LDAX  D");
+            write!(out, "LDAX  D")?;
         }
         0x1b => {
             // DE = DE - 1
-            println!("DCX   D");
+            write!(out, "DCX   D")?;
         }
         0x1c => {
             // E <- E + 1
-            println!("INR   E");
+            write!(out, "INR   E")?;
         }
         0x1d => {
             // E <- E - 1
-            println!("DCR   E");
+            write!(out, "DCR   E")?;
         }
         0x1e => {
             // E <- byte2
-            println!("MVI   E, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   E, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x1f => {
             // A = A >> 1; bit7 = prev bit7; CY = prev bit0
-            println!("RAR");
+            write!(out, "RAR")?;
         }
         0x21 => {
             // H <- byte3, L <- byte2
-            println!("LXI   H, #{}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "LXI   H, #{}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x22 => {
             // (adr) <- L; (adr+1) <- H
-            println!("SHLD  {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "SHLD  {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x23 => {
             // HL <- HL + 1
-            println!("INX   H");
+            write!(out, "INX   H")?;
         }
         0x24 => {
             // H <- H + 1
-            println!("INR   H");
+            write!(out, "INR   H")?;
         }
         0x25 => {
             // H <- H - 1
-            println!("DCR   H");
+            write!(out, "DCR   H")?;
         }
         0x26 => {
             // H <- byte2
-            println!("MVI   H, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   H, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x27 => {
             // special
-            println!("DAA");
+            write!(out, "DAA")?;
         }
         0x29 => {
             // HL = HL + HI
-            println!("DAD   H");
+            write!(out, "DAD   H")?;
         }
         0x2a => {
             // L <- (adr); H <- (adr + 1)
-            println!("LHLD  {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "LHLD  {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x2b => {
             // HL = HL - 1
-            println!("DCX   H");
+            write!(out, "DCX   H")?;
         }
         0x2c => {
             // L <- L + 1
-            println!("INR   L");
+            write!(out, "INR   L")?;
         }
         0x2d => {
             // L <- L - 1
-            println!("DCR   L");
+            write!(out, "DCR   L")?;
         }
         0x2e => {
             // L <- byte2
-            println!("MVI   L, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   L, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x2f => {
             // A <- !A
-            println!("CMA");
+            write!(out, "CMA")?;
         }
         0x31 => {
             // SP.hi <- byte3, SP.lo <- byte2
-            println!("LXI   SP, #{}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "LXI   SP, #{}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x32 => {
             // (adr) <- A
-            println!("STA   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "STA   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x33 => {
             // SP <- SP + 1
-            println!("INX   SP");
+            write!(out, "INX   SP")?;
         }
         0x34 => {
             // (HL) <- (HL) + 1
-            println!("INR   M");
+            write!(out, "INR   M")?;
         }
         0x35 => {
             // (HL) <- (HL) - 1
-            println!("DCR   M");
+            write!(out, "DCR   M")?;
         }
         0x36 => {
             // (HL) <- byte2
-            println!("MVI   M, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   M, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x37 => {
             // CY = 1
-            println!("STC");
+            write!(out, "STC")?;
         }
         0x39 => {
             // HL = HL + SP
-            println!("DAD   SP");
+            write!(out, "DAD   SP")?;
         }
         0x3a => {
             // A <- (adr)
-            println!("LDA   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "LDA   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0x3b => {
             // SP <- SP - 1
-            println!("DCX   SP");
+            write!(out, "DCX   SP")?;
         }
         0x3c => {
             // A <- A + 1
-            println!("INR   A");
+            write!(out, "INR   A")?;
         }
         0x3d => {
             // A <- A - 1
-            println!("DCR   A");
+            write!(out, "DCR   A")?;
         }
         0x3e => {
             // A <- byte2
-            println!("MVI   A, #{}", b2s!(buffer[pc + 1]));
+            write!(out, "MVI   A, #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0x3f => {
             // CY = !CY
-            println!("CMC");
+            write!(out, "CMC")?;
         }
         0x40 => {
             // B <- B
-            println!("MOV   B, B");
+            write!(out, "MOV   B, B")?;
         }
         0x41 => {
             // B <- C
-            println!("MOV   B, C");
+            write!(out, "MOV   B, C")?;
         }
         0x42 => {
             // B <- D
-            println!("MOV   B, D");
+            write!(out, "MOV   B, D")?;
         }
         0x43 => {
             // B <- E
-            println!("MOV   B, E");
+            write!(out, "MOV   B, E")?;
         }
         0x44 => {
             // B <- H
-            println!("MOV   B, H");
+            write!(out, "MOV   B, H")?;
         }
         0x45 => {
             // B <- L
-            println!("MOV   B, L");
+            write!(out, "MOV   B, L")?;
         }
         0x46 => {
             // B <- (HL)
-            println!("MOV   B, M");
+            write!(out, "MOV   B, M")?;
         }
         0x47 => {
             // B <- A
-            println!("MOV   B, A");
+            write!(out, "MOV   B, A")?;
         }
         0x48 => {
             // C <- B
-            println!("MOV   C, B");
+            write!(out, "MOV   C, B")?;
         }
         0x49 => {
             // C <- C
-            println!("MOV   C, C");
+            write!(out, "MOV   C, C")?;
         }
         0x4a => {
             // C <- D
-            println!("MOV   C, D");
+            write!(out, "MOV   C, D")?;
         }
         0x4b => {
             // C <- E
-            println!("MOV   C, E");
+            write!(out, "MOV   C, E")?;
         }
         0x4c => {
             // C <- H
-            println!("MOV   C, H");
+            write!(out, "MOV   C, H")?;
         }
         0x4d => {
             // C <- L
-            println!("MOV   C, L");
+            write!(out, "MOV   C, L")?;
         }
         0x4e => {
             // C <- (HL)
-            println!("MOV   C, M");
+            write!(out, "MOV   C, M")?;
         }
         0x4f => {
             // C <- A
-            println!("MOV   C, A");
+            write!(out, "MOV   C, A")?;
         }
         0x50 => {
             // D <- B
-            println!("MOV   D, B");
+            write!(out, "MOV   D, B")?;
         }
         0x51 => {
             // D <- C
-            println!("MOV   D, C");
+            write!(out, "MOV   D, C")?;
         }
         0x52 => {
             // D <- D
-            println!("MOV   D, D");
+            write!(out, "MOV   D, D")?;
         }
         0x53 => {
             // D <- E
-            println!("MOV   D, E");
+            write!(out, "MOV   D, E")?;
         }
         0x54 => {
             // D <- H
-            println!("MOV   D, H");
+            write!(out, "MOV   D, H")?;
         }
         0x55 => {
             // D <- L
-            println!("MOV   D, L");
+            write!(out, "MOV   D, L")?;
         }
         0x56 => {
             // D <- (HL)
-            println!("MOV   D, M");
+            write!(out, "MOV   D, M")?;
         }
         0x57 => {
             // D <- A
-            println!("MOV   D, A");
+            write!(out, "MOV   D, A")?;
         }
         0x58 => {
             // E <- B
-            println!("MOV   E, B");
+            write!(out, "MOV   E, B")?;
         }
         0x59 => {
             // E <- C
-            println!("MOV   E, C");
+            write!(out, "MOV   E, C")?;
         }
         0x5a => {
             // E <- D
-            println!("MOV   E, D");
+            write!(out, "MOV   E, D")?;
         }
         0x5b => {
             // E <- E
-            println!("MOV   E, E");
+            write!(out, "MOV   E, E")?;
         }
         0x5c => {
             // E <- H
-            println!("MOV   E, H");
+            write!(out, "MOV   E, H")?;
         }
         0x5d => {
             // E <- L
-            println!("MOV   E, L");
+            write!(out, "MOV   E, L")?;
         }
         0x5e => {
             // E <- (HL)
-            println!("MOV   E, M");
+            write!(out, "MOV   E, M")?;
         }
         0x5f => {
             // E <- A
-            println!("MOV   E, A");
+            write!(out, "MOV   E, A")?;
         }
         0x60 => {
             // H <- B
-            println!("MOV   H, B");
+            write!(out, "MOV   H, B")?;
         }
         0x61 => {
             // H <- C
-            println!("MOV   H, C");
+            write!(out, "MOV   H, C")?;
         }
         0x62 => {
             // H <- D
-            println!("MOV   H, D");
+            write!(out, "MOV   H, D")?;
         }
         0x63 => {
             // H <- E
-            println!("MOV   H, E");
+            write!(out, "MOV   H, E")?;
         }
         0x64 => {
             // H <- H
-            println!("MOV   H, H");
+            write!(out, "MOV   H, H")?;
         }
         0x65 => {
             // H <- L
-            println!("MOV   H, L");
+            write!(out, "MOV   H, L")?;
         }
         0x66 => {
             // H <- (HL)
-            println!("MOV   H, M");
+            write!(out, "MOV   H, M")?;
         }
         0x67 => {
             // H <- A
-            println!("MOV   H, A");
+            write!(out, "MOV   H, A")?;
         }
         0x68 => {
             // L <- B
-            println!("MOV   L, B");
+            write!(out, "MOV   L, B")?;
         }
         0x69 => {
             // L <- C
-            println!("MOV   L, C");
+            write!(out, "MOV   L, C")?;
         }
         0x6a => {
             // L <- D
-            println!("MOV   L, D");
+            write!(out, "MOV   L, D")?;
         }
         0x6b => {
             // L <- E
-            println!("MOV   L, E");
+            write!(out, "MOV   L, E")?;
         }
         0x6c => {
             // L <- H
-            println!("MOV   L, H");
+            write!(out, "MOV   L, H")?;
         }
         0x6d => {
             // L <- L
-            println!("MOV   L, L");
+            write!(out, "MOV   L, L")?;
         }
         0x6e => {
             // L <- (HL)
-            println!("MOV   L, M");
+            write!(out, "MOV   L, M")?;
         }
         0x6f => {
             // L <- A
-            println!("MOV   L, A");
+            write!(out, "MOV   L, A")?;
         }
         0x70 => {
             // M <- B
-            println!("MOV   M, B");
+            write!(out, "MOV   M, B")?;
         }
         0x71 => {
             // M <- C
-            println!("MOV   M, C");
+            write!(out, "MOV   M, C")?;
         }
         0x72 => {
             // M <- D
-            println!("MOV   M, D");
+            write!(out, "MOV   M, D")?;
         }
         0x73 => {
             // M <- E
-            println!("MOV   M, E");
+            write!(out, "MOV   M, E")?;
         }
         0x74 => {
             // M <- H
-            println!("MOV   M, H");
+            write!(out, "MOV   M, H")?;
         }
         0x75 => {
             // M <- L
-            println!("MOV   M, L");
+            write!(out, "MOV   M, L")?;
         }
         0x76 => {
             // special
-            println!("HLT");
+            write!(out, "HLT")?;
         }
         0x77 => {
             // M <- A
-            println!("MOV   M, A");
+            write!(out, "MOV   M, A")?;
         }
         0x78 => {
             // A <- B
-            println!("MOV   A, B");
+            write!(out, "MOV   A, B")?;
         }
         0x79 => {
             // A <- C
-            println!("MOV   A, C");
+            write!(out, "MOV   A, C")?;
         }
         0x7a => {
             // A <- D
-            println!("MOV   A, D");
+            write!(out, "MOV   A, D")?;
         }
         0x7b => {
             // A <- E
-            println!("MOV   A, E");
+            write!(out, "MOV   A, E")?;
         }
         0x7c => {
             // A <- H
-            println!("MOV   A, H");
+            write!(out, "MOV   A, H")?;
         }
         0x7d => {
             // A <- L
-            println!("MOV   A, L");
+            write!(out, "MOV   A, L")?;
         }
         0x7e => {
             // A <- (HL)
-            println!("MOV   A, M");
+            write!(out, "MOV   A, M")?;
         }
         0x7f => {
             // A <- A
-            println!("MOV   A, A");
+            write!(out, "MOV   A, A")?;
         }
         0x80 => {
             // A <- A + B
-            println!("ADD   B");
+            write!(out, "ADD   B")?;
         }
         0x81 => {
             // A <- A + C
-            println!("ADD   C");
+            write!(out, "ADD   C")?;
         }
         0x82 => {
             // A <- A + D
-            println!("ADD   D");
+            write!(out, "ADD   D")?;
         }
         0x83 => {
             // A <- A + E
-            println!("ADD   E");
+            write!(out, "ADD   E")?;
         }
         0x84 => {
             // A <- A + H
-            println!("ADD   H");
+            write!(out, "ADD   H")?;
         }
         0x85 => {
             // A <- A + L
-            println!("ADD   L");
+            write!(out, "ADD   L")?;
         }
         0x86 => {
             // A <- A + (HL)
-            println!("ADD   M");
+            write!(out, "ADD   M")?;
         }
         0x87 => {
             // A <- A + A
-            println!("ADD   A");
+            write!(out, "ADD   A")?;
         }
         0x88 => {
             // A <- A + B + CY
-            println!("ADC   B");
+            write!(out, "ADC   B")?;
         }
         0x89 => {
             // A <- A + C + CY
-            println!("ADC   C");
+            write!(out, "ADC   C")?;
         }
         0x8a => {
             // A <- A + D + CY
-            println!("ADC   D");
+            write!(out, "ADC   D")?;
         }
         0x8b => {
             // A <- A + E + CY
-            println!("ADC   E");
+            write!(out, "ADC   E")?;
         }
         0x8c => {
             // A <- A + H + CY
-            println!("ADC   H");
+            write!(out, "ADC   H")?;
         }
         0x8d => {
             // A <- A + L + CY
-            println!("ADC   L");
+            write!(out, "ADC   L")?;
         }
         0x8e => {
             // A <- A + (HL) + CY
-            println!("ADC   M");
+            write!(out, "ADC   M")?;
         }
         0x8f => {
             // A <- A + A + CY
-            println!("ADC   A");
+            write!(out, "ADC   A")?;
         }
         0x90 => {
             // A <- A - B
-            println!("SUB   B");
+            write!(out, "SUB   B")?;
         }
         0x91 => {
             // A <- A - C
-            println!("SUB   C");
+            write!(out, "SUB   C")?;
         }
         0x92 => {
             // A <- A - D
-            println!("SUB   D");
+            write!(out, "SUB   D")?;
         }
         0x93 => {
             // A <- A - E
-            println!("SUB   E");
+            write!(out, "SUB   E")?;
         }
         0x94 => {
             // A <- A - H
-            println!("SUB   H");
+            write!(out, "SUB   H")?;
         }
         0x95 => {
             // A <- A - L
-            println!("SUB   L");
+            write!(out, "SUB   L")?;
         }
         0x96 => {
             // A <- A - (HL)
-            println!("SUB   M");
+            write!(out, "SUB   M")?;
         }
         0x97 => {
             // A <- A - A
-            println!("SUB   A");
+            write!(out, "SUB   A")?;
         }
         0x98 => {
             // A <- A - B - CY
-            println!("SBB   B");
+            write!(out, "SBB   B")?;
         }
         0x99 => {
             // A <- A - C - CY
-            println!("SBB   C");
+            write!(out, "SBB   C")?;
         }
         0x9a => {
             // A <- A - D - CY
-            println!("SBB   D");
+            write!(out, "SBB   D")?;
         }
         0x9b => {
             // A <- A - E - CY
-            println!("SBB   E");
+            write!(out, "SBB   E")?;
         }
         0x9c => {
             // A <- A - H - CY
-            println!("SBB   H");
+            write!(out, "SBB   H")?;
         }
         0x9d => {
             // A <- A - L - CY
-            println!("SBB   L");
+            write!(out, "SBB   L")?;
         }
         0x9e => {
             // A <- A - (HL) - CY
-            println!("SBB   M");
+            write!(out, "SBB   M")?;
         }
         0x9f => {
             // A <- A - A - CY
-            println!("SBB   A");
+            write!(out, "SBB   A")?;
         }
         0xa0 => {
             // A <- A & B
-            println!("ANA   B");
+            write!(out, "ANA   B")?;
         }
         0xa1 => {
             // A <- A & C
-            println!("ANA   C");
+            write!(out, "ANA   C")?;
         }
         0xa2 => {
             // A <- A & D
-            println!("ANA   D");
+            write!(out, "ANA   D")?;
         }
         0xa3 => {
             // A <- A & E
-            println!("ANA   E");
+            write!(out, "ANA   E")?;
         }
         0xa4 => {
             // A <- A & H
-            println!("ANA   H");
+            write!(out, "ANA   H")?;
         }
         0xa5 => {
             // A <- A & L
-            println!("ANA   L");
+            write!(out, "ANA   L")?;
         }
         0xa6 => {
             // A <- A & (HL)
-            println!("ANA   M");
+            write!(out, "ANA   M")?;
         }
         0xa7 => {
             // A <- A & A
-            println!("ANA   A");
+            write!(out, "ANA   A")?;
         }
         0xa8 => {
             // A <- A ^ B
-            println!("XRA   B");
+            write!(out, "XRA   B")?;
         }
         0xa9 => {
             // A <- A ^ C
-            println!("XRA   C");
+            write!(out, "XRA   C")?;
         }
         0xaa => {
             // A <- A ^ D
-            println!("XRA   D");
+            write!(out, "XRA   D")?;
         }
         0xab => {
             // A <- A ^ E
-            println!("XRA   E");
+            write!(out, "XRA   E")?;
         }
         0xac => {
             // A <- A ^ H
-            println!("XRA   H");
+            write!(out, "XRA   H")?;
         }
         0xad => {
             // A <- A ^ L
-            println!("XRA   L");
+            write!(out, "XRA   L")?;
         }
         0xae => {
             // A <- A ^ (HL)
-            println!("XRA   M");
+            write!(out, "XRA   M")?;
         }
         0xaf => {
             // A <- A ^ A
-            println!("XRA   A");
+            write!(out, "XRA   A")?;
         }
         0xb0 => {
             // A <- A | B
-            println!("ORA   B");
+            write!(out, "ORA   B")?;
         }
         0xb1 => {
             // A <- A | C
-            println!("ORA   C");
+            write!(out, "ORA   C")?;
         }
         0xb2 => {
             // A <- A | D
-            println!("ORA   D");
+            write!(out, "ORA   D")?;
         }
         0xb3 => {
             // A <- A | E
-            println!("ORA   E");
+            write!(out, "ORA   E")?;
         }
         0xb4 => {
             // A <- A | H
-            println!("ORA   H");
+            write!(out, "ORA   H")?;
         }
         0xb5 => {
             // A <- A | L
-            println!("ORA   L");
+            write!(out, "ORA   L")?;
         }
         0xb6 => {
             // A <- A | (HL)
-            println!("ORA   M");
+            write!(out, "ORA   M")?;
         }
         0xb7 => {
             // A <- A | A
-            println!("ORA   A");
+            write!(out, "ORA   A")?;
         }
         0xb8 => {
             // A - B
-            println!("CMP   B");
+            write!(out, "CMP   B")?;
         }
         0xb9 => {
             // A - C
-            println!("CMP   C");
+            write!(out, "CMP   C")?;
         }
         0xba => {
             // A - D
-            println!("CMP   D");
+            write!(out, "CMP   D")?;
         }
         0xbb => {
             // A - E
-            println!("CMP   E");
+            write!(out, "CMP   E")?;
         }
         0xbc => {
             // A - H
-            println!("CMP   H");
+            write!(out, "CMP   H")?;
         }
         0xbd => {
             // A - L
-            println!("CMP   L");
+            write!(out, "CMP   L")?;
         }
         0xbe => {
             // A - (HL)
-            println!("CMP   M");
+            write!(out, "CMP   M")?;
         }
         0xbf => {
             // A - A
-            println!("CMP   A");
+            write!(out, "CMP   A")?;
         }
         0xc0 => {
             // if NZ, RET
-            println!("RNZ");
+            write!(out, "RNZ")?;
         }
         0xc1 => {
             // C <- (sp); B <- (sp+1); sp <- sp+2
-            println!("POP   B");
+            write!(out, "POP   B")?;
         }
         0xc2 => {
             // if NZ, PC <- adr
-            println!("JNZ   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JNZ   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xc3 => {
             // PC <- adr
-            println!("JMP   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JMP   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xc4 => {
             // if NZ, CALL adr
-            println!("CNZ   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CNZ   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xc5 => {
             // (sp-2) <- C; (sp-1) <- B; sp <- sp-2
-            println!("PUSH  B");
+            write!(out, "PUSH  B")?;
         }
         0xc6 => {
             // A <- A + byte
-            println!("ADI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "ADI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xc7 => {
             // CALL $0
-            println!("RST   0");
+            write!(out, "RST   0")?;
         }
         0xc8 => {
             // if Z, RET
-            println!("RZ");
+            write!(out, "RZ")?;
         }
         0xc9 => {
             // PC.lo <- (sp); PC.hi <- (sp+1); sp <- sp+2
-            println!("RET");
+            write!(out, "RET")?;
         }
         0xca => {
             // iF Z, PC <- adr
-            println!("JZ    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JZ    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xcc => {
             // if Z, CALL adr
-            println!("CZ    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CZ    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xcd => {
             // (sp-1) <- PC.hi; (sp-2) <- PC.lo; sp <- sp-2; PC <- adr
-            println!("CALL  {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CALL  {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xce => {
             // A <- A + byte2 + CY
-            println!("ACI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "ACI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xcf => {
             // CALL $8
-            println!("RST   1");
+            write!(out, "RST   1")?;
         }
         0xd0 => {
             // if NCY, RET
-            println!("RNC");
+            write!(out, "RNC")?;
         }
         0xd1 => {
             // E <- (sp); D <- (sp+1); sp <- sp+2
-            println!("POP   D");
+            write!(out, "POP   D")?;
         }
         0xd2 => {
             // if NCY, PC <- adr
-            println!("JNC   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JNC   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xd3 => {
             // special
-            println!("OUT   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "OUT   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xd4 => {
             // if NCY, CALL adr
-            println!("CNC   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CNC   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xd5 => {
             // (sp-2) <- E; (sp-1) <- D; sp <- sp-2
-            println!("PUSH  D");
+            write!(out, "PUSH  D")?;
         }
         0xd6 => {
             // A < A - byte2
-            println!("SUI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "SUI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xd7 => {
             // CALL $10
-            println!("RST   2")
+            write!(out, "RST   2")?;
         }
         0xd8 => {
             // if CY, RET
-            println!("RC");
+            write!(out, "RC")?;
         }
         0xda => {
             // if CY, PC <- adr
-            println!("JC    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JC    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xdb => {
             // special
-            println!("IN    #{}", b2s!(buffer[pc + 1]));
+            write!(out, "IN    #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xdc => {
             // if CY, CALL adr
-            println!("CC    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CC    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xde => {
             // A <- A - data - CY
-            println!("SBI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "SBI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xdf => {
             // CALL $18
-            println!("RST   3");
+            write!(out, "RST   3")?;
         }
         0xe0 => {
             // if PO, RET
-            println!("RPO");
+            write!(out, "RPO")?;
         }
         0xe1 => {
             // L <- (sp); H <- (sp+1); sp <- sp+2
-            println!("POP   H");
+            write!(out, "POP   H")?;
         }
         0xe2 => {
             // if PO, PC <- adr
-            println!("JPO   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JPO   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xe3 => {
             // L <-> (sp); H <-> (sp+1)
-            println!("XTHL");
+            write!(out, "XTHL")?;
         }
         0xe4 => {
             // if PO, CALL adr
-            println!("CPO   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CPO   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xe5 => {
             // (sp-2) <- L; (sp-1) <- H; sp <- sp-2
-            println!("PUSH  H");
+            write!(out, "PUSH  H")?;
         }
         0xe6 => {
             // A <- A & byte2
-            println!("ANI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "ANI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xe7 => {
             // CALL $20
-            println!("RST   4");
+            write!(out, "RST   4")?;
         }
         0xe8 => {
             // if PE, RET
-            println!("RPE");
+            write!(out, "RPE")?;
         }
         0xe9 => {
             // PC.hi <- H; PC.lo <- L
-            println!("PCHL");
+            write!(out, "PCHL")?;
         }
         0xea => {
             // if PE, PC <- adr
-            println!("JPE   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JPE   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xeb => {
             // H <-> D; L <-> E
-            println!("XCHG");
+            write!(out, "XCHG")?;
         }
         0xec => {
             // if PE, CALL adr
-            println!("CPE   {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CPE   {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xee => {
             // A <- A ^ byte2
-            println!("XRI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "XRI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xef => {
             // CALL $28
-            println!("RST   5");
+            write!(out, "RST   5")?;
         }
         0xf0 => {
             // if P, RET
-            println!("RP");
+            write!(out, "RP")?;
         }
         0xf1 => {
             // flags <- (sp); A <- (sp+1); sp <- sp+2
-            println!("POP   PSW");
+            write!(out, "POP   PSW")?;
         }
         0xf2 => {
             // if P=1, PC <- adr
-            println!("JP    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JP    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xf3 => {
             // special
-            println!("DI");
+            write!(out, "DI")?;
         }
         0xf4 => {
             // if P, CALL adr
-            println!("CP    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CP    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xf5 => {
             // (ps-2) <- flags; (sp-1) <- A; sp <- sp-2
-            println!("PUSH  PSW");
+            write!(out, "PUSH  PSW")?;
         }
         0xf6 => {
             // A <- A | byte2
-            println!("ORI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "ORI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xf7 => {
             // CALL $30
-            println!("RST   6");
+            write!(out, "RST   6")?;
         }
         0xf8 => {
             // if M, RET
-            println!("RM");
+            write!(out, "RM")?;
         }
         0xf9 => {
             // SP <- HL
-            println!("SPHL");
+            write!(out, "SPHL")?;
         }
         0xfa => {
             // if M, PC <- adr
-            println!("JM    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "JM    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3;
         }
         0xfb => {
             // special
-            println!("EI");
+            write!(out, "EI")?;
         }
         0xfc => {
             // if M, CALL adr
-            println!("CM    {}", b2s!(buffer[pc + 2], buffer[pc + 1]));
+            write!(out, "CM    {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
             op_bytes = 3
         }
         0xfe => {
             // A - data
-            println!("CPI   #{}", b2s!(buffer[pc + 1]));
+            write!(out, "CPI   #{}", b2s!(buffer[pc + 1]))?;
             op_bytes = 2;
         }
         0xff => {
             // CALL $38
-            println!("RST   7");
+            write!(out, "RST   7")?;
+        }
+        // undocumented opcodes, which alias documented instructions
+        0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
+            write!(out, "*NOP")?;
+        }
+        0xcb => {
+            write!(out, "*JMP  {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
+            op_bytes = 3;
         }
-        _ => {
-            println!("NOP");
+        0xd9 => {
+            write!(out, "*RET")?;
+        }
+        0xdd | 0xed | 0xfd => {
+            write!(out, "*CALL {}", b2s!(buffer[pc + 2], buffer[pc + 1]))?;
+            op_bytes = 3;
         }
     }
 
-    op_bytes
+    Ok(op_bytes)
+}
+
+/// Disassembles all of `buffer`, printing one instruction per line.
+pub fn disassemble(buffer: &[u8]) {
+    for (pc, text) in listing(buffer) {
+        println!("{:#04x}:    {}", pc, text);
+    }
+}
+
+/// Bytes that differ closer than this are shown in the same hunk.
+const DIFF_GAP: usize = 16;
+
+/// Disassembles `buffer` into (address, text) lines.
+fn listing(buffer: &[u8]) -> Vec<(usize, String)> {
+    // an instruction cut off by the end of the buffer reads zeros
+    let mut padded = buffer.to_vec();
+    padded.extend_from_slice(&[0, 0]);
+    let mut lines = Vec::new();
+    let mut pc = 0;
+    while pc < buffer.len() {
+        let (text, op_bytes) = instruction_text(&padded, pc);
+        lines.push((pc, text));
+        pc += op_bytes as usize;
+    }
+    lines
+}
+
+/// Prints the instructions around the bytes that differ between
+/// `original` and `patched`, like a unified diff: a `@@` line with the
+/// range of every hunk, then its instructions in `original` prefixed with
+/// `-` and in `patched` with `+`.
+///
+/// Both are disassembled from address 0, so an instruction changed in
+/// length shows up as different instructions after it. Returns the number
+/// of hunks.
+pub fn disassemble_diff(original: &[u8], patched: &[u8]) -> usize {
+    let length = original.len().max(patched.len());
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for address in 0..length {
+        if original.get(address) == patched.get(address) {
+            continue;
+        }
+        match hunks.last_mut() {
+            Some((_, end)) if address < *end + DIFF_GAP => *end = address + 1,
+            _ => hunks.push((address, address + 1)),
+        }
+    }
+
+    let (original_lines, patched_lines) = (listing(original), listing(patched));
+    // the instructions that overlap the bytes from `start` to `end`
+    let overlapping = |lines: &[(usize, String)], start: usize, end: usize| {
+        let first = lines
+            .partition_point(|(pc, _)| *pc <= start)
+            .saturating_sub(1);
+        lines[first..]
+            .iter()
+            .take_while(|(pc, _)| *pc < end)
+            .map(|(pc, text)| format!("{:#04x}:    {}", pc, text))
+            .collect::<Vec<_>>()
+    };
+    for &(start, end) in &hunks {
+        println!("@@ {:#06x}..{:#06x} @@", start, end);
+        for line in overlapping(&original_lines, start, end) {
+            println!("-{}", line);
+        }
+        for line in overlapping(&patched_lines, start, end) {
+            println!("+{}", line);
+        }
+    }
+    hunks.len()
 }
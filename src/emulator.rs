@@ -1,17 +1,362 @@
+use std::cell::Cell;
+use std::fmt;
+
+use crate::snapshot::{SaveState, StateError};
+
+/// Size of the 8080 address space.
+pub const MEMORY_SIZE: usize = 0x10000;
+
+/// Number of clock cycles taken by each opcode.
+///
+/// Conditional calls and returns are listed with the cycles for when the
+/// branch is not taken, `BRANCH_TAKEN_CYCLES` is added when it is.
+#[rustfmt::skip]
+const CYCLES: [u8; 256] = [
+    4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, // 0x00
+    4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4, // 0x10
+    4, 10, 16, 5, 5, 5, 7, 4, 4, 10, 16, 5, 5, 5, 7, 4, // 0x20
+    4, 10, 13, 5, 10, 10, 10, 4, 4, 10, 13, 5, 5, 5, 7, 4, // 0x30
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x40
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x50
+    5, 5, 5, 5, 5, 5, 7, 5, 5, 5, 5, 5, 5, 5, 7, 5, // 0x60
+    7, 7, 7, 7, 7, 7, 7, 7, 5, 5, 5, 5, 5, 5, 7, 5, // 0x70
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x80
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x90
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0xa0
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0xb0
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, // 0xc0
+    5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11, // 0xd0
+    5, 10, 10, 18, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11, // 0xe0
+    5, 10, 10, 4, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11, // 0xf0
+];
+
+/// Number of clock cycles taken by each opcode on the 8085, like `CYCLES`.
+///
+/// Conditional jumps are listed with the cycles for when they are not
+/// taken too.
+#[rustfmt::skip]
+const CYCLES_8085: [u8; 256] = [
+    4, 10, 7, 6, 4, 4, 7, 4, 10, 10, 7, 6, 4, 4, 7, 4, // 0x00
+    7, 10, 7, 6, 4, 4, 7, 4, 10, 10, 7, 6, 4, 4, 7, 4, // 0x10
+    4, 10, 16, 6, 4, 4, 7, 4, 10, 10, 16, 6, 4, 4, 7, 4, // 0x20
+    4, 10, 13, 6, 10, 10, 10, 4, 10, 10, 13, 6, 4, 4, 7, 4, // 0x30
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x40
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x50
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x60
+    7, 7, 7, 7, 7, 7, 5, 7, 4, 4, 4, 4, 4, 4, 7, 4, // 0x70
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x80
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0x90
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0xa0
+    4, 4, 4, 4, 4, 4, 7, 4, 4, 4, 4, 4, 4, 4, 7, 4, // 0xb0
+    6, 10, 7, 10, 9, 12, 7, 12, 6, 10, 7, 6, 9, 18, 7, 12, // 0xc0
+    6, 10, 7, 10, 9, 12, 7, 12, 6, 10, 7, 10, 9, 7, 7, 12, // 0xd0
+    6, 10, 7, 16, 9, 12, 7, 12, 6, 6, 7, 4, 9, 10, 7, 12, // 0xe0
+    6, 10, 7, 4, 9, 12, 7, 12, 6, 6, 7, 4, 9, 7, 7, 12, // 0xf0
+];
+
+// 8085 interrupt inputs, in the bit order of the RIM and SIM masks
+const RST55: u8 = 0x01;
+const RST65: u8 = 0x02;
+const RST75: u8 = 0x04;
+const TRAP: u8 = 0x08;
+
+/// The processor `Emulator8080` emulates, selected at construction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuVariant {
+    Intel8080,
+    /// The 8085: different cycle counts, the V and K flags, RIM and SIM,
+    /// the RST 5.5, 6.5, 7.5 and TRAP inputs, serial I/O pins and ten
+    /// undocumented instructions in place of the 8080's aliases.
+    Intel8085,
+}
+
+impl CpuVariant {
+    /// Whether `code` is an undocumented opcode of this processor.
+    ///
+    /// On the 8080 there are 12, which alias documented instructions:
+    /// 0x08, 0x10, 0x18, 0x20, 0x28, 0x30 and 0x38 act as NOP, 0xcb as JMP,
+    /// 0xd9 as RET and 0xdd, 0xed and 0xfd as CALL. The 8085 uses 0x20 and
+    /// 0x30 for RIM and SIM and the other 10 for DSUB, ARHL, RDEL, LDHI,
+    /// LDSI, RSTV, SHLX, JNK, LHLX and JK.
+    pub fn is_undocumented(self, code: u8) -> bool {
+        match self {
+            CpuVariant::Intel8080 => matches!(
+                code,
+                0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 | 0xcb | 0xd9 | 0xdd | 0xed | 0xfd
+            ),
+            CpuVariant::Intel8085 => matches!(
+                code,
+                0x08 | 0x10 | 0x18 | 0x28 | 0x38 | 0xcb | 0xd9 | 0xdd | 0xed | 0xfd
+            ),
+        }
+    }
+
+    /// Cycles every opcode takes, without a taken branch.
+    fn cycle_table(self) -> &'static [u8; 256] {
+        match self {
+            CpuVariant::Intel8080 => &CYCLES,
+            CpuVariant::Intel8085 => &CYCLES_8085,
+        }
+    }
+
+    /// Extra cycles the conditional instruction `code` takes when the
+    /// branch is taken.
+    fn branch_taken_cycles(self, code: u8) -> u32 {
+        match (self, code & 0xc7) {
+            // returns and calls
+            (CpuVariant::Intel8080, 0xc0 | 0xc4) => 6,
+            (CpuVariant::Intel8080, _) => 0,
+            // returns and RSTV
+            (CpuVariant::Intel8085, 0xc0 | 0xc3) => 6,
+            (CpuVariant::Intel8085, 0xc4) => 9,
+            // jumps, JNK and JK
+            (CpuVariant::Intel8085, _) => 3,
+        }
+    }
+}
+
+/// The extra interrupt inputs of the 8085, by increasing priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptLine {
+    /// Level triggered, maskable, vector 0x2c.
+    Rst55,
+    /// Level triggered, maskable, vector 0x34.
+    Rst65,
+    /// Edge triggered, maskable, vector 0x3c.
+    Rst75,
+    /// Edge triggered, not maskable, taken even with interrupts disabled,
+    /// vector 0x24.
+    Trap,
+}
+
+impl InterruptLine {
+    fn bit(self) -> u8 {
+        match self {
+            InterruptLine::Rst55 => RST55,
+            InterruptLine::Rst65 => RST65,
+            InterruptLine::Rst75 => RST75,
+            InterruptLine::Trap => TRAP,
+        }
+    }
+}
+
+/// State of the 8085 interrupt and serial pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pins8085 {
+    /// RST 5.5, 6.5 and 7.5 masks set by SIM.
+    masks: u8,
+    /// Levels of the interrupt inputs.
+    lines: u8,
+    /// The RST 7.5 flip-flop, set on a rising edge.
+    rst75_pending: bool,
+    /// Set on a rising edge of TRAP.
+    trap_pending: bool,
+    /// Interrupt enable before the last TRAP, returned by the next RIM.
+    enable_before_trap: Option<bool>,
+    /// SID, read by RIM.
+    serial_in: bool,
+    /// SOD, written by SIM.
+    serial_out: bool,
+}
+
+impl Pins8085 {
+    /// The state after RESET: RST 5.5, 6.5 and 7.5 masked.
+    fn new() -> Self {
+        Self {
+            masks: RST55 | RST65 | RST75,
+            lines: 0,
+            rst75_pending: false,
+            trap_pending: false,
+            enable_before_trap: None,
+            serial_in: false,
+            serial_out: false,
+        }
+    }
+
+    /// Whether any interrupt input is requesting, masked or not.
+    fn requested(&self) -> bool {
+        self.trap_pending || self.rst75_pending || self.lines & (RST55 | RST65) != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum RegPair {
     BC,
     DE,
     HL,
     SP,
 }
+
+/// The 8 bit registers, in the order they are encoded in opcodes.
+///
+/// `M` is the memory location pointed to by (HL).
+#[derive(Debug, Clone, Copy)]
+pub enum Reg {
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    M,
+    A,
+}
+
+impl Reg {
+    /// Decodes the 3 bit register field of an opcode.
+    pub fn from_code(code: u8) -> Self {
+        match code & 0x7 {
+            0 => Reg::B,
+            1 => Reg::C,
+            2 => Reg::D,
+            3 => Reg::E,
+            4 => Reg::H,
+            5 => Reg::L,
+            6 => Reg::M,
+            _ => Reg::A,
+        }
+    }
+}
+
+/// What `Emulator8080::step` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// An instruction ran and took `cycles`.
+    Executed { cycles: u32 },
+    /// The CPU is halted waiting for an interrupt; the time of a NOP
+    /// passed.
+    Halted { cycles: u32 },
+    /// PC is at a breakpoint. Nothing ran; the next step runs the
+    /// instruction.
+    Breakpoint { address: u16 },
+    /// An 8085 interrupt input was acknowledged: PC was pushed and set to
+    /// `vector`.
+    Interrupt { vector: u16, cycles: u32 },
+}
+
+/// Size of the pages of a `MemoryMap`.
+pub const PAGE_SIZE: usize = 0x400;
+
+/// What the bus answers at the addresses of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Ram,
+    /// Reads as usual, writes are dropped and are bus faults.
+    Rom,
+    /// Nothing answers: reads return 0xff, writes are dropped, and both
+    /// are bus faults.
+    Unmapped,
+}
+
+/// What the bus has at every address, by `PAGE_SIZE` pages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryMap {
+    pages: [Page; MEMORY_SIZE / PAGE_SIZE],
+}
+
+impl MemoryMap {
+    /// RAM over the whole address space, as for a bare 8080.
+    pub fn ram() -> Self {
+        Self {
+            pages: [Page::Ram; MEMORY_SIZE / PAGE_SIZE],
+        }
+    }
+
+    /// Nothing at any address, to map the regions of a board on.
+    pub fn unmapped() -> Self {
+        Self {
+            pages: [Page::Unmapped; MEMORY_SIZE / PAGE_SIZE],
+        }
+    }
+
+    /// Puts `page` at the `size` bytes from `address`, which must both be
+    /// multiples of `PAGE_SIZE`.
+    pub fn map(&mut self, address: u16, size: usize, page: Page) {
+        let start = address as usize;
+        assert!(
+            start.is_multiple_of(PAGE_SIZE) && size.is_multiple_of(PAGE_SIZE),
+            "regions must be made of whole pages"
+        );
+        self.pages[start / PAGE_SIZE..(start + size) / PAGE_SIZE].fill(page);
+    }
+
+    /// What is at `address`.
+    pub fn page(&self, address: u16) -> Page {
+        self.pages[address as usize / PAGE_SIZE]
+    }
+}
+
+/// Kind of the memory access that caused a bus fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Error returned by `Emulator8080::step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+    /// The opcode at `address` is undocumented and the CPU is strict.
+    /// Nothing ran and PC still points at it.
+    UndocumentedOpcode { address: u16, opcode: u8 },
+    /// The instruction at `pc` accessed `address`, where the memory map
+    /// has nothing or ROM, or which is past the end of the memory. The
+    /// instruction ran and took `cycles`, with reads there returning 0xff
+    /// and writes there dropped (reads of ROM work).
+    BusFault {
+        pc: u16,
+        address: u16,
+        access: Access,
+        cycles: u32,
+    },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuError::UndocumentedOpcode { address, opcode } => {
+                write!(f, "undocumented opcode {:02x} at {:04x}", opcode, address)
+            }
+            CpuError::BusFault {
+                pc,
+                address,
+                access,
+                ..
+            } => write!(
+                f,
+                "bus fault: instruction at {:04x} {}",
+                pc,
+                match access {
+                    Access::Read => format!("read {:04x}, where nothing is mapped", address),
+                    Access::Write => format!("wrote {:04x}, which is ROM or not mapped", address),
+                }
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+/// The devices connected to the IN and OUT instructions.
+pub trait IoDevices {
+    /// Returns the value read by `IN port`.
+    fn input(&mut self, port: u8) -> u8;
+    /// Handles `OUT port` with the content of the accumulator.
+    fn output(&mut self, port: u8, value: u8);
+}
+
 /// Struct containing the state for the 8080 condition flags.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct Flags8080 {
     z: bool,
     s: bool,
     p: bool,
     ac: bool,
     cy: bool,
+    /// Two's complement overflow, only visible on the 8085.
+    v: bool,
+    /// The 8085's undocumented K (or X5) flag.
+    k: bool,
 }
 
 impl Flags8080 {
@@ -22,12 +367,48 @@ impl Flags8080 {
             p: false,
             ac: false,
             cy: false,
+            v: false,
+            k: false,
+        }
+    }
+
+    /// Packs the flags into the PSW byte layout: `S Z 0 AC 0 P 1 CY`.
+    pub fn to_psw(&self) -> u8 {
+        (self.s as u8) << 7
+            | (self.z as u8) << 6
+            | (self.ac as u8) << 4
+            | (self.p as u8) << 2
+            | 0x02
+            | self.cy as u8
+    }
+
+    /// Packs the flags into the 8085 PSW byte layout: `S Z K AC 0 P V CY`.
+    pub fn to_psw_8085(&self) -> u8 {
+        (self.s as u8) << 7
+            | (self.z as u8) << 6
+            | (self.k as u8) << 5
+            | (self.ac as u8) << 4
+            | (self.p as u8) << 2
+            | (self.v as u8) << 1
+            | self.cy as u8
+    }
+
+    /// Unpacks a PSW byte into the condition flags.
+    pub fn from_psw(psw: u8) -> Self {
+        Self {
+            z: psw & 0x40 != 0,
+            s: psw & 0x80 != 0,
+            p: psw & 0x04 != 0,
+            ac: psw & 0x10 != 0,
+            cy: psw & 0x01 != 0,
+            v: psw & 0x02 != 0,
+            k: psw & 0x20 != 0,
         }
     }
 }
 
 /// Struct containing state for the 8080 processor.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Emulator8080 {
     // Special registers
     /// Program Counter
@@ -54,15 +435,44 @@ pub struct Emulator8080 {
     // condition flags
     pub flags: Flags8080,
 
-    // memory
+    /// The memory, at most `MEMORY_SIZE` bytes. Addresses past its end
+    /// read 0xff, ignore writes and make `step` report a bus fault.
     pub memory: Vec<u8>,
+    /// Where the bus has RAM, ROM or nothing, all RAM by default. Not
+    /// part of the save state.
+    pub memory_map: MemoryMap,
+    /// First access past the end of the memory since `step` started.
+    bus_fault: Cell<Option<(u16, Access)>>,
 
     // enable
     enable: bool,
+
+    /// Set by HLT, cleared by the next interrupt.
+    pub halted: bool,
+    /// Makes `step` reject undocumented opcodes instead of running the
+    /// instructions they alias.
+    pub strict: bool,
+
+    /// Addresses `step` stops at.
+    breakpoints: Vec<u16>,
+    /// The breakpoint `step` last stopped at, which the next step runs.
+    stopped_at: Option<u16>,
+
+    variant: CpuVariant,
+    /// `variant.cycle_table()`, kept at hand for speed.
+    cycles: &'static [u8; 256],
+    /// Unused on the 8080.
+    pins: Pins8085,
 }
 
 impl Emulator8080 {
+    /// An 8080 with cleared registers and memory.
     pub fn empty() -> Self {
+        Emulator8080::new(CpuVariant::Intel8080)
+    }
+
+    /// A `variant` processor with cleared registers and memory.
+    pub fn new(variant: CpuVariant) -> Self {
         Self {
             pc: 0,
             sp: 0,
@@ -74,32 +484,91 @@ impl Emulator8080 {
             rl: 0,
             rh: 0,
             flags: Flags8080::new(),
-            memory: vec![0; u16::MAX as usize],
-            enable: true,
+            memory: vec![0; MEMORY_SIZE],
+            memory_map: MemoryMap::ram(),
+            bus_fault: Cell::new(None),
+            enable: false,
+            halted: false,
+            strict: false,
+            breakpoints: Vec::new(),
+            stopped_at: None,
+            variant,
+            cycles: variant.cycle_table(),
+            pins: Pins8085::new(),
         }
     }
-    /* Helper functions */
 
-    /// Decides if the operation results in a auxiliary carry.
+    /// Resets the processor like the RESET pin does.
+    ///
+    /// The program counter is cleared and interrupts are disabled, the
+    /// other registers and the memory are left as they are.
     ///
-    /// TODO: implement
-    pub fn aux_carry(_: u8) -> bool {
-        false
+    /// On the 8085 RST 5.5, 6.5 and 7.5 are masked again and SOD is
+    /// cleared.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.enable = false;
+        self.halted = false;
+        self.pins = Pins8085 {
+            lines: self.pins.lines,
+            serial_in: self.pins.serial_in,
+            ..Pins8085::new()
+        };
+    }
+
+    pub fn variant(&self) -> CpuVariant {
+        self.variant
+    }
+
+    /// The PSW as PUSH PSW stores it, which depends on the variant.
+    pub fn psw(&self) -> u8 {
+        match self.variant {
+            CpuVariant::Intel8080 => self.flags.to_psw(),
+            CpuVariant::Intel8085 => self.flags.to_psw_8085(),
+        }
+    }
+
+    /// Makes `step` stop before running the instruction at `address`.
+    pub fn set_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    /// Removes the breakpoint at `address`, returning whether there was one.
+    pub fn clear_breakpoint(&mut self, address: u16) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|&breakpoint| breakpoint != address);
+        self.breakpoints.len() != count
+    }
+
+    /// The addresses with a breakpoint, in the order they were set.
+    pub fn breakpoints(&self) -> &[u16] {
+        &self.breakpoints
+    }
+
+    /// Returns true if interrupts are enabled (EI).
+    pub fn interrupts_enabled(&self) -> bool {
+        self.enable
+    }
+
+    /* Helper functions */
+
+    /// Decides if adding `lhs`, `rhs` and `carry` carries out of bit 3.
+    pub fn aux_carry(lhs: u8, rhs: u8, carry: bool) -> bool {
+        (lhs & 0x0f) + (rhs & 0x0f) + carry as u8 > 0x0f
     }
 
     /// Determines whether the value (which is a result of a arithmetic operation)
-    /// has carried/borrowed into/from bit 8.
+    /// has carried into bit 8.
     ///
     /// Example:
     /// ```rust
-    /// use emulator::Emulator8080
-    /// Emulator8080::carry(0x14f) // returns true
+    /// use space_invaders_emulator::emulator::Emulator8080;
+    /// assert!(Emulator8080::carry(0x14f));
     /// ```
-    pub fn carry(value: u8, compare_to: u8, subtraction: bool) -> bool {
-        if subtraction {
-            return value > compare_to;
-        }
-        value < compare_to
+    pub fn carry(value: u16) -> bool {
+        value > 0xff
     }
     /// The parity method performs a parity check of the value.
     ///
@@ -108,8 +577,8 @@ impl Emulator8080 {
     ///
     /// Example:
     /// ```rust
-    /// use emulator::Emulator8080
-    /// Emulator8080::parity(0x02) // returns false
+    /// use space_invaders_emulator::emulator::Emulator8080;
+    /// assert!(!Emulator8080::parity(0x02));
     /// ```
     pub fn parity(mut value: u8) -> bool {
         value ^= value >> 4;
@@ -128,49 +597,113 @@ impl Emulator8080 {
 
     /// The zero method decides if `value` is zero
     pub fn zero(value: u8) -> bool {
-        (value & 0xff) == 0
+        value == 0
     }
 
-    /// Sets the flags specified by the `set_*` parameters.
-    ///
-    /// This method is meant to be used in arithmetic operation.
-    fn set_flags(
-        &mut self,
-        result: u8,
-        compare_to: u8,
-        subtraction: bool,
-        set_ac: bool,
-        set_cy: bool,
-        set_p: bool,
-        set_s: bool,
-        set_z: bool,
-    ) {
-        // carries, requires u16
-        if set_ac {
-            self.flags.ac = Emulator8080::aux_carry(result);
-        }
-        if set_cy {
-            self.flags.cy = Emulator8080::carry(result, compare_to, subtraction);
-        }
-        // requires u8
-        if set_p {
-            self.flags.p = Emulator8080::parity(result);
+    /// Sets the Z, S and P flags from `result`.
+    fn set_zsp(&mut self, result: u8) {
+        self.flags.z = Emulator8080::zero(result);
+        self.flags.s = Emulator8080::sign(result);
+        self.flags.p = Emulator8080::parity(result);
+    }
+
+    /// Reads the byte at `address`, 0xff where nothing is mapped or past
+    /// the end of the memory.
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match (
+            self.memory_map.page(address),
+            self.memory.get(address as usize),
+        ) {
+            (Page::Ram | Page::Rom, Some(&value)) => value,
+            _ => {
+                self.record_bus_fault(address, Access::Read);
+                0xff
+            }
         }
-        if set_s {
-            self.flags.s = Emulator8080::sign(result);
+    }
+
+    /// Writes `value` to `address`, if there is RAM there.
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        match (
+            self.memory_map.page(address),
+            self.memory.get_mut(address as usize),
+        ) {
+            (Page::Ram, Some(byte)) => *byte = value,
+            _ => self.record_bus_fault(address, Access::Write),
         }
-        if set_z {
-            self.flags.z = Emulator8080::zero(result);
+    }
+
+    /// Keeps the first bus fault of an instruction for `step`.
+    fn record_bus_fault(&self, address: u16, access: Access) {
+        if self.bus_fault.get().is_none() {
+            self.bus_fault.set(Some((address, access)));
         }
     }
 
+    /// Reads the little endian word at `address`.
+    pub fn read_word(&self, address: u16) -> u16 {
+        u16::from_le_bytes([
+            self.read_byte(address),
+            self.read_byte(address.wrapping_add(1)),
+        ])
+    }
+
+    /// Writes `value` as a little endian word to `address`.
+    pub fn write_word(&mut self, address: u16, value: u16) {
+        let [lo, hi] = value.to_le_bytes();
+        self.write_byte(address, lo);
+        self.write_byte(address.wrapping_add(1), hi);
+    }
+
+    /// Fetches the byte at PC and advances PC.
+    fn next_byte(&mut self) -> u8 {
+        let value = self.read_byte(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+
+    /// Fetches the word at PC and advances PC.
+    fn next_word(&mut self) -> u16 {
+        let value = self.read_word(self.pc);
+        self.pc = self.pc.wrapping_add(2);
+        value
+    }
+
     /// Fetches the address that is in H and L.
     fn get_address(&self) -> u16 {
         ((self.rh as u16) << 8) | (self.rl as u16)
     }
 
+    /// Fetches the content of `reg`.
+    pub fn get_reg(&self, reg: Reg) -> u8 {
+        match reg {
+            Reg::B => self.rb,
+            Reg::C => self.rc,
+            Reg::D => self.rd,
+            Reg::E => self.re,
+            Reg::H => self.rh,
+            Reg::L => self.rl,
+            Reg::M => self.read_byte(self.get_address()),
+            Reg::A => self.ra,
+        }
+    }
+
+    /// Sets `reg` to `value`.
+    pub fn set_reg(&mut self, reg: Reg, value: u8) {
+        match reg {
+            Reg::B => self.rb = value,
+            Reg::C => self.rc = value,
+            Reg::D => self.rd = value,
+            Reg::E => self.re = value,
+            Reg::H => self.rh = value,
+            Reg::L => self.rl = value,
+            Reg::M => self.write_byte(self.get_address(), value),
+            Reg::A => self.ra = value,
+        }
+    }
+
     /// fetches the content of the given `reg_pair`
-    fn get_reg_pair(&self, reg_pair: RegPair) -> u16 {
+    pub fn get_reg_pair(&self, reg_pair: RegPair) -> u16 {
         match reg_pair {
             RegPair::BC => ((self.rb as u16) << 8) | (self.rc as u16),
             RegPair::DE => ((self.rd as u16) << 8) | (self.re as u16),
@@ -180,7 +713,7 @@ impl Emulator8080 {
     }
 
     /// Sets `reg_pair` to `value`
-    fn set_reg_pair(&mut self, value: u16, reg_pair: RegPair) {
+    pub fn set_reg_pair(&mut self, value: u16, reg_pair: RegPair) {
         let value_tuple = ((value >> 8) as u8, (value & 0xff) as u8);
         match reg_pair {
             RegPair::BC => {
@@ -201,14 +734,46 @@ impl Emulator8080 {
         }
     }
 
+    /// Decodes the register pair field (bits 4 and 5) of an opcode.
+    fn reg_pair_from_code(code: u8) -> RegPair {
+        match (code >> 4) & 0x3 {
+            0 => RegPair::BC,
+            1 => RegPair::DE,
+            2 => RegPair::HL,
+            _ => RegPair::SP,
+        }
+    }
+
     /* Arithmetic group */
+    /// Adds `rhs` and `carry` to `lhs`.
+    ///
+    /// This method affects Z, S, P, CY and AC.
+    fn add_with_carry(&mut self, lhs: u8, rhs: u8, carry: bool) -> u8 {
+        let result = lhs as u16 + rhs as u16 + carry as u16;
+
+        self.flags.cy = Emulator8080::carry(result);
+        self.flags.ac = Emulator8080::aux_carry(lhs, rhs, carry);
+        self.set_zsp(result as u8);
+        self.set_vk(lhs, rhs, result as u8);
+        result as u8
+    }
+
+    /// Sets the 8085 V and K flags for the addition of `lhs` and `rhs`.
+    ///
+    /// K follows the description by Dehnhardt and Sorensen: set when
+    /// both operands are negative, or either is and the result is not.
+    fn set_vk(&mut self, lhs: u8, rhs: u8, result: u8) {
+        self.flags.v = (lhs ^ result) & (rhs ^ result) & 0x80 != 0;
+        self.flags.k = (lhs & rhs | lhs & !result | rhs & !result) & 0x80 != 0;
+    }
+
     /// Adds rhs to lhs.
     ///
     /// If `with_carry` is set, it also adds the content of the
     /// cy flag.
     fn add(&mut self, lhs: u8, rhs: u8, with_carry: bool) -> u8 {
-        lhs.wrapping_add(rhs)
-            .wrapping_add(if with_carry { self.flags.cy as u8 } else { 0 })
+        let carry = with_carry && self.flags.cy;
+        self.add_with_carry(lhs, rhs, carry)
     }
 
     /// Adds the content of `value` to the accumulator register (`ra`).
@@ -219,120 +784,73 @@ impl Emulator8080 {
     /// The function also sets flags affected. This method affects
     /// Z, S, P, CY and AC.
     pub fn add_a(&mut self, value: u8, with_carry: bool) {
-        let result = self.add(self.ra, value, with_carry);
-
-        self.set_flags(result, self.ra, false, true, true, true, true, true);
-        self.ra = result;
-    }
-
-    /// Adds the content of the memory location specified by (HL).
-    ///
-    /// The method affects Z, S, P, CY and AC
-    pub fn add_mem(&mut self, with_carry: bool) {
-        let address = self.get_address() as usize;
-        self.add_a(self.memory[address], with_carry);
+        self.ra = self.add(self.ra, value, with_carry);
     }
 
     /// Subtracts rhs from lhs.
     ///
     /// If with_borrow is specified, it also subtracts the content
-    /// of the cy flag.
+    /// of the cy flag. Like the real chip, this adds the two's complement
+    /// of `rhs`, so AC is the carry out of bit 3 of that addition and
+    /// CY is set on borrow.
     fn sub(&mut self, lhs: u8, rhs: u8, with_borrow: bool) -> u8 {
-        lhs.wrapping_sub(rhs)
-            .wrapping_sub(if with_borrow { self.flags.cy as u8 } else { 0 })
+        let borrow = with_borrow && self.flags.cy;
+        let result = self.add_with_carry(lhs, !rhs, !borrow);
+
+        self.flags.cy = !self.flags.cy;
+        result
     }
 
     /// Subtracts value from register a.
     ///
     /// This method affects the flags Z, S, P, CY and AC.
     pub fn sub_a(&mut self, value: u8, with_borrow: bool) {
-        let result = self.sub(self.ra, value, with_borrow);
-
-        self.set_flags(result, self.ra, true, true, true, true, true, true);
-        self.ra = result;
-    }
-
-    /// Subtracts the content pointed to by (HL) from the accumulator.
-    pub fn sub_mem(&mut self, with_borrow: bool) {
-        let address = self.get_address() as usize;
-        self.sub_a(self.memory[address], with_borrow);
+        self.ra = self.sub(self.ra, value, with_borrow);
     }
 
     /// Increment the value of `reg`.
     ///
     /// This method affects Z, S, P and AC.
-    pub fn inc_reg(&mut self, reg: u8) {
-        let result = self.add(reg, 1, false);
+    pub fn inc_reg(&mut self, reg: Reg) {
+        let value = self.get_reg(reg);
+        let result = value.wrapping_add(1);
 
-        self.set_flags(result, reg, false, true, false, true, true, true);
-    }
-
-    /// Increment the value pointed to by RH and RL.
-    ///
-    /// This method affects Z, S, P and AC.
-    pub fn inc_mem(&mut self) {
-        let address = self.get_address() as usize;
-        let value = self.memory[address];
-        let result = self.add(value, 1, false);
-
-        self.set_flags(result, value, false, true, false, true, true, true);
-        self.memory[address] = result;
+        self.flags.ac = (value & 0x0f) == 0x0f;
+        self.flags.v = result == 0x80;
+        self.set_zsp(result);
+        self.set_reg(reg, result);
     }
 
     /// Decrement the value of `reg`.
     ///
     /// This method affects Z, S, P and AC.
-    pub fn dec_reg(&mut self, reg: u8) {
-        let result = self.sub(reg, 1, false);
+    pub fn dec_reg(&mut self, reg: Reg) {
+        let value = self.get_reg(reg);
+        let result = value.wrapping_sub(1);
 
-        self.set_flags(result, reg, true, true, false, true, true, true);
+        self.flags.ac = (result & 0x0f) != 0x0f;
+        self.flags.v = result == 0x7f;
+        self.set_zsp(result);
+        self.set_reg(reg, result);
     }
 
-    /// Decrement the value pointed to by RH and RL.
+    /// Increments or decrements are register pair specified by `reg_pair`.
     ///
-    /// This method affects Z, S, P and AC.
-    pub fn dec_mem(&mut self) {
-        let address = self.get_address() as usize;
-        let value = self.memory[address];
-        let result = self.sub(value, 1, false);
-
-        self.set_flags(result, value, true, true, false, true, true, true);
-        self.memory[address] = result;
-    }
-
-    /// Increments or decrements the value of (rh rl).
-    fn get_inc_or_dec_reg_pair(rh: u8, rl: u8, increment: bool) -> u16 {
-        let value = (rh as u16) << 8 + rl as u16;
-        if increment {
+    /// Only the 8085's K flag is affected, it is set when the pair wraps
+    /// around.
+    pub fn inc_or_dec_reg_pair(&mut self, reg_pair: RegPair, increment: bool) {
+        let value = self.get_reg_pair(reg_pair);
+        let result = if increment {
             value.wrapping_add(1)
         } else {
             value.wrapping_sub(1)
-        }
-    }
-
-    /// Increments or decrements are register pair specified by `reg_pair`.
-    pub fn inc_or_dec_reg_pair(&mut self, reg_pair: RegPair, increment: bool) {
-        match reg_pair {
-            RegPair::BC => {
-                let result = Emulator8080::get_inc_or_dec_reg_pair(self.rb, self.rc, increment);
-                self.set_reg_pair(result, RegPair::BC);
-            }
-            RegPair::DE => {
-                let result = Emulator8080::get_inc_or_dec_reg_pair(self.rd, self.re, increment);
-                self.set_reg_pair(result, RegPair::DE);
-            }
-            RegPair::HL => {
-                let result = Emulator8080::get_inc_or_dec_reg_pair(self.rh, self.rl, increment);
-                self.set_reg_pair(result, RegPair::HL);
-            }
-            RegPair::SP => {
-                if increment {
-                    self.sp = self.sp.wrapping_add(1);
-                } else {
-                    self.sp = self.sp.wrapping_sub(1);
-                }
-            }
-        }
+        };
+        self.flags.k = if increment {
+            result == 0
+        } else {
+            result == 0xffff
+        };
+        self.set_reg_pair(result, reg_pair);
     }
 
     /// Adds the content of `reg_pair` into the content of (HL)
@@ -341,11 +859,7 @@ impl Emulator8080 {
         let b = self.get_reg_pair(reg_pair);
         let result = a.wrapping_add(b);
 
-        if result < a {
-            self.flags.cy = true;
-        } else {
-            self.flags.cy = false
-        }
+        self.flags.cy = result < a;
 
         self.set_reg_pair(result, RegPair::HL);
     }
@@ -354,30 +868,717 @@ impl Emulator8080 {
     /// to form two four-bit Binary-Coded-Decimal by the following process:
     ///
     /// 1. If the value of the least significant 4 bits of the
-    /// accumulator is greater than 9 or if the AC flag
-    /// is set, 6 is added to the accumulator.
+    ///    accumulator is greater than 9 or if the AC flag
+    ///    is set, 6 is added to the accumulator.
     /// 2. If the value of the most significant 4 bits of the
-    /// accumulator is now greater than 9, or if the CY
-    /// flag is set, 6 is added to the most significant 4
-    /// bits of the accumulator.
+    ///    accumulator is now greater than 9, or if the CY
+    ///    flag is set, 6 is added to the most significant 4
+    ///    bits of the accumulator.
     pub fn daa(&mut self) {
+        let mut correction = 0;
+        let mut carry = self.flags.cy;
         // least significant 4 bits
         let ls4b = self.ra & 0x0f;
         if ls4b > 0x9 || self.flags.ac {
-            self.ra = self.add(self.ra, 0x6, false);
+            correction |= 0x06;
         }
-        // most significant 4 bits
-        let mut ms4b = (self.ra & 0xf0) >> 4;
-        if ms4b > 0x9 || self.flags.cy {
-            ms4b = ms4b.wrapping_add(0x6) << 4;
+        // most significant 4 bits, after the low correction
+        let ms4b = self.ra >> 4;
+        if ms4b > 0x9 || (ms4b == 0x9 && ls4b > 0x9) || self.flags.cy {
+            correction |= 0x60;
+            carry = true;
         }
-        let result = (self.ra & 0xf) | ms4b;
-        self.set_flags(result, self.ra, false, true, true, true, true, true);
+        self.ra = self.add_with_carry(self.ra, correction, false);
+        self.flags.cy = carry;
     }
+
     /* Branch group */
+    /// Decides the condition encoded in bits 3-5 of a conditional branch.
+    ///
+    /// The conditions are, in order: NZ, Z, NC, C, PO, PE, P and M.
+    fn condition(&self, code: u8) -> bool {
+        match (code >> 3) & 0x7 {
+            0 => !self.flags.z,
+            1 => self.flags.z,
+            2 => !self.flags.cy,
+            3 => self.flags.cy,
+            4 => !self.flags.p,
+            5 => self.flags.p,
+            6 => !self.flags.s,
+            _ => self.flags.s,
+        }
+    }
+
+    /// Pushes the return address and jumps to `address`.
+    pub fn call(&mut self, address: u16) {
+        self.push(self.pc);
+        self.pc = address;
+    }
+
+    /// Pops the return address into PC.
+    pub fn ret(&mut self) {
+        self.pc = self.pop();
+    }
+
     /* Data transfer group */
+    /// Exchanges the content of HL and DE.
+    pub fn xchg(&mut self) {
+        std::mem::swap(&mut self.rh, &mut self.rd);
+        std::mem::swap(&mut self.rl, &mut self.re);
+    }
+
+    /// Exchanges the content of HL with the top of the stack.
+    pub fn xthl(&mut self) {
+        let top = self.read_word(self.sp);
+        self.write_word(self.sp, self.get_reg_pair(RegPair::HL));
+        self.set_reg_pair(top, RegPair::HL);
+    }
+
     /* Logical group */
+    /// ANDs `value` into the accumulator.
+    ///
+    /// CY is cleared and AC is set to the OR of bit 3 of the operands, or
+    /// always set on the 8085.
+    pub fn ana(&mut self, value: u8) {
+        self.flags.ac = match self.variant {
+            CpuVariant::Intel8080 => ((self.ra | value) & 0x08) != 0,
+            CpuVariant::Intel8085 => true,
+        };
+        self.flags.cy = false;
+        self.ra &= value;
+        self.set_zsp(self.ra);
+    }
+
+    /// XORs `value` into the accumulator, clearing CY and AC.
+    pub fn xra(&mut self, value: u8) {
+        self.ra ^= value;
+        self.flags.ac = false;
+        self.flags.cy = false;
+        self.set_zsp(self.ra);
+    }
+
+    /// ORs `value` into the accumulator, clearing CY and AC.
+    pub fn ora(&mut self, value: u8) {
+        self.ra |= value;
+        self.flags.ac = false;
+        self.flags.cy = false;
+        self.set_zsp(self.ra);
+    }
+
+    /// Compares `value` with the accumulator by subtracting it, setting the
+    /// flags but leaving the accumulator unchanged.
+    pub fn cmp(&mut self, value: u8) {
+        self.sub(self.ra, value, false);
+    }
+
+    /// Rotates the accumulator left, CY and bit 0 get the old bit 7.
+    pub fn rlc(&mut self) {
+        self.flags.cy = (self.ra & 0x80) != 0;
+        self.ra = self.ra.rotate_left(1);
+    }
+
+    /// Rotates the accumulator right, CY and bit 7 get the old bit 0.
+    pub fn rrc(&mut self) {
+        self.flags.cy = (self.ra & 0x01) != 0;
+        self.ra = self.ra.rotate_right(1);
+    }
+
+    /// Rotates the accumulator left through the carry.
+    pub fn ral(&mut self) {
+        let carry = self.flags.cy as u8;
+        self.flags.cy = (self.ra & 0x80) != 0;
+        self.ra = (self.ra << 1) | carry;
+    }
+
+    /// Rotates the accumulator right through the carry.
+    pub fn rar(&mut self) {
+        let carry = self.flags.cy as u8;
+        self.flags.cy = (self.ra & 0x01) != 0;
+        self.ra = (self.ra >> 1) | (carry << 7);
+    }
+
+    /// Performs the ALU operation encoded in bits 3-5 of `code` with `value`.
+    ///
+    /// The operations are, in order: ADD, ADC, SUB, SBB, ANA, XRA, ORA and CMP.
+    fn alu(&mut self, code: u8, value: u8) {
+        match (code >> 3) & 0x7 {
+            0 => self.add_a(value, false),
+            1 => self.add_a(value, true),
+            2 => self.sub_a(value, false),
+            3 => self.sub_a(value, true),
+            4 => self.ana(value),
+            5 => self.xra(value),
+            6 => self.ora(value),
+            _ => self.cmp(value),
+        }
+    }
+
+    /* 8085 group */
+    /// DSUB: subtracts BC from HL. All flags are set from the 16 bit
+    /// subtraction, Z for the whole result.
+    pub fn dsub(&mut self) {
+        let low = self.sub(self.rl, self.rc, false);
+        let high = self.sub(self.rh, self.rb, true);
+        self.flags.z = low == 0 && high == 0;
+        self.set_reg_pair(u16::from_le_bytes([low, high]), RegPair::HL);
+    }
+
+    /// ARHL: shifts HL right, keeping bit 15; CY gets the old bit 0.
+    pub fn arhl(&mut self) {
+        let value = self.get_reg_pair(RegPair::HL);
+        self.flags.cy = value & 0x0001 != 0;
+        self.set_reg_pair((value as i16 >> 1) as u16, RegPair::HL);
+    }
+
+    /// RDEL: rotates DE left through the carry. V is set when bit 15
+    /// changes.
+    pub fn rdel(&mut self) {
+        let value = self.get_reg_pair(RegPair::DE);
+        let result = (value << 1) | self.flags.cy as u16;
+        self.flags.cy = value & 0x8000 != 0;
+        self.flags.v = (value ^ result) & 0x8000 != 0;
+        self.set_reg_pair(result, RegPair::DE);
+    }
+
+    /// RIM: reads the serial input, the pending interrupts, the interrupt
+    /// enable and the masks into the accumulator.
+    ///
+    /// The first RIM after a TRAP returns the interrupt enable from before
+    /// the TRAP.
+    pub fn rim(&mut self) {
+        let enable = self.pins.enable_before_trap.take().unwrap_or(self.enable);
+        self.ra = (self.pins.serial_in as u8) << 7
+            | (self.pins.rst75_pending as u8) << 6
+            | (self.pins.lines & (RST55 | RST65)) << 4
+            | (enable as u8) << 3
+            | self.pins.masks;
+    }
+
+    /// SIM: sets the interrupt masks (if bit 3 of the accumulator is set),
+    /// clears the RST 7.5 flip-flop (bit 4) and sets SOD to bit 7 (if bit
+    /// 6 is set).
+    pub fn sim(&mut self) {
+        if self.ra & 0x08 != 0 {
+            self.pins.masks = self.ra & (RST55 | RST65 | RST75);
+        }
+        if self.ra & 0x10 != 0 {
+            self.pins.rst75_pending = false;
+        }
+        if self.ra & 0x40 != 0 {
+            self.pins.serial_out = self.ra & 0x80 != 0;
+        }
+    }
+
+    /// Sets the level of an 8085 interrupt input.
+    ///
+    /// RST 5.5 and 6.5 are requested while high; RST 7.5 and TRAP when
+    /// they go high.
+    pub fn set_interrupt_line(&mut self, line: InterruptLine, high: bool) {
+        assert_eq!(
+            self.variant,
+            CpuVariant::Intel8085,
+            "only the 8085 has the {:?} input",
+            line
+        );
+        let bit = line.bit();
+        let rising = high && self.pins.lines & bit == 0;
+        if high {
+            self.pins.lines |= bit;
+        } else {
+            self.pins.lines &= !bit;
+        }
+        match line {
+            InterruptLine::Rst75 if rising => self.pins.rst75_pending = true,
+            InterruptLine::Trap if rising => self.pins.trap_pending = true,
+            _ => {}
+        }
+    }
+
+    /// Sets the level of the 8085's SID pin, read by RIM.
+    pub fn set_serial_input(&mut self, high: bool) {
+        self.pins.serial_in = high;
+    }
+
+    /// The level of the 8085's SOD pin, written by SIM.
+    pub fn serial_output(&self) -> bool {
+        self.pins.serial_out
+    }
+
+    /// Acknowledges the 8085 interrupt input with the highest priority
+    /// that is pending, enabled and not masked, returning its vector.
+    ///
+    /// Called before every instruction, so the common case of no request
+    /// is kept out of line.
+    fn take_pin_interrupt(&mut self) -> Option<u16> {
+        if self.pins.requested() {
+            self.acknowledge_pin_interrupt()
+        } else {
+            None
+        }
+    }
+
+    #[cold]
+    fn acknowledge_pin_interrupt(&mut self) -> Option<u16> {
+        let pins = &mut self.pins;
+        let unmasked = |bit: u8| pins.masks & bit == 0;
+        let vector = if pins.trap_pending {
+            pins.trap_pending = false;
+            pins.enable_before_trap = Some(self.enable);
+            0x24
+        } else if !self.enable {
+            return None;
+        } else if pins.rst75_pending && unmasked(RST75) {
+            pins.rst75_pending = false;
+            0x3c
+        } else if pins.lines & RST65 != 0 && unmasked(RST65) {
+            0x34
+        } else if pins.lines & RST55 != 0 && unmasked(RST55) {
+            0x2c
+        } else {
+            return None;
+        };
+        self.enable = false;
+        self.halted = false;
+        self.call(vector);
+        Some(vector)
+    }
+
     /* Stack, I/O and machine control group */
+    /// Pushes `value` onto the stack.
+    pub fn push(&mut self, value: u16) {
+        self.sp = self.sp.wrapping_sub(2);
+        self.write_word(self.sp, value);
+    }
+
+    /// Pops a word off the stack.
+    pub fn pop(&mut self) -> u16 {
+        let value = self.read_word(self.sp);
+        self.sp = self.sp.wrapping_add(2);
+        value
+    }
+
+    /// Requests an interrupt that executes `RST n`.
+    ///
+    /// The request is ignored if interrupts are disabled. Returns whether
+    /// the interrupt was taken.
+    pub fn interrupt(&mut self, n: u8) -> bool {
+        if !self.enable {
+            return false;
+        }
+        self.enable = false;
+        self.halted = false;
+        self.call(((n & 0x7) as u16) * 8);
+        true
+    }
 
-    pub fn emulate_instruction(&mut self) {}
+    /// Executes the instruction at PC, checking for breakpoints, bus
+    /// faults and, when strict, undocumented opcodes.
+    ///
+    /// IN and OUT are forwarded to `io`. Executing HLT is `Executed`, the
+    /// steps after it are `Halted` until an interrupt.
+    pub fn step(&mut self, io: &mut impl IoDevices) -> Result<StepOutcome, CpuError> {
+        if let Some(vector) = self.take_pin_interrupt() {
+            return Ok(StepOutcome::Interrupt {
+                vector,
+                cycles: self.interrupt_cycles(),
+            });
+        }
+        if self.halted {
+            return Ok(StepOutcome::Halted {
+                cycles: self.cycles[0x00] as u32,
+            });
+        }
+        let pc = self.pc;
+        if self.breakpoints.contains(&pc) && self.stopped_at != Some(pc) {
+            self.stopped_at = Some(pc);
+            return Ok(StepOutcome::Breakpoint { address: pc });
+        }
+        self.stopped_at = None;
+
+        self.bus_fault.set(None);
+        let opcode = self.read_byte(pc);
+        if self.strict && self.variant.is_undocumented(opcode) {
+            return Err(CpuError::UndocumentedOpcode {
+                address: pc,
+                opcode,
+            });
+        }
+        let cycles = self.execute(io);
+        match self.bus_fault.take() {
+            Some((address, access)) => Err(CpuError::BusFault {
+                pc,
+                address,
+                access,
+                cycles,
+            }),
+            None => Ok(StepOutcome::Executed { cycles }),
+        }
+    }
+
+    /// Executes the instruction at PC.
+    ///
+    /// IN and OUT are forwarded to `io`. Returns the number of clock cycles
+    /// the instruction took. While halted, no instruction is executed and
+    /// the time of a NOP passes.
+    ///
+    /// Unlike `step` this ignores breakpoints, bus faults and `strict`.
+    pub fn emulate_instruction(&mut self, io: &mut impl IoDevices) -> u32 {
+        if self.take_pin_interrupt().is_some() {
+            return self.interrupt_cycles();
+        }
+        if self.halted {
+            return self.cycles[0x00] as u32;
+        }
+        self.execute(io)
+    }
+
+    /// Cycles acknowledging an 8085 interrupt input takes, as long as an
+    /// RST.
+    fn interrupt_cycles(&self) -> u32 {
+        self.cycles[0xc7] as u32
+    }
+
+    /// Runs the instruction at PC and returns the cycles it took.
+    fn execute(&mut self, io: &mut impl IoDevices) -> u32 {
+        let code = self.next_byte();
+        let mut cycles = self.cycles[code as usize] as u32;
+
+        match code {
+            // the opcodes the 8080 leaves undocumented are new instructions
+            // on the 8085
+            0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 | 0xcb | 0xd9 | 0xdd | 0xed | 0xfd
+                if self.variant == CpuVariant::Intel8085 =>
+            {
+                return cycles + self.execute_8085(code);
+            }
+            0x00 | 0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
+                // no-op, and its undocumented aliases
+            }
+            0x01 | 0x11 | 0x21 | 0x31 => {
+                // LXI rp <- word
+                let value = self.next_word();
+                self.set_reg_pair(value, Emulator8080::reg_pair_from_code(code));
+            }
+            0x02 | 0x12 => {
+                // STAX: (rp) <- A
+                let address = self.get_reg_pair(Emulator8080::reg_pair_from_code(code));
+                self.write_byte(address, self.ra);
+            }
+            0x0a | 0x1a => {
+                // LDAX: A <- (rp)
+                let address = self.get_reg_pair(Emulator8080::reg_pair_from_code(code));
+                self.ra = self.read_byte(address);
+            }
+            0x03 | 0x13 | 0x23 | 0x33 => {
+                // INX rp
+                self.inc_or_dec_reg_pair(Emulator8080::reg_pair_from_code(code), true);
+            }
+            0x0b | 0x1b | 0x2b | 0x3b => {
+                // DCX rp
+                self.inc_or_dec_reg_pair(Emulator8080::reg_pair_from_code(code), false);
+            }
+            0x09 | 0x19 | 0x29 | 0x39 => {
+                // DAD rp
+                self.dad(Emulator8080::reg_pair_from_code(code));
+            }
+            _ if code & 0xc7 == 0x04 => {
+                // INR r
+                self.inc_reg(Reg::from_code(code >> 3));
+            }
+            _ if code & 0xc7 == 0x05 => {
+                // DCR r
+                self.dec_reg(Reg::from_code(code >> 3));
+            }
+            _ if code & 0xc7 == 0x06 => {
+                // MVI r <- byte2
+                let value = self.next_byte();
+                self.set_reg(Reg::from_code(code >> 3), value);
+            }
+            0x07 => self.rlc(),
+            0x0f => self.rrc(),
+            0x17 => self.ral(),
+            0x1f => self.rar(),
+            0x22 => {
+                // SHLD: (adr) <- L; (adr+1) <- H
+                let address = self.next_word();
+                self.write_word(address, self.get_reg_pair(RegPair::HL));
+            }
+            0x2a => {
+                // LHLD: L <- (adr); H <- (adr+1)
+                let address = self.next_word();
+                let value = self.read_word(address);
+                self.set_reg_pair(value, RegPair::HL);
+            }
+            0x27 => self.daa(),
+            0x2f => {
+                // CMA: A <- !A
+                self.ra = !self.ra;
+            }
+            0x32 => {
+                // STA: (adr) <- A
+                let address = self.next_word();
+                self.write_byte(address, self.ra);
+            }
+            0x3a => {
+                // LDA: A <- (adr)
+                let address = self.next_word();
+                self.ra = self.read_byte(address);
+            }
+            0x37 => {
+                // STC
+                self.flags.cy = true;
+            }
+            0x3f => {
+                // CMC
+                self.flags.cy = !self.flags.cy;
+            }
+            0x76 => {
+                // HLT
+                self.halted = true;
+            }
+            0x40..=0x7f => {
+                // MOV dst <- src
+                let value = self.get_reg(Reg::from_code(code));
+                self.set_reg(Reg::from_code(code >> 3), value);
+            }
+            0x80..=0xbf => {
+                // ALU op with register
+                let value = self.get_reg(Reg::from_code(code));
+                self.alu(code, value);
+            }
+            _ if code & 0xc7 == 0xc6 => {
+                // ALU op with byte2
+                let value = self.next_byte();
+                self.alu(code, value);
+            }
+            _ if code & 0xc7 == 0xc0 => {
+                // conditional RET
+                let taken = self.condition(code);
+                if taken {
+                    self.ret();
+                    cycles += self.variant.branch_taken_cycles(code);
+                }
+            }
+            _ if code & 0xc7 == 0xc2 => {
+                // conditional JMP
+                let address = self.next_word();
+                if self.condition(code) {
+                    self.pc = address;
+                    cycles += self.variant.branch_taken_cycles(code);
+                }
+            }
+            _ if code & 0xc7 == 0xc4 => {
+                // conditional CALL
+                let address = self.next_word();
+                if self.condition(code) {
+                    self.call(address);
+                    cycles += self.variant.branch_taken_cycles(code);
+                }
+            }
+            _ if code & 0xc7 == 0xc7 => {
+                // RST n
+                self.call((code & 0x38) as u16);
+            }
+            0xc1 | 0xd1 | 0xe1 => {
+                // POP rp
+                let value = self.pop();
+                self.set_reg_pair(value, Emulator8080::reg_pair_from_code(code));
+            }
+            0xf1 => {
+                // POP PSW
+                let value = self.pop();
+                self.ra = (value >> 8) as u8;
+                self.flags = Flags8080::from_psw(value as u8);
+            }
+            0xc5 | 0xd5 | 0xe5 => {
+                // PUSH rp
+                self.push(self.get_reg_pair(Emulator8080::reg_pair_from_code(code)));
+            }
+            0xf5 => {
+                // PUSH PSW
+                self.push(((self.ra as u16) << 8) | self.psw() as u16);
+            }
+            0xc3 | 0xcb => {
+                // JMP adr
+                self.pc = self.next_word();
+            }
+            0xc9 | 0xd9 => self.ret(),
+            0xcd | 0xdd | 0xed | 0xfd => {
+                // CALL adr
+                let address = self.next_word();
+                self.call(address);
+            }
+            0xd3 => {
+                // OUT port
+                let port = self.next_byte();
+                io.output(port, self.ra);
+            }
+            0xdb => {
+                // IN port
+                let port = self.next_byte();
+                self.ra = io.input(port);
+            }
+            0xe3 => self.xthl(),
+            0xe9 => {
+                // PCHL
+                self.pc = self.get_reg_pair(RegPair::HL);
+            }
+            0xeb => self.xchg(),
+            0xf3 => {
+                // DI
+                self.enable = false;
+            }
+            0xf9 => {
+                // SPHL
+                self.sp = self.get_reg_pair(RegPair::HL);
+            }
+            0xfb => {
+                // EI
+                self.enable = true;
+            }
+            _ => unreachable!("every opcode is handled above"),
+        }
+
+        cycles
+    }
+
+    /// Runs the 8085 instruction `code`, one of those the 8080 leaves
+    /// undocumented, and returns the extra cycles of a taken branch.
+    fn execute_8085(&mut self, code: u8) -> u32 {
+        match code {
+            0x08 => self.dsub(),
+            0x10 => self.arhl(),
+            0x18 => self.rdel(),
+            0x20 => self.rim(),
+            0x30 => self.sim(),
+            0x28 | 0x38 => {
+                // LDHI, LDSI: DE <- HL or SP + byte2
+                let base = if code == 0x28 {
+                    self.get_reg_pair(RegPair::HL)
+                } else {
+                    self.sp
+                };
+                let value = base.wrapping_add(self.next_byte() as u16);
+                self.set_reg_pair(value, RegPair::DE);
+            }
+            0xcb => {
+                // RSTV: RST 8 if V
+                if self.flags.v {
+                    self.call(0x40);
+                    return self.variant.branch_taken_cycles(code);
+                }
+            }
+            0xd9 => {
+                // SHLX: (DE) <- L; (DE+1) <- H
+                let address = self.get_reg_pair(RegPair::DE);
+                self.write_word(address, self.get_reg_pair(RegPair::HL));
+            }
+            0xed => {
+                // LHLX: L <- (DE); H <- (DE+1)
+                let value = self.read_word(self.get_reg_pair(RegPair::DE));
+                self.set_reg_pair(value, RegPair::HL);
+            }
+            _ => {
+                // JNK (0xdd), JK (0xfd) adr
+                let address = self.next_word();
+                if self.flags.k == (code == 0xfd) {
+                    self.pc = address;
+                    return self.variant.branch_taken_cycles(code);
+                }
+            }
+        }
+        0
+    }
+}
+
+/// Number of bytes the CPU registers take up in a save state.
+const REGISTER_STATE_SIZE: usize = 14;
+/// Number of bytes the 8085 pins take up in a save state, after the memory.
+const PINS_STATE_SIZE: usize = 3;
+
+impl Emulator8080 {
+    /// Number of bytes `save_state` writes.
+    pub fn state_size(&self) -> usize {
+        let memory_end = REGISTER_STATE_SIZE + self.memory.len();
+        match self.variant {
+            CpuVariant::Intel8080 => memory_end,
+            CpuVariant::Intel8085 => memory_end + PINS_STATE_SIZE,
+        }
+    }
+}
+
+impl SaveState for Emulator8080 {
+    /// Layout: PC and SP (little endian), A B C D E H L, PSW, interrupt enable,
+    /// halted, followed by the whole memory. The 8085 adds its interrupt
+    /// masks, input levels and a byte of pin flags.
+    ///
+    /// The variant is not saved: load into a processor of the same one.
+    fn save_state(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.pc.to_le_bytes());
+        out.extend_from_slice(&self.sp.to_le_bytes());
+        out.extend_from_slice(&[
+            self.ra,
+            self.rb,
+            self.rc,
+            self.rd,
+            self.re,
+            self.rh,
+            self.rl,
+            self.psw(),
+            self.enable as u8,
+            self.halted as u8,
+        ]);
+        out.extend_from_slice(&self.memory);
+        if self.variant == CpuVariant::Intel8085 {
+            let pins = &self.pins;
+            out.extend_from_slice(&[
+                pins.masks,
+                pins.lines,
+                pins.rst75_pending as u8
+                    | (pins.trap_pending as u8) << 1
+                    | (pins.enable_before_trap.is_some() as u8) << 2
+                    | ((pins.enable_before_trap == Some(true)) as u8) << 3
+                    | (pins.serial_in as u8) << 4
+                    | (pins.serial_out as u8) << 5,
+            ]);
+        }
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<usize, StateError> {
+        let memory_end = REGISTER_STATE_SIZE + self.memory.len();
+        let needed = self.state_size();
+        if state.len() < needed {
+            return Err(StateError::Truncated {
+                needed,
+                available: state.len(),
+            });
+        }
+        self.pc = u16::from_le_bytes([state[0], state[1]]);
+        self.sp = u16::from_le_bytes([state[2], state[3]]);
+        self.ra = state[4];
+        self.rb = state[5];
+        self.rc = state[6];
+        self.rd = state[7];
+        self.re = state[8];
+        self.rh = state[9];
+        self.rl = state[10];
+        self.flags = Flags8080::from_psw(state[11]);
+        self.enable = state[12] != 0;
+        self.halted = state[13] != 0;
+        self.memory
+            .copy_from_slice(&state[REGISTER_STATE_SIZE..memory_end]);
+        if self.variant == CpuVariant::Intel8085 {
+            let pins = &state[memory_end..needed];
+            self.pins = Pins8085 {
+                masks: pins[0] & (RST55 | RST65 | RST75),
+                lines: pins[1] & (RST55 | RST65 | RST75 | TRAP),
+                rst75_pending: pins[2] & 0x01 != 0,
+                trap_pending: pins[2] & 0x02 != 0,
+                enable_before_trap: (pins[2] & 0x04 != 0).then_some(pins[2] & 0x08 != 0),
+                serial_in: pins[2] & 0x10 != 0,
+                serial_out: pins[2] & 0x20 != 0,
+            };
+        }
+        Ok(needed)
+    }
 }
@@ -0,0 +1,324 @@
+use crate::game_state::{GameMode, GameState, Position, ALIEN_COUNT};
+use crate::machine::{SpaceInvaders, COIN, MAX_SCORE, P1_FIRE, P1_LEFT, P1_RIGHT, P1_START};
+use crate::snapshot::{self, SaveState};
+use crate::video;
+
+/// Frames the coin and start buttons are held while starting a game.
+const BUTTON_FRAMES: u64 = 6;
+/// Frames to give up after when the game does not start, e.g. because
+/// the ROM is not Space Invaders.
+const MAX_START_FRAMES: u64 = 1200;
+
+/// Length of the vector returned for `ObservationKind::State`.
+pub const STATE_SIZE: usize = 2 + 3 + 3 + ALIEN_COUNT + 3 * 3 + 3 + 1;
+
+/// What player 1 does during a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerAction {
+    Noop,
+    Left,
+    Right,
+    Fire,
+    LeftFire,
+    RightFire,
+}
+
+impl PlayerAction {
+    /// Every action, in the order of the discriminants.
+    pub const ALL: [PlayerAction; 6] = [
+        PlayerAction::Noop,
+        PlayerAction::Left,
+        PlayerAction::Right,
+        PlayerAction::Fire,
+        PlayerAction::LeftFire,
+        PlayerAction::RightFire,
+    ];
+
+    /// The buttons held on input port 1.
+    fn port1(self) -> u8 {
+        match self {
+            PlayerAction::Noop => 0,
+            PlayerAction::Left => P1_LEFT,
+            PlayerAction::Right => P1_RIGHT,
+            PlayerAction::Fire => P1_FIRE,
+            PlayerAction::LeftFire => P1_LEFT | P1_FIRE,
+            PlayerAction::RightFire => P1_RIGHT | P1_FIRE,
+        }
+    }
+}
+
+/// Which observation `Env` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObservationKind {
+    /// The screen, as made by `video::render`.
+    Screen,
+    /// A compact vector made from `GameState`, see `Env::state_vector`.
+    State,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Observation {
+    /// 224x256 pixels, row-major, one byte (0 or 1) per pixel.
+    Screen(Vec<u8>),
+    /// `STATE_SIZE` values between 0 and 1.
+    State(Vec<f32>),
+}
+
+/// Settings of an `Env`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvConfig {
+    /// Frames every step lasts, with the same action held.
+    pub frame_skip: u32,
+    /// Chance (0 to 1) that a frame keeps the previous frame's action
+    /// instead of the new one, to keep agents from relying on exact
+    /// timing.
+    pub sticky_action_probability: f32,
+    pub observation: ObservationKind,
+    /// Seed of the random numbers used for sticky actions.
+    pub seed: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            frame_skip: 4,
+            sticky_action_probability: 0.0,
+            observation: ObservationKind::Screen,
+            seed: 0,
+        }
+    }
+}
+
+/// Gym style environment playing one player games on the real ROM.
+///
+/// `reset` starts a game and `step` plays it: the reward is the score
+/// gained during the step and the episode is done at game over.
+pub struct Env {
+    machine: SpaceInvaders,
+    config: EnvConfig,
+    /// Save state at the start of a game, so later resets skip the
+    /// attract mode.
+    start_state: Option<Vec<u8>>,
+    previous_action: PlayerAction,
+    score: u16,
+    done: bool,
+    /// State of the xorshift random number generator.
+    rng: u64,
+}
+
+impl Env {
+    /// Creates an environment around `machine`, which keeps its DIP
+    /// switches over resets.
+    pub fn new(machine: SpaceInvaders, config: EnvConfig) -> Self {
+        assert!(config.frame_skip > 0, "frame_skip must be at least 1.");
+        assert!(
+            (0.0..=1.0).contains(&config.sticky_action_probability),
+            "sticky_action_probability must be between 0 and 1."
+        );
+        Self {
+            machine,
+            config,
+            start_state: None,
+            previous_action: PlayerAction::Noop,
+            score: 0,
+            done: true,
+            rng: seed_rng(config.seed),
+        }
+    }
+
+    pub fn machine(&self) -> &SpaceInvaders {
+        &self.machine
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// Starts a new game and returns the first observation.
+    ///
+    /// The first reset powers the machine on, inserts a coin and presses
+    /// 1P start; the state at the start of that game is reused by the
+    /// following resets.
+    pub fn reset(&mut self) -> Observation {
+        self.restart();
+        self.observation()
+    }
+
+    /// Same as `reset`, without making an observation.
+    pub fn restart(&mut self) {
+        match &self.start_state {
+            Some(state) => {
+                self.machine
+                    .load_state(state)
+                    .expect("start state was saved by this machine");
+            }
+            None => {
+                self.start_game();
+                self.start_state = Some(snapshot::snapshot(&self.machine));
+            }
+        }
+        self.previous_action = PlayerAction::Noop;
+        self.score = 0;
+        self.done = false;
+    }
+
+    /// Powers on and plays the attract mode until a game has started.
+    fn start_game(&mut self) {
+        self.machine.power_on();
+        // the game ignores the coin slot while it initializes
+        let mut pressed = None;
+        while GameState::read(&self.machine).mode == GameMode::Attract {
+            assert!(
+                self.machine.frame < MAX_START_FRAMES,
+                "The game did not start."
+            );
+            let frame = self.machine.frame;
+            let button = match pressed {
+                None if frame >= 60 => {
+                    pressed = Some(frame);
+                    COIN
+                }
+                Some(start) if frame < start + BUTTON_FRAMES => COIN,
+                Some(start) if frame >= start + 2 * BUTTON_FRAMES => P1_START,
+                _ => 0,
+            };
+            self.machine.io.port1 = button;
+            self.machine.io.port2 = 0;
+            self.machine.run_frame();
+        }
+        self.machine.io.port1 = 0;
+    }
+
+    /// Plays `action` for `EnvConfig::frame_skip` frames and returns the
+    /// observation, the score gained and whether the game is over.
+    pub fn step(&mut self, action: PlayerAction) -> (Observation, f32, bool) {
+        let (reward, done) = self.act(action);
+        (self.observation(), reward, done)
+    }
+
+    /// Same as `step`, without making an observation.
+    pub fn act(&mut self, action: PlayerAction) -> (f32, bool) {
+        assert!(!self.done, "step called before reset or after game over.");
+        let mut reward = 0;
+        for _ in 0..self.config.frame_skip {
+            if self.random() >= self.config.sticky_action_probability {
+                self.previous_action = action;
+            }
+            self.machine.io.port1 = self.previous_action.port1();
+            self.machine.run_frame();
+
+            let state = GameState::read(&self.machine);
+            let score = state.scores[0];
+            // the score counter rolls over at 9999
+            reward += if score >= self.score {
+                (score - self.score) as u32
+            } else {
+                (score as u32 + MAX_SCORE as u32 + 1) - self.score as u32
+            };
+            self.score = score;
+            if state.mode == GameMode::Attract {
+                self.done = true;
+                break;
+            }
+        }
+        (reward as f32, self.done)
+    }
+
+    /// The observation of the current frame, of the configured kind.
+    pub fn observation(&self) -> Observation {
+        match self.config.observation {
+            ObservationKind::Screen => Observation::Screen(video::render(self.machine.vram())),
+            ObservationKind::State => {
+                Observation::State(Env::state_vector(&GameState::read(&self.machine)))
+            }
+        }
+    }
+
+    /// Renders the screen into `pixels`, like `ObservationKind::Screen`
+    /// but without allocating.
+    pub fn screen_into(&self, pixels: &mut [u8]) {
+        video::render_into(self.machine.vram(), pixels);
+    }
+
+    /// Writes the state vector into `values`, like `ObservationKind::State`
+    /// but without allocating.
+    pub fn state_into(&self, values: &mut [f32]) {
+        Env::write_state_vector(&GameState::read(&self.machine), values);
+    }
+
+    /// Packs `state` into `STATE_SIZE` values between 0 and 1: the player
+    /// position, the player shot (present, x, y), the fleet position and
+    /// whether any invader is left, a flag for every invader, the three
+    /// alien shots and the UFO (present, x, y) and the lives.
+    ///
+    /// Absent objects have all their values at 0.
+    pub fn state_vector(state: &GameState) -> Vec<f32> {
+        let mut vector = vec![0.0; STATE_SIZE];
+        Env::write_state_vector(state, &mut vector);
+        vector
+    }
+
+    /// Same as `state_vector`, into `out`, which must be `STATE_SIZE` long.
+    pub fn write_state_vector(state: &GameState, out: &mut [f32]) {
+        assert_eq!(out.len(), STATE_SIZE, "state vector has the wrong size");
+        let coordinate = |value: u8| value as f32 / 255.0;
+        let flag = |value: bool| if value { 1.0 } else { 0.0 };
+        let object = |object: Option<Position>| match object {
+            Some(position) => [1.0, coordinate(position.x), coordinate(position.y)],
+            None => [0.0; 3],
+        };
+
+        let values = [coordinate(state.player.x), coordinate(state.player.y)]
+            .into_iter()
+            .chain(object(state.player_shot))
+            .chain([
+                coordinate(state.fleet_position.x),
+                coordinate(state.fleet_position.y),
+                flag(state.aliens_left() > 0),
+            ])
+            .chain(state.aliens.iter().map(|&alive| flag(alive)))
+            .chain(state.alien_shots.into_iter().flat_map(object))
+            .chain(object(state.ufo))
+            .chain([state.lives as f32 / 255.0]);
+        for (slot, value) in out.iter_mut().zip(values) {
+            *slot = value;
+        }
+    }
+
+    /// Next random number from 0 (inclusive) to 1 (exclusive).
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// The xorshift state for `seed`.
+///
+/// The seed goes through splitmix64 first, so that close seeds, such as
+/// the `seed + i` of the instances of a `BatchRunner`, give unrelated
+/// streams. Xorshift gets stuck at 0, which is replaced by a fixed value.
+fn seed_rng(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    match z ^ (z >> 31) {
+        0 => 0x2545_f491_4f6c_dd1d,
+        state => state,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_seeds_give_different_states() {
+        let states: Vec<u64> = (0..64).map(seed_rng).collect();
+        for (index, state) in states.iter().enumerate() {
+            assert_ne!(*state, 0);
+            assert!(!states[index + 1..].contains(state));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::machine::{self, SpaceInvaders, GAME_MODE_ADDRESS, HIGH_SCORE_ADDRESS, RAM_START};
+use crate::video::SCREEN_HEIGHT;
+
+/// Number of invaders in a fleet.
+pub const ALIEN_COUNT: usize = 55;
+/// Rows of invaders in a fleet.
+pub const ALIEN_ROWS: usize = 5;
+/// Invaders in every row.
+pub const ALIEN_COLUMNS: usize = 11;
+
+// Work RAM of the game
+/// Whether the player's ship is alive (0xff) or exploding.
+const PLAYER_ALIVE: usize = 0x2015;
+/// Player ship position, height then horizontal.
+const PLAYER_POSITION: usize = 0x201a;
+/// Player shot status.
+const PLAYER_SHOT_STATUS: usize = 0x2025;
+/// Player shot position.
+const PLAYER_SHOT_POSITION: usize = 0x2029;
+/// Status bytes of the rolling, plunger and squiggly alien shots; the
+/// position of each shot is 8 bytes further.
+const ALIEN_SHOT_STATUS: [usize; 3] = [0x2035, 0x2045, 0x2055];
+/// Position of the invader in the bottom left corner of the fleet, which
+/// the others are drawn relative to.
+const FLEET_POSITION: usize = 0x2009;
+/// High byte of the address of the current player's data, 0x21 or 0x22.
+const PLAYER_DATA: usize = 0x2067;
+/// Whether the UFO is flying.
+const UFO_ACTIVE: usize = 0x2084;
+/// UFO position.
+const UFO_POSITION: usize = 0x2089;
+/// Credits, 2 BCD digits.
+const CREDITS: usize = 0x20eb;
+/// Scores of player 1 and 2, 4 BCD digits each, least significant first.
+const SCORES: [usize; 2] = [0x20f8, 0x20fc];
+/// Offset of the reserve ship count within the player's data.
+const RESERVE_SHIPS: usize = 0xff;
+
+/// Player shot status of a shot that is flying.
+const SHOT_MOVING: u8 = 2;
+/// Player shot status of a shot that hit something.
+const SHOT_EXPLODING: u8 = 3;
+/// Set in the status of an alien shot that is on screen.
+const ALIEN_SHOT_ACTIVE: u8 = 0x80;
+
+/// What the game is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// Attract mode: the title screens and the demo.
+    Attract,
+    /// A game is being played.
+    Playing,
+}
+
+/// Position of an object as the game keeps it: `x` is the screen column
+/// plus 32, `y` the screen row counted up from the bottom, both of the
+/// bottom left corner of the sprite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Position {
+    fn read(ram: &[u8], address: usize) -> Self {
+        Self {
+            y: ram[address - RAM_START],
+            x: ram[address + 1 - RAM_START],
+        }
+    }
+
+    /// The pixel of the bitmap made by `video::render` at the bottom left
+    /// corner of the sprite, as (x, y).
+    pub fn to_screen(self) -> (usize, usize) {
+        (
+            (self.x as usize).saturating_sub(32),
+            SCREEN_HEIGHT - 1 - self.y as usize,
+        )
+    }
+}
+
+/// A typed view of the game's work RAM at one point in time.
+///
+/// The values are only meaningful while the game has them set up: most
+/// of them are left over from the demo in attract mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameState {
+    pub mode: GameMode,
+    /// The player whose turn it is, 1 or 2.
+    pub current_player: u8,
+    /// Scores of player 1 and 2.
+    pub scores: [u16; 2],
+    pub high_score: u16,
+    pub credits: u8,
+    /// Ships left for the current player, counting the one in play, as
+    /// shown in the bottom left corner. 0 in attract mode.
+    pub lives: u8,
+    /// Whether the player's ship is alive rather than exploding.
+    pub player_alive: bool,
+    pub player: Position,
+    /// The current player's invaders, `true` for those still alive, by
+    /// row from the bottom and then by column from the left.
+    pub aliens: [bool; ALIEN_COUNT],
+    /// Position of the bottom left invader of the fleet, alive or not.
+    pub fleet_position: Position,
+    /// The player's shot while it is flying or exploding.
+    pub player_shot: Option<Position>,
+    /// The rolling, plunger and squiggly shots of the invaders, when on
+    /// screen.
+    pub alien_shots: [Option<Position>; 3],
+    /// The UFO, while it flies.
+    pub ufo: Option<Position>,
+}
+
+impl GameState {
+    /// Reads the state from the work RAM, as returned by
+    /// `SpaceInvaders::ram`.
+    pub fn from_ram(ram: &[u8]) -> Self {
+        let byte = |address: usize| ram[address - RAM_START];
+        let bcd_word =
+            |address: usize| machine::from_bcd([byte(address + 1), byte(address)]).unwrap_or(0);
+
+        let mode = if byte(GAME_MODE_ADDRESS) == 0 {
+            GameMode::Attract
+        } else {
+            GameMode::Playing
+        };
+        let player_data = (byte(PLAYER_DATA) as usize) << 8;
+        // anything but player 2's data is taken as player 1's
+        let (current_player, player_data) = if player_data == 0x2200 {
+            (2, player_data)
+        } else {
+            (1, 0x2100)
+        };
+        let lives = match mode {
+            GameMode::Attract => 0,
+            GameMode::Playing => byte(player_data + RESERVE_SHIPS).saturating_add(1),
+        };
+        let aliens = std::array::from_fn(|index| byte(player_data + index) != 0);
+
+        let player_shot = match byte(PLAYER_SHOT_STATUS) {
+            SHOT_MOVING | SHOT_EXPLODING => Some(Position::read(ram, PLAYER_SHOT_POSITION)),
+            _ => None,
+        };
+        let alien_shots = ALIEN_SHOT_STATUS.map(|status| {
+            (byte(status) & ALIEN_SHOT_ACTIVE != 0).then(|| Position::read(ram, status + 8))
+        });
+        let ufo = (byte(UFO_ACTIVE) != 0).then(|| Position::read(ram, UFO_POSITION));
+
+        GameState {
+            mode,
+            current_player,
+            scores: SCORES.map(bcd_word),
+            high_score: bcd_word(HIGH_SCORE_ADDRESS),
+            credits: machine::from_bcd([0, byte(CREDITS)]).unwrap_or(0) as u8,
+            lives,
+            player_alive: byte(PLAYER_ALIVE) == 0xff,
+            player: Position::read(ram, PLAYER_POSITION),
+            aliens,
+            fleet_position: Position::read(ram, FLEET_POSITION),
+            player_shot,
+            alien_shots,
+            ufo,
+        }
+    }
+
+    /// Reads the state of `machine`.
+    pub fn read(machine: &SpaceInvaders) -> Self {
+        GameState::from_ram(machine.ram())
+    }
+
+    /// Whether the invader in `row` (from the bottom) and `column` (from
+    /// the left) is alive.
+    pub fn alien_alive(&self, row: usize, column: usize) -> bool {
+        self.aliens[row * ALIEN_COLUMNS + column]
+    }
+
+    /// Number of invaders still alive.
+    pub fn aliens_left(&self) -> usize {
+        self.aliens.iter().filter(|&&alive| alive).count()
+    }
+
+    /// Score of the current player.
+    pub fn score(&self) -> u16 {
+        self.scores[self.current_player as usize - 1]
+    }
+}
@@ -0,0 +1,300 @@
+use crate::dip::DipSetting;
+use crate::machine::{GAME_MODE_ADDRESS, HIGH_SCORE_ADDRESS};
+use crate::sound::{SoundEffect, SoundMap, AMP_ENABLE, INVADERS_EFFECTS};
+
+/// A ROM chip of a game and where it is mapped.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RomChip {
+    /// File name of the chip, as MAME calls it.
+    pub file: &'static str,
+    pub address: u16,
+    pub size: u16,
+    /// Hashes of a known good dump, `None` if none has been checked.
+    pub hash: Option<ChipHash>,
+}
+
+/// Hashes of a ROM chip, as MAME lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChipHash {
+    pub crc32: u32,
+    /// SHA-1 in lower case hex.
+    pub sha1: &'static str,
+}
+
+/// The ports a game reads and writes.
+///
+/// The buttons are always on input ports 0 to 2: port 0 mirrors the
+/// player 1 controls, port 1 holds the coin, start and player 1 buttons
+/// and port 2 the player 2 controls and the DIP switches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortLayout {
+    /// Bits wired high on input ports 0, 1 and 2.
+    pub always_set: [u8; 3],
+    /// Input port the shift register is read from.
+    pub shift_result: u8,
+    /// Output port setting the shift amount.
+    pub shift_count: u8,
+    /// Output port the shift register is written through.
+    pub shift_data: u8,
+    /// Output ports of the two sound latches.
+    pub sound: [u8; 2],
+    pub watchdog: u8,
+}
+
+/// Where a game keeps the values the machine reads from its work RAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreRam {
+    /// Address of the high score, 4 BCD digits, least significant byte
+    /// first.
+    pub high_score: usize,
+    /// Address of the flag set while a game is played, clear in attract
+    /// mode.
+    pub game_mode: usize,
+}
+
+/// A game running on the Midway/Taito 8080 black and white board.
+///
+/// The games share the CPU, the RAM at 0x2000 to 0x3fff, the video and
+/// the shift register; they differ in their ROMs, DIP switches and
+/// sounds. The color overlays of the Taito versions are not emulated.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GameDefinition {
+    /// Short name the set is selected with, as MAME calls it.
+    pub name: &'static str,
+    pub title: &'static str,
+    pub year: u16,
+    /// Name of the set this one is a clone of, `None` for a parent set.
+    pub parent: Option<&'static str>,
+    /// The chips in the order they are concatenated in a single image.
+    pub roms: &'static [RomChip],
+    pub ports: PortLayout,
+    pub dip_switches: &'static [DipSetting],
+    pub sound: SoundMap,
+    /// `None` for games whose RAM layout is not known, which then do
+    /// not get their high score saved.
+    pub score_ram: Option<ScoreRam>,
+}
+
+impl GameDefinition {
+    /// Size of all the ROM chips together.
+    pub fn rom_size(&self) -> usize {
+        self.roms.iter().map(|chip| chip.size as usize).sum()
+    }
+}
+
+/// A chip whose good dump has not been checked yet, which `romdb` can
+/// only check for its size and for obviously bad dumps.
+const fn chip(file: &'static str, address: u16, size: u16) -> RomChip {
+    RomChip {
+        file,
+        address,
+        size,
+        hash: None,
+    }
+}
+
+/// A chip whose good dump has the hashes `crc32` and `sha1`.
+const fn dumped_chip(
+    file: &'static str,
+    address: u16,
+    size: u16,
+    crc32: u32,
+    sha1: &'static str,
+) -> RomChip {
+    RomChip {
+        file,
+        address,
+        size,
+        hash: Some(ChipHash { crc32, sha1 }),
+    }
+}
+
+/// The port map of Space Invaders, kept by the other games of the board.
+const INVADERS_PORTS: PortLayout = PortLayout {
+    always_set: [0x0e, 0x08, 0x00],
+    shift_result: 3,
+    shift_count: 2,
+    shift_data: 4,
+    sound: [3, 5],
+    watchdog: 6,
+};
+
+const SHIPS_3_TO_6: DipSetting = DipSetting {
+    name: "ships",
+    mask: 0x03,
+    values: &[("3", 0x00), ("4", 0x01), ("5", 0x02), ("6", 0x03)],
+    expected: "3 to 6",
+};
+
+const SHIPS_3_OR_4: DipSetting = DipSetting {
+    name: "ships",
+    mask: 0x01,
+    values: &[("3", 0x00), ("4", 0x01)],
+    expected: "3 or 4",
+};
+
+/// Score of the extra ship.
+const BONUS: DipSetting = DipSetting {
+    name: "bonus",
+    mask: 0x08,
+    values: &[("1500", 0x00), ("1000", 0x08)],
+    expected: "1000 or 1500",
+};
+
+/// Whether attract mode shows the coin information.
+const COIN_INFO: DipSetting = DipSetting {
+    name: "coin_info",
+    mask: 0x80,
+    values: &[("on", 0x00), ("off", 0x80)],
+    expected: "on or off",
+};
+
+const INVADERS_SOUND: SoundMap = SoundMap {
+    effects: &INVADERS_EFFECTS,
+    amp_enable: AMP_ENABLE,
+    synthesized: true,
+};
+
+/// Rocket thrust, shot, player dying, alien hit and bonus on the first
+/// latch, the rescue jingles on the second.
+#[rustfmt::skip]
+const LRESCUE_SOUND: SoundMap = SoundMap {
+    effects: &[
+        SoundEffect { port5: false, bit: 0x01, looping: true },
+        SoundEffect { port5: false, bit: 0x02, looping: false },
+        SoundEffect { port5: false, bit: 0x04, looping: false },
+        SoundEffect { port5: false, bit: 0x08, looping: false },
+        SoundEffect { port5: false, bit: 0x10, looping: false },
+        SoundEffect { port5: true, bit: 0x01, looping: false },
+        SoundEffect { port5: true, bit: 0x02, looping: false },
+        SoundEffect { port5: true, bit: 0x04, looping: false },
+    ],
+    amp_enable: AMP_ENABLE,
+    synthesized: false,
+};
+
+#[rustfmt::skip]
+pub static INVADERS: GameDefinition = GameDefinition {
+    name: "invaders",
+    title: "Space Invaders",
+    year: 1978,
+    parent: None,
+    roms: &[
+        dumped_chip("invaders.h", 0x0000, 0x0800, 0x734f5ad8, "ff6200af4c9110d8181249cbcef1a8a40fa40b7f"),
+        dumped_chip("invaders.g", 0x0800, 0x0800, 0x6bfaca4a, "16f48649b531bdef8c2d1446c429b5f414524350"),
+        dumped_chip("invaders.f", 0x1000, 0x0800, 0x0ccead96, "537aef03468f63c5b9e11dd61e253f7ae17d9743"),
+        dumped_chip("invaders.e", 0x1800, 0x0800, 0x14e538b0, "1d6ca0c99f9df71e2990b610deb9d7da0125e2d8"),
+    ],
+    ports: INVADERS_PORTS,
+    dip_switches: &[SHIPS_3_TO_6, BONUS, COIN_INFO],
+    sound: INVADERS_SOUND,
+    score_ram: Some(ScoreRam {
+        high_score: HIGH_SCORE_ADDRESS,
+        game_mode: GAME_MODE_ADDRESS,
+    }),
+};
+
+pub static INVADPT2: GameDefinition = GameDefinition {
+    name: "invadpt2",
+    title: "Space Invaders Part II",
+    year: 1979,
+    parent: None,
+    roms: &[
+        chip("pv01", 0x0000, 0x0800),
+        chip("pv02", 0x0800, 0x0800),
+        chip("pv03", 0x1000, 0x0800),
+        chip("pv04", 0x1800, 0x0800),
+        chip("pv05", 0x4000, 0x0800),
+    ],
+    ports: INVADERS_PORTS,
+    dip_switches: &[SHIPS_3_OR_4, COIN_INFO],
+    sound: INVADERS_SOUND,
+    score_ram: None,
+};
+
+pub static INVADDLX: GameDefinition = GameDefinition {
+    name: "invaddlx",
+    title: "Space Invaders Deluxe",
+    year: 1980,
+    parent: Some("invadpt2"),
+    roms: &[
+        chip("invdelux.h", 0x0000, 0x0800),
+        chip("invdelux.g", 0x0800, 0x0800),
+        chip("invdelux.f", 0x1000, 0x0800),
+        chip("invdelux.e", 0x1800, 0x0800),
+        chip("invdelux.d", 0x4000, 0x0800),
+    ],
+    ports: INVADERS_PORTS,
+    dip_switches: &[SHIPS_3_OR_4, COIN_INFO],
+    sound: INVADERS_SOUND,
+    score_ram: None,
+};
+
+pub static LRESCUE: GameDefinition = GameDefinition {
+    name: "lrescue",
+    title: "Lunar Rescue",
+    year: 1979,
+    parent: None,
+    roms: &[
+        chip("lrescue.1", 0x0000, 0x0800),
+        chip("lrescue.2", 0x0800, 0x0800),
+        chip("lrescue.3", 0x1000, 0x0800),
+        chip("lrescue.4", 0x1800, 0x0800),
+        chip("lrescue.5", 0x4000, 0x0800),
+        chip("lrescue.6", 0x4800, 0x0800),
+    ],
+    ports: INVADERS_PORTS,
+    dip_switches: &[SHIPS_3_TO_6, COIN_INFO],
+    sound: LRESCUE_SOUND,
+    score_ram: None,
+};
+
+pub static BALLBOMB: GameDefinition = GameDefinition {
+    name: "ballbomb",
+    title: "Balloon Bomber",
+    year: 1980,
+    parent: None,
+    roms: &[
+        chip("tn01", 0x0000, 0x0800),
+        chip("tn02", 0x0800, 0x0800),
+        chip("tn03", 0x1000, 0x0800),
+        chip("tn04", 0x1800, 0x0800),
+        chip("tn05-1", 0x4000, 0x0800),
+    ],
+    ports: INVADERS_PORTS,
+    dip_switches: &[SHIPS_3_TO_6, COIN_INFO],
+    sound: INVADERS_SOUND,
+    score_ram: None,
+};
+
+/// A bootleg of Space Invaders on 1K chips.
+pub static SPACEATT: GameDefinition = GameDefinition {
+    name: "spaceatt",
+    title: "Space Attack",
+    year: 1978,
+    parent: Some("invaders"),
+    roms: &[
+        chip("h", 0x0000, 0x0400),
+        chip("sv02.bin", 0x0400, 0x0400),
+        chip("f", 0x0800, 0x0400),
+        chip("g", 0x0c00, 0x0400),
+        chip("e", 0x1000, 0x0400),
+        chip("c", 0x1400, 0x0400),
+        chip("b", 0x1800, 0x0400),
+        chip("a", 0x1c00, 0x0400),
+    ],
+    ports: INVADERS_PORTS,
+    dip_switches: &[SHIPS_3_TO_6, BONUS, COIN_INFO],
+    sound: INVADERS_SOUND,
+    score_ram: None,
+};
+
+/// Every game the emulator knows, Space Invaders first.
+pub static GAMES: [&GameDefinition; 6] = [
+    &INVADERS, &INVADPT2, &INVADDLX, &LRESCUE, &BALLBOMB, &SPACEATT,
+];
+
+/// The game called `name`, see `GameDefinition::name`.
+pub fn find(name: &str) -> Option<&'static GameDefinition> {
+    GAMES.iter().copied().find(|game| game.name == name)
+}
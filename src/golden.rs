@@ -0,0 +1,218 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::machine::SpaceInvaders;
+use crate::movie::FrameInput;
+
+/// Environment variable that makes `assert_golden` rewrite the golden file
+/// instead of comparing against it.
+pub const BLESS_VAR: &str = "SPAINEM_BLESS";
+
+/// Error returned when an input script can not be parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    /// 1 based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// A sequence of input changes, each holding from its frame until the next.
+///
+/// The text format has one change per line, `<frame> <port1> <port2>` with
+/// the port values in hex, and `#` starting a comment:
+///
+/// ```text
+/// # insert a coin, then press 1P start
+/// 60   01 00
+/// 66   00 00
+/// 120  04 00
+/// 126  00 00
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputScript {
+    /// Frame and the inputs from that frame on, sorted by frame.
+    changes: Vec<(u64, FrameInput)>,
+}
+
+impl InputScript {
+    /// Creates an empty script, where no buttons are ever pressed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds `input` from `frame` on, until the next change.
+    pub fn set(&mut self, frame: u64, input: FrameInput) {
+        match self.changes.binary_search_by_key(&frame, |&(f, _)| f) {
+            Ok(index) => self.changes[index].1 = input,
+            Err(index) => self.changes.insert(index, (frame, input)),
+        }
+    }
+
+    /// The inputs held during `frame`.
+    pub fn input_at(&self, frame: u64) -> FrameInput {
+        let index = self.changes.partition_point(|&(f, _)| f <= frame);
+        if index == 0 {
+            FrameInput::default()
+        } else {
+            self.changes[index - 1].1
+        }
+    }
+
+    /// Sets the input ports of `machine` for `frame`, counted from the
+    /// start of the run like the frames of `frame_hashes`.
+    pub fn apply(&self, machine: &mut SpaceInvaders, frame: u64) {
+        let input = self.input_at(frame);
+        machine.io.port1 = input.port1;
+        machine.io.port2 = input.port2;
+    }
+
+    /// Parses the text format described on the type.
+    pub fn parse(text: &str) -> Result<Self, ScriptError> {
+        let mut script = InputScript::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| ScriptError {
+                line: index + 1,
+                message: message.to_owned(),
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(error("expected `<frame> <port1> <port2>`"));
+            }
+            let frame = fields[0]
+                .parse()
+                .map_err(|_| error("frame is not a number"))?;
+            let port1 =
+                u8::from_str_radix(fields[1], 16).map_err(|_| error("port1 is not a hex byte"))?;
+            let port2 =
+                u8::from_str_radix(fields[2], 16).map_err(|_| error("port2 is not a hex byte"))?;
+            script.set(frame, FrameInput { port1, port2 });
+        }
+        Ok(script)
+    }
+
+    /// Reads and parses the script at `file_path`.
+    pub fn load(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(InputScript::parse(&fs::read_to_string(file_path)?)?)
+    }
+}
+
+/// Runs `machine` for `frames` frames, feeding it the inputs from `script`,
+/// and returns `SpaceInvaders::frame_hash` after every frame.
+///
+/// Script frames count from the frame the machine is at when called.
+/// Nothing is throttled or drawn, so this runs as fast as the host allows.
+pub fn frame_hashes(machine: &mut SpaceInvaders, script: &InputScript, frames: u64) -> Vec<u64> {
+    (0..frames)
+        .map(|frame| {
+            script.apply(machine, frame);
+            machine.run_frame();
+            machine.frame_hash()
+        })
+        .collect()
+}
+
+/// Writes `hashes` to `file_path`, one hex hash per line.
+pub fn write_golden(file_path: &str, hashes: &[u64]) -> io::Result<()> {
+    let text: String = hashes
+        .iter()
+        .map(|hash| format!("{:016x}\n", hash))
+        .collect();
+    fs::write(file_path, text)
+}
+
+/// Reads hashes written by `write_golden`.
+pub fn read_golden(file_path: &str) -> io::Result<Vec<u64>> {
+    fs::read_to_string(file_path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            u64::from_str_radix(line.trim(), 16)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+        .collect()
+}
+
+/// The first difference between a run and its golden hashes.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GoldenMismatch {
+    /// The hashes differ at `frame` (0 based).
+    Hash {
+        frame: usize,
+        expected: u64,
+        found: u64,
+    },
+    /// All common frames match, but the number of frames differs.
+    Length { expected: usize, found: usize },
+}
+
+impl fmt::Display for GoldenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenMismatch::Hash {
+                frame,
+                expected,
+                found,
+            } => write!(
+                f,
+                "frame {} differs: expected {:016x}, got {:016x}",
+                frame, expected, found
+            ),
+            GoldenMismatch::Length { expected, found } => {
+                write!(f, "expected {} frames, got {}", expected, found)
+            }
+        }
+    }
+}
+
+/// Compares `hashes` against `golden`, returning the first difference.
+pub fn compare_golden(golden: &[u64], hashes: &[u64]) -> Result<(), GoldenMismatch> {
+    if let Some(frame) = golden.iter().zip(hashes).position(|(a, b)| a != b) {
+        return Err(GoldenMismatch::Hash {
+            frame,
+            expected: golden[frame],
+            found: hashes[frame],
+        });
+    }
+    if golden.len() != hashes.len() {
+        return Err(GoldenMismatch::Length {
+            expected: golden.len(),
+            found: hashes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Test helper that panics unless `hashes` match the golden file at `file_path`.
+///
+/// When the `SPAINEM_BLESS` environment variable is set, the golden file is
+/// (re)written from `hashes` instead.
+pub fn assert_golden(file_path: &str, hashes: &[u64]) {
+    if env::var_os(BLESS_VAR).is_some() {
+        write_golden(file_path, hashes)
+            .unwrap_or_else(|err| panic!("Could not write {}: {}", file_path, err));
+        return;
+    }
+    let golden = read_golden(file_path).unwrap_or_else(|err| {
+        panic!(
+            "Could not read {}: {} (run with {}=1 to create it)",
+            file_path, err, BLESS_VAR
+        )
+    });
+    if let Err(mismatch) = compare_golden(&golden, hashes) {
+        panic!("{} does not match: {}", file_path, mismatch);
+    }
+}
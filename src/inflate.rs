@@ -0,0 +1,288 @@
+use std::fmt;
+
+/// Longest Huffman code DEFLATE uses.
+const MAX_BITS: usize = 15;
+
+/// Base lengths of the length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// Extra bits of the length codes 257 to 285.
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Base distances of the distance codes 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// Extra bits of the distance codes 0 to 29.
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order the code length code lengths of a dynamic block are sent in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Error returned for data that is not valid DEFLATE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    /// The data ends in the middle of a block.
+    UnexpectedEnd,
+    InvalidBlockType,
+    /// The length of a stored block does not match its complement.
+    StoredLength,
+    /// A Huffman table is over-subscribed or a code is not in its table.
+    InvalidCode,
+    /// A match reaches back before the start of the output.
+    DistanceTooFar,
+    /// The output would be bigger than the size it was limited to.
+    TooBig,
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            InflateError::UnexpectedEnd => "compressed data ends too early",
+            InflateError::InvalidBlockType => "invalid block type",
+            InflateError::StoredLength => "stored block length does not match its complement",
+            InflateError::InvalidCode => "invalid Huffman code",
+            InflateError::DistanceTooFar => "match distance is before the start of the data",
+            InflateError::TooBig => "decompressed data is bigger than expected",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl std::error::Error for InflateError {}
+
+/// Reads the input a bit at a time, least significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bit(&mut self) -> Result<u32, InflateError> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or(InflateError::UnexpectedEnd)?;
+        let value = (byte as u32 >> self.bit) & 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    /// `count` bits as a number, the first one least significant.
+    fn bits(&mut self, count: u8) -> Result<u32, InflateError> {
+        (0..count).try_fold(0, |value, index| Ok(value | self.bit()? << index))
+    }
+
+    /// Skips to the next byte boundary and takes `count` bytes.
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], InflateError> {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.position += 1;
+        }
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or(InflateError::UnexpectedEnd)?;
+        self.position += count;
+        Ok(bytes)
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of every length and
+/// the symbols in code order.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds the code from the code length of every symbol, 0 for unused
+    /// symbols.
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                return Err(InflateError::InvalidCode);
+            }
+        }
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    /// Reads one symbol, a bit at a time.
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, InflateError> {
+        // `code` is the code read so far, `first` the first code of its
+        // length and `index` the index of that code in `symbols`
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= reader.bit()? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InflateError::InvalidCode)
+    }
+}
+
+/// Decompresses raw DEFLATE data (RFC 1951), as stored in ZIP archives.
+///
+/// Fails as soon as the output would grow past `max_size` bytes, so that
+/// a small archive can not make it allocate without bounds.
+pub fn inflate(data: &[u8], max_size: usize) -> Result<Vec<u8>, InflateError> {
+    let mut reader = BitReader {
+        data,
+        position: 0,
+        bit: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bit()? == 1;
+        match reader.bits(2)? {
+            0 => {
+                let header = reader.bytes(4)?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                let complement = u16::from_le_bytes([header[2], header[3]]);
+                if length != !complement {
+                    return Err(InflateError::StoredLength);
+                }
+                let bytes = reader.bytes(length as usize)?;
+                if out.len() + bytes.len() > max_size {
+                    return Err(InflateError::TooBig);
+                }
+                out.extend_from_slice(bytes);
+            }
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                inflate_block(&mut reader, &mut out, max_size, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, max_size, &literals, &distances)?;
+            }
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// The literal/length and distance codes of a fixed Huffman block.
+fn fixed_codes() -> Result<(Huffman, Huffman), InflateError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+/// Reads the literal/length and distance codes of a dynamic Huffman
+/// block.
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), InflateError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let &previous = lengths.last().ok_or(InflateError::InvalidCode)?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count || lengths[256] == 0 {
+        return Err(InflateError::InvalidCode);
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+/// Decodes the symbols of a Huffman block up to its end of block code,
+/// keeping `out` within `max_size` bytes.
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    max_size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 if out.len() >= max_size => return Err(InflateError::TooBig),
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let code = symbol - 257;
+                let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code])? as usize;
+                let code = distances.decode(reader)? as usize;
+                if code >= DISTANCE_BASE.len() {
+                    return Err(InflateError::InvalidCode);
+                }
+                let distance =
+                    DISTANCE_BASE[code] as usize + reader.bits(DISTANCE_EXTRA[code])? as usize;
+                if distance > out.len() {
+                    return Err(InflateError::DistanceTooFar);
+                }
+                if out.len() + length > max_size {
+                    return Err(InflateError::TooBig);
+                }
+                // the match may overlap the bytes it produces
+                let start = out.len() - distance;
+                for index in 0..length {
+                    out.push(out[start + index]);
+                }
+            }
+            _ => return Err(InflateError::InvalidCode),
+        }
+    }
+}
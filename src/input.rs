@@ -0,0 +1,232 @@
+use std::fmt;
+
+use crate::config::{Config, ConfigError};
+use crate::machine::{
+    COIN, P1_FIRE, P1_LEFT, P1_RIGHT, P1_START, P2_FIRE, P2_LEFT, P2_RIGHT, P2_START, TILT,
+};
+use crate::movie::FrameInput;
+
+/// Host keys a frontend can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A printable character, lower case for letters.
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+}
+
+impl Key {
+    /// Parses a key name as written in the config file: a single
+    /// character, or one of `space`, `comma`, `up`, `down`, `left`,
+    /// `right` and `escape`.
+    pub fn from_name(name: &str) -> Option<Key> {
+        let lower = name.to_ascii_lowercase();
+        let key = match lower.as_str() {
+            "space" => Key::Char(' '),
+            "comma" => Key::Char(','),
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "escape" | "esc" => Key::Escape,
+            _ => {
+                let mut chars = lower.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_control() => Key::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        Some(key)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(' ') => write!(f, "space"),
+            Key::Char(',') => write!(f, "comma"),
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Up => write!(f, "up"),
+            Key::Down => write!(f, "down"),
+            Key::Left => write!(f, "left"),
+            Key::Right => write!(f, "right"),
+            Key::Escape => write!(f, "escape"),
+        }
+    }
+}
+
+/// The cabinet's buttons and switches, one for every input bit the game
+/// reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Coin,
+    P1Start,
+    P2Start,
+    /// Player 1 controls, wired to both port 0 and port 1.
+    P1Left,
+    P1Right,
+    P1Fire,
+    P2Left,
+    P2Right,
+    P2Fire,
+    Tilt,
+}
+
+impl Action {
+    /// Every action, in the order of the discriminants.
+    pub const ALL: [Action; 10] = [
+        Action::Coin,
+        Action::P1Start,
+        Action::P2Start,
+        Action::P1Left,
+        Action::P1Right,
+        Action::P1Fire,
+        Action::P2Left,
+        Action::P2Right,
+        Action::P2Fire,
+        Action::Tilt,
+    ];
+
+    /// The name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Coin => "coin",
+            Action::P1Start => "p1_start",
+            Action::P2Start => "p2_start",
+            Action::P1Left => "p1_left",
+            Action::P1Right => "p1_right",
+            Action::P1Fire => "p1_fire",
+            Action::P2Left => "p2_left",
+            Action::P2Right => "p2_right",
+            Action::P2Fire => "p2_fire",
+            Action::Tilt => "tilt",
+        }
+    }
+
+    /// The action called `name` in the config file.
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Sets the input bit of the action in `input`.
+    pub fn press(self, input: &mut FrameInput) {
+        match self {
+            Action::Coin => input.port1 |= COIN,
+            Action::P1Start => input.port1 |= P1_START,
+            Action::P2Start => input.port1 |= P2_START,
+            Action::P1Left => input.port1 |= P1_LEFT,
+            Action::P1Right => input.port1 |= P1_RIGHT,
+            Action::P1Fire => input.port1 |= P1_FIRE,
+            Action::P2Left => input.port2 |= P2_LEFT,
+            Action::P2Right => input.port2 |= P2_RIGHT,
+            Action::P2Fire => input.port2 |= P2_FIRE,
+            Action::Tilt => input.port2 |= TILT,
+        }
+    }
+}
+
+/// Which host keys press which cabinet buttons, shared by all frontends.
+///
+/// A key may press several actions and an action may have several keys.
+/// The `[input]` section of the config file lists the keys of an action,
+/// separated by commas:
+///
+/// ```text
+/// [input]
+/// p1_fire = space, up
+/// tilt = t
+/// ```
+///
+/// Actions that are not listed keep their default keys; an empty list
+/// leaves the action unbound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl InputMap {
+    /// Creates a map without any bindings.
+    pub fn empty() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Makes `key` press `action`, on top of any other bindings.
+    pub fn bind(&mut self, key: Key, action: Action) {
+        if !self.bindings.contains(&(key, action)) {
+            self.bindings.push((key, action));
+        }
+    }
+
+    /// Removes all keys of `action`.
+    pub fn unbind(&mut self, action: Action) {
+        self.bindings.retain(|&(_, bound)| bound != action);
+    }
+
+    /// The actions `key` presses.
+    pub fn actions_for(&self, key: Key) -> impl Iterator<Item = Action> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    /// The keys that press `action`.
+    pub fn keys_for(&self, action: Action) -> impl Iterator<Item = Key> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == action)
+            .map(|&(key, _)| key)
+    }
+
+    /// The default map with the `[input]` section of `config` applied.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut map = InputMap::default();
+        for entry in config.section("input") {
+            let action = Action::from_name(&entry.name)
+                .ok_or_else(|| entry.error(&format!("unknown action `{}`", entry.name)))?;
+            map.unbind(action);
+            for name in entry.value.split(',').map(str::trim) {
+                if name.is_empty() {
+                    continue;
+                }
+                let key = Key::from_name(name)
+                    .ok_or_else(|| entry.error(&format!("unknown key `{}`", name)))?;
+                map.bind(key, action);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl Default for InputMap {
+    /// `c` coin, `1`/`2` start, arrows or `a`/`d` move, space, `w` or up
+    /// fire, `j`/`l`/`i` player 2 and `t` tilt.
+    fn default() -> Self {
+        let mut map = InputMap::empty();
+        for (key, action) in [
+            (Key::Char('c'), Action::Coin),
+            (Key::Char('1'), Action::P1Start),
+            (Key::Char('2'), Action::P2Start),
+            (Key::Char('a'), Action::P1Left),
+            (Key::Left, Action::P1Left),
+            (Key::Char('d'), Action::P1Right),
+            (Key::Right, Action::P1Right),
+            (Key::Char(' '), Action::P1Fire),
+            (Key::Char('w'), Action::P1Fire),
+            (Key::Up, Action::P1Fire),
+            (Key::Char('j'), Action::P2Left),
+            (Key::Char('l'), Action::P2Right),
+            (Key::Char('i'), Action::P2Fire),
+            (Key::Char('t'), Action::Tilt),
+        ] {
+            map.bind(key, action);
+        }
+        map
+    }
+}
@@ -1,5 +1,280 @@
+use std::fmt;
 use std::fs;
+use std::io;
+use std::path::Path;
 
-pub fn read_from_file(file_path: &str) -> Vec<u8> {
-    fs::read(file_path).expect("Could not read from file.")
+use crate::games::GameDefinition;
+use crate::machine::MAX_SCORE;
+use crate::romdb::RomDump;
+use crate::zip::{ZipArchive, ZipError};
+
+/// Error returned when a file can not be read or written.
+#[derive(Debug)]
+pub struct FileError {
+    pub path: String,
+    pub source: io::Error,
+}
+
+impl FileError {
+    pub fn new(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Self {
+            path: path.as_ref().display().to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.source)
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Error returned when a ROM can not be loaded.
+#[derive(Debug)]
+pub enum RomError {
+    File(FileError),
+    /// The image does not fit in the ROM chips of the game.
+    TooBig {
+        path: String,
+        size: usize,
+        rom_size: usize,
+    },
+    /// A zip archive can not be read.
+    Zip {
+        path: String,
+        err: ZipError,
+    },
+    /// A chip file does not have the size of the chip.
+    WrongSize {
+        path: String,
+        size: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::File(err) => write!(f, "{}", err),
+            RomError::TooBig {
+                path,
+                size,
+                rom_size,
+            } => write!(
+                f,
+                "{}: ROM image is {} bytes, more than the {} bytes of program ROM",
+                path, size, rom_size
+            ),
+            RomError::Zip { path, err } => write!(f, "{}: {}", path, err),
+            RomError::WrongSize {
+                path,
+                size,
+                expected,
+            } => write!(f, "{}: chip is {} bytes, expected {}", path, size, expected),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<FileError> for RomError {
+    fn from(err: FileError) -> Self {
+        RomError::File(err)
+    }
+}
+
+/// Error returned when a high score file can not be read.
+#[derive(Debug)]
+pub enum HighScoreError {
+    File(FileError),
+    /// The file does not hold a score from 0 to `MAX_SCORE`.
+    NotAScore {
+        path: String,
+    },
+}
+
+impl fmt::Display for HighScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HighScoreError::File(err) => write!(f, "{}", err),
+            HighScoreError::NotAScore { path } => write!(
+                f,
+                "{}: high score file does not hold a score from 0 to {}",
+                path, MAX_SCORE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HighScoreError {}
+
+pub fn read_from_file(file_path: &str) -> Result<Vec<u8>, FileError> {
+    fs::read(file_path).map_err(|err| FileError::new(file_path, err))
+}
+
+/// Reads `file_path` as UTF-8 text.
+pub fn read_text(file_path: &str) -> Result<String, FileError> {
+    fs::read_to_string(file_path).map_err(|err| FileError::new(file_path, err))
+}
+
+/// Writes `contents` to `file_path`, replacing it if it exists.
+pub fn write_file(file_path: &str, contents: &[u8]) -> Result<(), FileError> {
+    fs::write(file_path, contents).map_err(|err| FileError::new(file_path, err))
+}
+
+/// Whether `path` names a zip archive rather than an image.
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
+
+/// Reads the zip archive at `zip_path`.
+fn read_zip(zip_path: &str) -> Result<ZipArchive, RomError> {
+    ZipArchive::new(read_from_file(zip_path)?).map_err(|err| RomError::Zip {
+        path: zip_path.to_owned(),
+        err,
+    })
+}
+
+/// Reads the files that make up the ROM of `game` at `rom_path`, as
+/// (path, bytes) in the order of `GameDefinition::roms`.
+///
+/// `rom_path` is either a single image holding all the chips, or a
+/// directory or zip archive containing the separate chips, e.g.
+/// `invaders.h`, `.g`, `.f` and `.e` for Space Invaders. In an archive
+/// the chips are found by name, in any directory, or else by CRC-32.
+pub fn read_rom_files(
+    rom_path: &str,
+    game: &GameDefinition,
+) -> Result<Vec<(String, Vec<u8>)>, RomError> {
+    let path = Path::new(rom_path);
+    if is_zip(path) {
+        let archive = read_zip(rom_path)?;
+        let files = archive.entries().iter().filter(|entry| !entry.is_dir());
+        return game
+            .roms
+            .iter()
+            .map(|chip| {
+                let entry = files
+                    .clone()
+                    .find(|entry| entry.file_name().eq_ignore_ascii_case(chip.file))
+                    .or_else(|| {
+                        let hash = chip.hash?;
+                        files.clone().find(|entry| entry.crc32 == hash.crc32)
+                    })
+                    .ok_or_else(|| {
+                        FileError::new(
+                            format!("{}/{}", rom_path, chip.file),
+                            io::Error::new(io::ErrorKind::NotFound, "not in the archive"),
+                        )
+                    })?;
+                let bytes = archive.read(entry).map_err(|err| RomError::Zip {
+                    path: rom_path.to_owned(),
+                    err,
+                })?;
+                Ok((format!("{}/{}", rom_path, entry.name), bytes))
+            })
+            .collect();
+    }
+    if !path.is_dir() {
+        return Ok(vec![(rom_path.to_owned(), read_from_file(rom_path)?)]);
+    }
+    game.roms
+        .iter()
+        .map(|chip| {
+            let chip_path = path.join(chip.file);
+            let bytes = fs::read(&chip_path).map_err(|err| FileError::new(&chip_path, err))?;
+            Ok((chip_path.display().to_string(), bytes))
+        })
+        .collect::<Result<_, FileError>>()
+        .map_err(RomError::File)
+}
+
+/// Reads the ROM of `game` from `rom_path` as a single image, see
+/// `read_rom_files`. Separate chips must have the size of the chip.
+pub fn read_rom(rom_path: &str, game: &GameDefinition) -> Result<Vec<u8>, RomError> {
+    let files = read_rom_files(rom_path, game)?;
+    let path = Path::new(rom_path);
+    if path.is_dir() || is_zip(path) {
+        for ((path, bytes), chip) in files.iter().zip(game.roms) {
+            if bytes.len() != chip.size as usize {
+                return Err(RomError::WrongSize {
+                    path: path.clone(),
+                    size: bytes.len(),
+                    expected: chip.size as usize,
+                });
+            }
+        }
+    }
+    let rom: Vec<u8> = files.into_iter().flat_map(|(_, bytes)| bytes).collect();
+    if rom.len() > game.rom_size() {
+        return Err(RomError::TooBig {
+            path: rom_path.to_owned(),
+            size: rom.len(),
+            rom_size: game.rom_size(),
+        });
+    }
+    Ok(rom)
+}
+
+/// Reads the ROM at `rom_path` without knowing the game: an image, or
+/// every file of a directory or zip archive as a chip.
+pub fn read_rom_dump(rom_path: &str) -> Result<RomDump, RomError> {
+    let path = Path::new(rom_path);
+    if is_zip(path) {
+        let archive = read_zip(rom_path)?;
+        let mut chips = Vec::new();
+        for entry in archive.entries().iter().filter(|entry| !entry.is_dir()) {
+            let bytes = archive.read(entry).map_err(|err| RomError::Zip {
+                path: rom_path.to_owned(),
+                err,
+            })?;
+            chips.push((entry.file_name().to_owned(), bytes));
+        }
+        chips.sort();
+        return Ok(RomDump::Chips(chips));
+    }
+    if !path.is_dir() {
+        return Ok(RomDump::Image(read_from_file(rom_path)?));
+    }
+    let entries = fs::read_dir(path).map_err(|err| FileError::new(path, err))?;
+    let mut chips = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| FileError::new(path, err))?;
+        let chip_path = entry.path();
+        if chip_path.is_file() {
+            let bytes = fs::read(&chip_path).map_err(|err| FileError::new(&chip_path, err))?;
+            chips.push((entry.file_name().to_string_lossy().into_owned(), bytes));
+        }
+    }
+    chips.sort();
+    Ok(RomDump::Chips(chips))
+}
+
+/// Reads a high score saved by `write_high_score`, `None` if the file does
+/// not exist yet.
+pub fn read_high_score(file_path: &str) -> Result<Option<u16>, HighScoreError> {
+    let text = match fs::read_to_string(file_path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(HighScoreError::File(FileError::new(file_path, err))),
+    };
+    match text.trim().parse() {
+        Ok(score) if score <= MAX_SCORE => Ok(Some(score)),
+        _ => Err(HighScoreError::NotAScore {
+            path: file_path.to_owned(),
+        }),
+    }
+}
+
+/// Writes `score` to `file_path` as a decimal number.
+pub fn write_high_score(file_path: &str, score: u16) -> Result<(), FileError> {
+    write_file(file_path, format!("{}\n", score).as_bytes())
 }
@@ -0,0 +1,39 @@
+//! SpaInEm, an emulator for the Space Invaders arcade board.
+//!
+//! The 8080 CPU lives in `emulator`, the board around it in `machine` and
+//! the screen in `video`. The other games of the board are described in
+//! `games`. The binaries in this package only parse their arguments, with
+//! the help of `cli`, and call into these modules, e.g. `debugger`.
+
+pub mod batch;
+pub mod cli;
+pub mod config;
+pub mod debugger;
+pub mod dip;
+pub mod disassembler;
+pub mod emulator;
+pub mod env;
+pub mod game_state;
+pub mod games;
+pub mod golden;
+pub mod hash;
+pub mod inflate;
+pub mod input;
+pub mod io_spainem;
+pub mod machine;
+pub mod movie;
+pub mod patch;
+pub mod rewind;
+pub mod romdb;
+pub mod snapshot;
+pub mod sound;
+pub mod synth;
+pub mod terminal;
+pub mod timing;
+pub mod video;
+pub mod wav;
+pub mod zip;
+
+pub use emulator::{CpuError, CpuVariant, Emulator8080, InterruptLine, IoDevices, StepOutcome};
+pub use games::GameDefinition;
+pub use machine::SpaceInvaders;
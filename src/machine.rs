@@ -0,0 +1,545 @@
+use crate::dip::DipSwitches;
+use crate::emulator::{CpuError, Emulator8080, IoDevices, MemoryMap, Page, StepOutcome};
+use crate::games::{self, GameDefinition, PortLayout};
+use crate::snapshot::{SaveState, StateError};
+use crate::sound::SoundPorts;
+
+/// CPU clock of the Space Invaders board, in Hz.
+pub const CLOCK_HZ: u32 = 2_000_000;
+/// Screen refresh rate, in Hz.
+pub const FRAMES_PER_SECOND: u32 = 60;
+/// CPU cycles between two vertical blanks.
+pub const CYCLES_PER_FRAME: u32 = CLOCK_HZ / FRAMES_PER_SECOND;
+
+/// Size of the program ROM mapped at 0x0000.
+pub const ROM_SIZE: usize = 0x2000;
+/// Start of the work RAM.
+pub const RAM_START: usize = 0x2000;
+/// Start of the video RAM.
+pub const VRAM_START: usize = 0x2400;
+/// End (exclusive) of the RAM, work and video RAM combined.
+pub const RAM_END: usize = 0x4000;
+
+// Input port 1
+/// Coin slot, set while a coin is inserted.
+pub const COIN: u8 = 0x01;
+/// 2 player start button.
+pub const P2_START: u8 = 0x02;
+/// 1 player start button.
+pub const P1_START: u8 = 0x04;
+/// Player 1 fire button.
+pub const P1_FIRE: u8 = 0x10;
+/// Player 1 joystick left.
+pub const P1_LEFT: u8 = 0x20;
+/// Player 1 joystick right.
+pub const P1_RIGHT: u8 = 0x40;
+
+// Input port 2
+/// Tilt switch.
+pub const TILT: u8 = 0x04;
+/// Player 2 fire button.
+pub const P2_FIRE: u8 = 0x10;
+/// Player 2 joystick left.
+pub const P2_LEFT: u8 = 0x20;
+/// Player 2 joystick right.
+pub const P2_RIGHT: u8 = 0x40;
+
+/// Address of the high score, 4 BCD digits, least significant byte first.
+pub const HIGH_SCORE_ADDRESS: usize = 0x20f4;
+/// Address of the flag the game sets while it is being played, and clears
+/// in attract mode.
+pub const GAME_MODE_ADDRESS: usize = 0x20ef;
+/// Highest score the game can show.
+pub const MAX_SCORE: u16 = 9999;
+
+/// Frames without a kick on OUT 6 before the watchdog resets the CPU.
+pub const WATCHDOG_FRAMES: u32 = 255;
+
+/// The hardware the CPU reaches through IN and OUT: the input ports,
+/// the bit shift register and the sound latches.
+#[derive(Debug, Clone)]
+pub struct SpaceInvadersIo {
+    /// Buttons held on input port 1, see `COIN`, `P1_FIRE` etc.
+    pub port1: u8,
+    /// Buttons held on input port 2, see `TILT`, `P2_FIRE` etc.
+    pub port2: u8,
+    /// The cabinet's DIP switches, also read through port 2.
+    pub dip_switches: DipSwitches,
+    /// Last value written to the first sound port, 3 on Space Invaders.
+    pub sound1: u8,
+    /// Last value written to the second sound port, 5 on Space Invaders.
+    pub sound2: u8,
+    /// Which ports the game uses for what.
+    ports: PortLayout,
+    /// The 16 bit shift register, written a byte at a time through port 4.
+    shift_register: u16,
+    /// Shift amount written through port 2.
+    shift_offset: u8,
+    /// Bits of the sound ports that went high since `take_sound_ports`.
+    sound_rose: [u8; 2],
+    /// Whether the game wrote to the watchdog port since the last frame.
+    watchdog_kicked: bool,
+}
+
+impl SpaceInvadersIo {
+    /// The I/O of a board running `game`, with nothing pressed.
+    pub fn new(game: &GameDefinition) -> Self {
+        Self {
+            port1: 0,
+            port2: 0,
+            dip_switches: DipSwitches::new(game.dip_switches),
+            sound1: 0,
+            sound2: 0,
+            ports: game.ports,
+            shift_register: 0,
+            shift_offset: 0,
+            sound_rose: [0; 2],
+            watchdog_kicked: false,
+        }
+    }
+
+    /// Returns the sound latches and the bits that went high since the
+    /// previous call.
+    pub fn take_sound_ports(&mut self) -> SoundPorts {
+        let [rose3, rose5] = std::mem::take(&mut self.sound_rose);
+        SoundPorts {
+            port3: self.sound1,
+            port5: self.sound2,
+            rose3,
+            rose5,
+        }
+    }
+}
+
+impl Default for SpaceInvadersIo {
+    /// The I/O of Space Invaders.
+    fn default() -> Self {
+        SpaceInvadersIo::new(&games::INVADERS)
+    }
+}
+
+impl IoDevices for SpaceInvadersIo {
+    fn input(&mut self, port: u8) -> u8 {
+        let ports = &self.ports;
+        if port == ports.shift_result {
+            return (self.shift_register >> (8 - self.shift_offset)) as u8;
+        }
+        match port {
+            // the player 1 controls are wired to port 0 as well
+            0 => ports.always_set[0] | (self.port1 & (P1_FIRE | P1_LEFT | P1_RIGHT)),
+            1 => self.port1 | ports.always_set[1],
+            2 => self.port2 | ports.always_set[2] | self.dip_switches.port2_bits(),
+            _ => 0,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        let ports = &self.ports;
+        if port == ports.shift_count {
+            self.shift_offset = value & 0x7;
+        } else if port == ports.shift_data {
+            self.shift_register = ((value as u16) << 8) | (self.shift_register >> 8);
+        } else if port == ports.sound[0] {
+            self.sound_rose[0] |= value & !self.sound1;
+            self.sound1 = value;
+        } else if port == ports.sound[1] {
+            self.sound_rose[1] |= value & !self.sound2;
+            self.sound2 = value;
+        } else if port == ports.watchdog {
+            self.watchdog_kicked = true;
+        }
+    }
+}
+
+/// A CPU reset by the watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogReset {
+    /// The frame at whose end the watchdog fired.
+    pub frame: u64,
+    /// Where the CPU was when it was reset.
+    pub pc: u16,
+}
+
+/// The watchdog timer of the board, which resets the CPU when the game
+/// stops writing to OUT 6.
+///
+/// Emulation bugs that hang the ROM would otherwise just freeze the
+/// screen; with the watchdog they show up as resets, which the frontend
+/// reports from `resets` and `last_reset`.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    timeout_frames: u32,
+    frames_since_kick: u32,
+    resets: u64,
+    last_reset: Option<WatchdogReset>,
+}
+
+impl Watchdog {
+    /// Creates a watchdog that fires after `timeout_frames` frames without
+    /// a kick. The board's timeout is `WATCHDOG_FRAMES`.
+    pub fn new(timeout_frames: u32) -> Self {
+        Self {
+            timeout_frames,
+            frames_since_kick: 0,
+            resets: 0,
+            last_reset: None,
+        }
+    }
+
+    /// Frames without a kick after which the watchdog fires.
+    pub fn timeout_frames(&self) -> u32 {
+        self.timeout_frames
+    }
+
+    /// Number of times the watchdog has reset the CPU.
+    pub fn resets(&self) -> u64 {
+        self.resets
+    }
+
+    /// The latest reset, if there was any.
+    pub fn last_reset(&self) -> Option<WatchdogReset> {
+        self.last_reset
+    }
+
+    /// Counts a frame, returning true if the watchdog fires.
+    fn tick(&mut self, kicked: bool) -> bool {
+        if kicked {
+            self.frames_since_kick = 0;
+            return false;
+        }
+        self.frames_since_kick += 1;
+        if self.frames_since_kick < self.timeout_frames {
+            return false;
+        }
+        self.frames_since_kick = 0;
+        self.resets += 1;
+        true
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(WATCHDOG_FRAMES)
+    }
+}
+
+/// The memory map of the board running `game`: the ROM at 0x0000-0x1fff
+/// and the chips of the game, RAM at 0x2000-0x3fff and nothing elsewhere.
+fn memory_map(game: &GameDefinition) -> MemoryMap {
+    let mut map = MemoryMap::unmapped();
+    map.map(0, ROM_SIZE, Page::Rom);
+    map.map(RAM_START as u16, RAM_END - RAM_START, Page::Ram);
+    for chip in game.roms {
+        map.map(chip.address, chip.size as usize, Page::Rom);
+    }
+    map
+}
+
+/// The Space Invaders board: an 8080 with 8K ROM, 1K work RAM,
+/// 7K video RAM and the I/O around it.
+///
+/// The other games of the board run on it too, with the ROM layout and
+/// port map of their `GameDefinition`.
+#[derive(Debug, Clone)]
+pub struct SpaceInvaders {
+    pub cpu: Emulator8080,
+    pub io: SpaceInvadersIo,
+    game: &'static GameDefinition,
+    /// Frames emulated since power on.
+    pub frame: u64,
+    /// Cycles run since the start of the current frame.
+    frame_cycles: u32,
+    /// The watchdog, if enforced. Like the DIP switches it is not part of
+    /// the save state.
+    pub watchdog: Option<Watchdog>,
+    /// High score waiting for attract mode to be written to RAM.
+    pending_high_score: Option<u16>,
+}
+
+impl SpaceInvaders {
+    /// Creates a machine with `rom` (the h, g, f and e chips in that order)
+    /// loaded at address 0, ready to run from power on.
+    pub fn new(rom: &[u8]) -> Self {
+        SpaceInvaders::with_game(&games::INVADERS, rom)
+    }
+
+    /// Creates a machine running `game`, with `rom` holding its chips one
+    /// after the other in the order of `GameDefinition::roms`. A short
+    /// image leaves the last chips empty.
+    pub fn with_game(game: &'static GameDefinition, rom: &[u8]) -> Self {
+        assert!(
+            rom.len() <= game.rom_size(),
+            "ROM image is bigger than the chips of {}.",
+            game.name
+        );
+        let mut cpu = Emulator8080::empty();
+        cpu.memory_map = memory_map(game);
+        let mut rest = rom;
+        for chip in game.roms {
+            let (bytes, tail) = rest.split_at(rest.len().min(chip.size as usize));
+            let address = chip.address as usize;
+            cpu.memory[address..address + bytes.len()].copy_from_slice(bytes);
+            rest = tail;
+        }
+        Self {
+            cpu,
+            io: SpaceInvadersIo::new(game),
+            game,
+            frame: 0,
+            frame_cycles: 0,
+            watchdog: None,
+            pending_high_score: None,
+        }
+    }
+
+    /// The game the machine runs.
+    pub fn game(&self) -> &'static GameDefinition {
+        self.game
+    }
+
+    /// Puts the machine back in its power on state.
+    ///
+    /// The ROM, DIP switches and breakpoints are kept, RAM, registers and
+    /// I/O are cleared, and a high score not restored yet is dropped.
+    pub fn power_on(&mut self) {
+        let old = std::mem::replace(&mut self.cpu, Emulator8080::empty());
+        self.cpu.memory_map = old.memory_map.clone();
+        for chip in self.game.roms {
+            let range = chip.address as usize..(chip.address + chip.size) as usize;
+            self.cpu.memory[range.clone()].copy_from_slice(&old.memory[range]);
+        }
+        for &address in old.breakpoints() {
+            self.cpu.set_breakpoint(address);
+        }
+        self.io = SpaceInvadersIo {
+            dip_switches: self.io.dip_switches,
+            ..SpaceInvadersIo::new(self.game)
+        };
+        self.frame = 0;
+        self.frame_cycles = 0;
+        self.pending_high_score = None;
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.frames_since_kick = 0;
+        }
+    }
+
+    /// Runs one instruction with `Emulator8080::step` and advances the
+    /// video timing by the cycles it took.
+    ///
+    /// The video hardware raises RST 1 when the beam reaches the middle of
+    /// the screen and RST 2 at the start of vertical blank, after the
+    /// instruction during which the beam got there. Cycles run past the
+    /// end of a frame count towards the next one, so the interrupts stay
+    /// in step with the video timing.
+    ///
+    /// An instruction that caused a bus fault has run, so its cycles count
+    /// too.
+    pub fn step(&mut self) -> Result<StepOutcome, CpuError> {
+        let result = self.cpu.step(&mut self.io);
+        if let Ok(
+            StepOutcome::Executed { cycles }
+            | StepOutcome::Halted { cycles }
+            | StepOutcome::Interrupt { cycles, .. },
+        )
+        | Err(CpuError::BusFault { cycles, .. }) = result
+        {
+            self.advance(cycles);
+        }
+        result
+    }
+
+    /// Emulates the rest of the current video frame.
+    ///
+    /// Like `Emulator8080::emulate_instruction` this does not stop at
+    /// breakpoints and runs undocumented opcodes even when strict.
+    pub fn run_frame(&mut self) {
+        let frame = self.frame;
+        while self.frame == frame {
+            let cycles = self.cpu.emulate_instruction(&mut self.io);
+            self.advance(cycles);
+        }
+    }
+
+    /// Advances the video timing by `cycles`, raising the interrupts the
+    /// beam gets to.
+    fn advance(&mut self, cycles: u32) {
+        let before = self.frame_cycles;
+        self.frame_cycles += cycles;
+        if before < CYCLES_PER_FRAME / 2 && self.frame_cycles >= CYCLES_PER_FRAME / 2 {
+            self.cpu.interrupt(1);
+        }
+        if self.frame_cycles >= CYCLES_PER_FRAME {
+            self.frame_cycles -= CYCLES_PER_FRAME;
+            self.cpu.interrupt(2);
+            self.end_frame();
+        }
+    }
+
+    /// Cycles run since the start of the current frame.
+    pub fn frame_cycles(&self) -> u32 {
+        self.frame_cycles
+    }
+
+    /// Counts the frame that just ended and runs what happens once a
+    /// frame: the watchdog and the pending high score.
+    fn end_frame(&mut self) {
+        self.frame += 1;
+
+        let kicked = std::mem::take(&mut self.io.watchdog_kicked);
+        if let Some(watchdog) = &mut self.watchdog {
+            if watchdog.tick(kicked) {
+                watchdog.last_reset = Some(WatchdogReset {
+                    frame: self.frame,
+                    pc: self.cpu.pc,
+                });
+                self.cpu.reset();
+            }
+        }
+
+        // the game has initialized its RAM by the end of the first frame
+        if let (Some(score), Some(ram)) = (self.pending_high_score, self.game.score_ram) {
+            if self.in_attract_mode() {
+                self.pending_high_score = None;
+                let [high, low] = to_bcd(score);
+                self.cpu.memory[ram.high_score] = low;
+                self.cpu.memory[ram.high_score + 1] = high;
+            }
+        }
+    }
+
+    /// Whether the game is in attract mode rather than being played,
+    /// always false for games without `GameDefinition::score_ram`.
+    pub fn in_attract_mode(&self) -> bool {
+        self.game
+            .score_ram
+            .is_some_and(|ram| self.cpu.memory[ram.game_mode] == 0)
+    }
+
+    /// The high score in RAM, `None` if it does not hold valid BCD or the
+    /// game has no known `GameDefinition::score_ram`.
+    pub fn high_score(&self) -> Option<u16> {
+        let ram = self.game.score_ram?;
+        from_bcd([
+            self.cpu.memory[ram.high_score + 1],
+            self.cpu.memory[ram.high_score],
+        ])
+    }
+
+    /// Sets the high score, typically one saved by an earlier session.
+    ///
+    /// Writing RAM behind the game's back while it is played could upset
+    /// it, so the score is written at the end of the first frame that is
+    /// in attract mode. The screen shows it from its next redraw of the
+    /// scores. Games without a known `GameDefinition::score_ram` ignore
+    /// it.
+    pub fn restore_high_score(&mut self, score: u16) {
+        assert!(score <= MAX_SCORE, "High score has more than 4 digits.");
+        self.pending_high_score = Some(score);
+    }
+
+    /// The work and video RAM.
+    pub fn ram(&self) -> &[u8] {
+        &self.cpu.memory[RAM_START..RAM_END]
+    }
+
+    /// The video RAM, 1 bit per pixel, 32 bytes per column of the
+    /// (unrotated) 256x224 screen.
+    pub fn vram(&self) -> &[u8] {
+        &self.cpu.memory[VRAM_START..RAM_END]
+    }
+
+    /// A 64 bit FNV-1a hash of the work and video RAM.
+    ///
+    /// Two runs that produce the same hash for a frame have, for all
+    /// practical purposes, drawn the same screen with the same game state.
+    pub fn frame_hash(&self) -> u64 {
+        self.ram()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+}
+
+/// Converts `value` (at most 9999) to 4 BCD digits, most significant byte
+/// first.
+fn to_bcd(value: u16) -> [u8; 2] {
+    let digits = [value / 1000, value / 100 % 10, value / 10 % 10, value % 10];
+    [
+        (digits[0] << 4 | digits[1]) as u8,
+        (digits[2] << 4 | digits[3]) as u8,
+    ]
+}
+
+/// Converts 4 BCD digits, most significant byte first, to their value.
+pub fn from_bcd(bytes: [u8; 2]) -> Option<u16> {
+    bytes.iter().try_fold(0, |value, &byte| {
+        let (high, low) = (byte >> 4, byte & 0x0f);
+        if high > 9 || low > 9 {
+            return None;
+        }
+        Some(value * 100 + high as u16 * 10 + low as u16)
+    })
+}
+
+/// Version of the `SaveState` layout of `SpaceInvaders`, bumped whenever
+/// the layout changes.
+const STATE_VERSION: u8 = 2;
+/// Number of bytes the board takes up in a save state, after the CPU.
+const BOARD_STATE_SIZE: usize = 24;
+
+impl SaveState for SpaceInvaders {
+    /// Layout: `STATE_VERSION`, the CPU state, port 1 and 2, sound ports 3
+    /// and 5 and their bits that rose since `take_sound_ports`, the shift
+    /// register (little endian) and offset, whether the watchdog was
+    /// kicked this frame, the frame counter, the cycles into the current
+    /// frame and the high score waiting to be restored (0xffff for none).
+    fn save_state(&self, out: &mut Vec<u8>) {
+        out.push(STATE_VERSION);
+        self.cpu.save_state(out);
+        out.extend_from_slice(&[self.io.port1, self.io.port2, self.io.sound1, self.io.sound2]);
+        out.extend_from_slice(&self.io.sound_rose);
+        out.extend_from_slice(&self.io.shift_register.to_le_bytes());
+        out.push(self.io.shift_offset);
+        out.push(self.io.watchdog_kicked as u8);
+        out.extend_from_slice(&self.frame.to_le_bytes());
+        out.extend_from_slice(&self.frame_cycles.to_le_bytes());
+        out.extend_from_slice(&self.pending_high_score.unwrap_or(0xffff).to_le_bytes());
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<usize, StateError> {
+        // checked before anything is loaded, so a bad state leaves the
+        // machine as it was
+        let cpu_size = self.cpu.state_size();
+        let needed = 1 + cpu_size + BOARD_STATE_SIZE;
+        if state.len() < needed {
+            return Err(StateError::Truncated {
+                needed,
+                available: state.len(),
+            });
+        }
+        if state[0] != STATE_VERSION {
+            return Err(StateError::Version {
+                expected: STATE_VERSION,
+                found: state[0],
+            });
+        }
+        self.cpu.load_state(&state[1..])?;
+        let io = &state[1 + cpu_size..needed];
+        self.io.port1 = io[0];
+        self.io.port2 = io[1];
+        self.io.sound1 = io[2];
+        self.io.sound2 = io[3];
+        self.io.sound_rose = [io[4], io[5]];
+        self.io.shift_register = u16::from_le_bytes([io[6], io[7]]);
+        self.io.shift_offset = io[8] & 0x7;
+        self.io.watchdog_kicked = io[9] != 0;
+        self.frame = u64::from_le_bytes(io[10..18].try_into().expect("slice is 8 bytes"));
+        self.frame_cycles = u32::from_le_bytes(io[18..22].try_into().expect("slice is 4 bytes"));
+        self.pending_high_score = match u16::from_le_bytes([io[22], io[23]]) {
+            score if score <= MAX_SCORE => Some(score),
+            _ => None,
+        };
+        Ok(needed)
+    }
+}
@@ -1,19 +1,722 @@
-use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-mod disassembler;
-mod emulator;
-mod instructions;
-mod io_spainem;
+use space_invaders_emulator::cli::{
+    self, apply_patch, expect_positionals, find_game, load_script, positional, wants_help, Args,
+    CliError, DebugOptions, MachineOptions, DEBUG_OPTIONS, MACHINE_OPTIONS,
+};
+use space_invaders_emulator::debugger::{self, TraceError};
+use space_invaders_emulator::games;
+use space_invaders_emulator::input::InputMap;
+use space_invaders_emulator::io_spainem::{self, FileError};
+use space_invaders_emulator::machine::{SpaceInvaders, Watchdog};
+use space_invaders_emulator::movie::{Movie, MovieError, MovieRecorder};
+use space_invaders_emulator::romdb::{self, Verdict};
+use space_invaders_emulator::sound::{
+    SampleClock, SampleMixer, SampleSet, SoundGenerator, SoundMap, OUTPUT_SAMPLE_RATE,
+};
+use space_invaders_emulator::synth::Synthesizer;
+use space_invaders_emulator::timing::{self, FrameLimiter};
+use space_invaders_emulator::wav::{WavError, WavWriter};
+use space_invaders_emulator::{disassembler, terminal, video};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    assert!(args.len() == 2, "No file provided");
-    let op_buffer = io_spainem::read_from_file(&args[1]);
-    // let op_buffer_test: Vec<u8> = vec![0x11, 0x23, 0x32];
-    let num_bytes = u16::try_from(op_buffer.len()).expect("Binary file is too big.");
-    let mut pc: u16 = 0;
+const USAGE: &str = "\
+Usage: space-invaders-emulator <command> [options]
 
-    while pc < num_bytes {
-        pc += disassembler::disassemble_8080_op(&op_buffer, pc as usize)
+Commands:
+  run <rom>                    Play the game, or run it headless
+  screenshot <rom> <file.bmp>  Run the game and save the screen
+  trace <rom>                  Print every instruction the CPU runs
+  debug <rom>                  Step through the game from a prompt
+  replay <rom> <movie>         Replay a movie and check it ends in sync
+  disasm <file>                Disassemble a binary
+  verify-rom <rom>             Check that a ROM can be loaded
+  games                        List the games that can be run with --game
+
+<rom> is an image holding all the chips of the game, or a directory or .zip
+archive holding the separate chips: invaders.h, .g, .f and .e for Space
+Invaders.
+Run `space-invaders-emulator <command> --help` for the options of a command.
+
+Exit codes: 0 success, 1 command failed, 2 bad usage, 3 file could not be
+read or written, 4 file has invalid contents.
+";
+
+const RUN_USAGE: &str = "\
+Usage: space-invaders-emulator run <rom> [options]
+
+Runs the game paced to 60 Hz, controlled by commands on stdin, in the
+terminal with --terminal, or as fast as possible with --headless.
+
+Options:
+  --headless             Run without display or throttling, needs --frames
+  --terminal             Play in the terminal (at least 112x65, needs stty)
+  --frames <N>           Stop after N frames
+  --speed <X>            Speed multiplier, 0.25 to 8
+  --input <script>       Feed the inputs from an input script
+  --high-score <file>    Restore the high score from and save it to file
+  --screenshot <file>    Save the screen at the end of a headless run
+  --dump-ram <file>      Save the RAM at the end of a headless run
+  --audio-out <file>     Record the sound of a headless run as WAV
+  --record <file>        Record the inputs of a headless run as a movie
+  --samples <dir>        Sound samples 0.wav to 9.wav for --audio-out
+";
+
+const SCREENSHOT_USAGE: &str = "\
+Usage: space-invaders-emulator screenshot <rom> <file.bmp> [options]
+
+Runs the game headless and saves the screen as a BMP image.
+
+Options:
+  --frames <N>      Frames to run first, 600 by default
+  --input <script>  Feed the inputs from an input script
+";
+
+const TRACE_USAGE: &str = "\
+Usage: space-invaders-emulator trace <rom> [options]
+
+Prints every instruction the CPU runs with the registers before it: the
+frame, the address, the instruction, A, the flags, BC, DE, HL and SP.
+
+Options:
+  --from <N>        Run N frames before tracing, 0 by default
+  --frames <N>      Frames to trace, 1 by default
+  --input <script>  Feed the inputs from an input script
+  --strict          Stop at undocumented opcodes instead of running them
+  --out <file>      Write the trace to file instead of stdout
+";
+
+const DEBUG_USAGE: &str = "\
+Usage: space-invaders-emulator debug <rom> [options]
+
+Runs the game under a debugger controlled from a prompt, see `h` there.
+The `debugger` binary runs the same debugger.
+";
+
+const REPLAY_USAGE: &str = "\
+Usage: space-invaders-emulator replay <rom> <movie> [options]
+
+Replays a movie recorded with `run --headless --record` and checks that the
+machine ends up in the recorded state. The DIP switches are those of the
+recording.
+";
+
+const DISASM_USAGE: &str = "\
+Usage: space-invaders-emulator disasm <file> [options]
+
+Prints every instruction of the binary with its address.
+
+Options:
+  --patch <file>  Apply an IPS or BPS patch and print only the instructions
+                  it changes, before (-) and after (+)
+";
+
+const VERIFY_ROM_USAGE: &str = "\
+Usage: space-invaders-emulator verify-rom <rom> [options]
+
+Checks every chip of the ROM against the known dumps: whether it is
+good, modified, a bad dump, missing or the wrong size. Without --game the
+game is identified from the chips, and a hint is printed when they look
+like another game than the one given.
+
+Options:
+  --game <name>  Game on the ROM
+";
+
+const GAMES_USAGE: &str = "\
+Usage: space-invaders-emulator games
+
+Lists the games of the board with their ROM chips and DIP switches.
+";
+
+#[derive(Default)]
+struct RunOptions {
+    machine: MachineOptions,
+    headless: bool,
+    terminal: bool,
+    frames: Option<u64>,
+    speed: f64,
+    input_script: Option<String>,
+    screenshot: Option<String>,
+    dump_ram: Option<String>,
+    audio_out: Option<String>,
+    samples_dir: Option<String>,
+    high_score_file: Option<String>,
+    movie: Option<String>,
+}
+
+impl RunOptions {
+    /// Parses the arguments of `run`, see `RUN_USAGE`.
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut options = RunOptions {
+            speed: 1.0,
+            ..RunOptions::default()
+        };
+        let mut positionals = Vec::new();
+        let mut args = Args::new(args);
+        while let Some(arg) = args.next() {
+            if options.machine.parse_option(arg, &mut args)? {
+                continue;
+            }
+            match arg {
+                "--headless" => options.headless = true,
+                "--terminal" => options.terminal = true,
+                "--frames" => options.frames = Some(args.number(arg)?),
+                "--speed" => {
+                    let speed: f64 = args.number(arg)?;
+                    if !(timing::MIN_SPEED..=timing::MAX_SPEED).contains(&speed) {
+                        return Err(CliError::Usage(format!(
+                            "--speed must be between {} and {}",
+                            timing::MIN_SPEED,
+                            timing::MAX_SPEED
+                        )));
+                    }
+                    options.speed = speed;
+                }
+                "--input" => options.input_script = Some(args.value(arg)?.to_owned()),
+                "--screenshot" => options.screenshot = Some(args.value(arg)?.to_owned()),
+                "--dump-ram" => options.dump_ram = Some(args.value(arg)?.to_owned()),
+                "--audio-out" => options.audio_out = Some(args.value(arg)?.to_owned()),
+                "--samples" => options.samples_dir = Some(args.value(arg)?.to_owned()),
+                "--high-score" => options.high_score_file = Some(args.value(arg)?.to_owned()),
+                "--record" => options.movie = Some(args.value(arg)?.to_owned()),
+                _ => positionals.push(positional(arg)?),
+            }
+        }
+        let [rom_path] = expect_positionals(positionals, "a ROM")?;
+        options.machine.rom_path = rom_path;
+        if options.headless && options.terminal {
+            return Err(CliError::Usage(
+                "--headless and --terminal can not be combined".to_owned(),
+            ));
+        }
+        if options.headless && options.frames.is_none() {
+            return Err(CliError::Usage("--headless needs --frames".to_owned()));
+        }
+        if options.movie.is_some() && !options.headless {
+            return Err(CliError::Usage("--record needs --headless".to_owned()));
+        }
+        if options.movie.is_some() && options.high_score_file.is_some() {
+            // movies start from power on, where no high score is restored
+            return Err(CliError::Usage(
+                "--record and --high-score can not be combined".to_owned(),
+            ));
+        }
+        Ok(options)
+    }
+
+    /// Creates the machine, with the high score restored from the high
+    /// score file.
+    fn create_machine(&self) -> Result<SpaceInvaders, CliError> {
+        let mut machine = self.machine.create_machine()?;
+        if let Some(file_path) = &self.high_score_file {
+            if let Some(score) = io_spainem::read_high_score(file_path)? {
+                machine.restore_high_score(score);
+            }
+        }
+        Ok(machine)
+    }
+
+    /// Saves the high score of `machine` to the high score file, unless
+    /// the file already holds a higher one.
+    fn save_high_score(&self, machine: &SpaceInvaders) -> Result<(), CliError> {
+        let Some(file_path) = &self.high_score_file else {
+            return Ok(());
+        };
+        let saved = io_spainem::read_high_score(file_path)?;
+        if let Some(score) = machine.high_score() {
+            if saved.is_none_or(|saved| score > saved) {
+                io_spainem::write_high_score(file_path, score)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The limiter for the real-time and terminal frontends.
+    fn frame_limiter(&self) -> FrameLimiter {
+        let mut limiter = FrameLimiter::new();
+        limiter.set_speed(self.speed);
+        limiter
+    }
+}
+
+/// Creates the sound generator for `--audio-out` for a game with `sound`:
+/// the samples in `samples_dir`, or the synthesizer when no samples are
+/// given.
+fn create_sound_generator(
+    samples_dir: Option<&str>,
+    sound: SoundMap,
+) -> Result<Box<dyn SoundGenerator>, CliError> {
+    let Some(dir) = samples_dir else {
+        if !sound.synthesized {
+            return Err(CliError::Usage(
+                "the game has no synthesized sound, --audio-out needs --samples".to_owned(),
+            ));
+        }
+        return Ok(Box::new(Synthesizer::new(OUTPUT_SAMPLE_RATE)));
+    };
+    let samples = SampleSet::load_dir(dir).map_err(|err| match err {
+        WavError::Io(err) => CliError::File(FileError::new(dir, err)),
+        err => CliError::invalid(dir, err),
+    })?;
+    Ok(Box::new(SampleMixer::new(
+        &samples,
+        sound,
+        OUTPUT_SAMPLE_RATE,
+    )))
+}
+
+/// Runs the machine without throttling or display and reports the speed.
+fn run_headless(options: &RunOptions) -> Result<(), CliError> {
+    let frames = options.frames.expect("checked by RunOptions::parse");
+    let script = load_script(options.input_script.as_deref())?;
+    let mut machine = options.create_machine()?;
+    let mut audio = match &options.audio_out {
+        Some(file_path) => {
+            let generator =
+                create_sound_generator(options.samples_dir.as_deref(), machine.game().sound)?;
+            let writer = WavWriter::create(file_path, OUTPUT_SAMPLE_RATE)
+                .map_err(|err| FileError::new(file_path, err))?;
+            Some((file_path, generator, writer))
+        }
+        None => None,
+    };
+    let mut recorder = options
+        .movie
+        .as_ref()
+        .map(|_| MovieRecorder::from_power_on(&mut machine));
+    let mut clock = SampleClock::new(OUTPUT_SAMPLE_RATE);
+    let mut buffer = Vec::new();
+
+    let start = Instant::now();
+    for frame in 0..frames {
+        script.apply(&mut machine, frame);
+        match &mut recorder {
+            Some(recorder) => recorder.run_frame(&mut machine),
+            None => machine.run_frame(),
+        }
+
+        if let Some((file_path, generator, writer)) = &mut audio {
+            buffer.resize(clock.samples_for_frame(), 0);
+            generator.update(machine.io.take_sound_ports());
+            generator.render(&mut buffer);
+            writer
+                .write_samples(&buffer)
+                .map_err(|err| FileError::new(file_path, err))?;
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{} frames in {:.3}s ({:.0} fps), frame hash {:016x}",
+        frames,
+        elapsed,
+        frames as f64 / elapsed,
+        machine.frame_hash()
+    );
+    report_watchdog(&machine);
+
+    if let Some(file_path) = &options.screenshot {
+        write_screenshot(file_path, &machine)?;
     }
+    if let Some(file_path) = &options.dump_ram {
+        io_spainem::write_file(file_path, machine.ram())?;
+    }
+    if let Some((file_path, _, writer)) = audio {
+        writer
+            .finish()
+            .map_err(|err| FileError::new(file_path, err))?;
+    }
+    if let (Some(file_path), Some(recorder)) = (&options.movie, recorder) {
+        io_spainem::write_file(file_path, &recorder.finish(&machine).to_bytes())?;
+    }
+    options.save_high_score(&machine)
+}
+
+/// Prints how often the watchdog reset the CPU, if it is enforced.
+fn report_watchdog(machine: &SpaceInvaders) {
+    let Some(watchdog) = &machine.watchdog else {
+        return;
+    };
+    match watchdog.last_reset() {
+        Some(reset) => println!(
+            "watchdog resets: {}, the last at frame {} (pc {:04x})",
+            watchdog.resets(),
+            reset.frame,
+            reset.pc
+        ),
+        None => println!("watchdog resets: 0"),
+    }
+}
+
+/// Forwards the lines typed on stdin, so the real-time loop can poll them.
+fn spawn_command_reader() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Runs the machine paced to real time, reporting the emulated frame rate
+/// every second.
+///
+/// Until there is a frontend, the loop is controlled by commands on stdin:
+/// `p` pauses or resumes, `n` advances one frame while paused, `s <speed>`
+/// changes the speed multiplier and `q` quits.
+fn run_realtime(options: &RunOptions) -> Result<(), CliError> {
+    let script = load_script(options.input_script.as_deref())?;
+    let mut machine = options.create_machine()?;
+    let mut limiter = options.frame_limiter();
+    let commands = spawn_command_reader();
+    println!("Commands: p (pause/resume), n (next frame), s <speed>, q (quit)");
+
+    let mut last_report = Instant::now();
+    'run: while options.frames.is_none_or(|frames| machine.frame < frames) {
+        while let Ok(command) = commands.try_recv() {
+            let mut words = command.split_whitespace();
+            match words.next() {
+                Some("p") => limiter.set_paused(!limiter.is_paused()),
+                Some("n") => limiter.advance_frame(),
+                Some("s") => match words.next().and_then(|speed| speed.parse().ok()) {
+                    Some(speed) => limiter.set_speed(speed),
+                    None => println!("Usage: s <speed>"),
+                },
+                Some("q") => break 'run,
+                _ => println!("Unknown command: {}", command),
+            }
+        }
+
+        if limiter.wait() {
+            let frame = machine.frame;
+            script.apply(&mut machine, frame);
+            let resets = machine.watchdog.as_ref().map(Watchdog::resets);
+            machine.run_frame();
+            if let Some(watchdog) = &machine.watchdog {
+                if let Some(reset) = watchdog
+                    .last_reset()
+                    .filter(|_| Some(watchdog.resets()) != resets)
+                {
+                    println!(
+                        "watchdog: no kick for {} frames, reset the CPU at frame {} (pc {:04x})",
+                        watchdog.timeout_frames(),
+                        reset.frame,
+                        reset.pc
+                    );
+                }
+            }
+        }
+
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            println!(
+                "frame {}: {:.1} fps at {}x{}",
+                machine.frame,
+                limiter.fps(),
+                limiter.speed(),
+                if limiter.is_paused() { " (paused)" } else { "" }
+            );
+            last_report = Instant::now();
+        }
+    }
+    options.save_high_score(&machine)
+}
+
+/// Plays the game in the terminal with the keys from the config file.
+fn run_terminal(options: &RunOptions) -> Result<(), CliError> {
+    let config = options.machine.load_config()?;
+    let input_map = InputMap::from_config(&config).map_err(|err| {
+        CliError::invalid(options.machine.config.as_deref().unwrap_or_default(), err)
+    })?;
+    let mut machine = options.create_machine()?;
+    let mut limiter = options.frame_limiter();
+    terminal::run(&mut machine, &mut limiter, &input_map)
+        .map_err(|err| CliError::Failed(format!("terminal frontend failed: {}", err)))?;
+    report_watchdog(&machine);
+    options.save_high_score(&machine)
+}
+
+fn command_run(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}\n{}", RUN_USAGE, MACHINE_OPTIONS);
+        return Ok(());
+    }
+    let options = RunOptions::parse(args)?;
+    if options.headless {
+        run_headless(&options)
+    } else if options.terminal {
+        run_terminal(&options)
+    } else {
+        run_realtime(&options)
+    }
+}
+
+fn write_screenshot(file_path: &str, machine: &SpaceInvaders) -> Result<(), CliError> {
+    video::write_screenshot(file_path, machine.vram())
+        .map_err(|err| CliError::File(FileError::new(file_path, err)))
+}
+
+fn command_screenshot(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}\n{}", SCREENSHOT_USAGE, MACHINE_OPTIONS);
+        return Ok(());
+    }
+    let mut machine_options = MachineOptions::default();
+    let mut frames = 600;
+    let mut input_script = None;
+    let mut positionals = Vec::new();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        if machine_options.parse_option(arg, &mut args)? {
+            continue;
+        }
+        match arg {
+            "--frames" => frames = args.number(arg)?,
+            "--input" => input_script = Some(args.value(arg)?),
+            _ => positionals.push(positional(arg)?),
+        }
+    }
+    let [rom_path, file_path] = expect_positionals(positionals, "a ROM and an image file")?;
+    machine_options.rom_path = rom_path;
+
+    let script = load_script(input_script)?;
+    let mut machine = machine_options.create_machine()?;
+    for frame in 0..frames {
+        script.apply(&mut machine, frame);
+        machine.run_frame();
+    }
+    write_screenshot(&file_path, &machine)
+}
+
+fn command_trace(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}\n{}", TRACE_USAGE, MACHINE_OPTIONS);
+        return Ok(());
+    }
+    let mut machine_options = MachineOptions::default();
+    let mut from: u64 = 0;
+    let mut frames: u64 = 1;
+    let mut input_script = None;
+    let mut strict = false;
+    let mut out_path = None;
+    let mut positionals = Vec::new();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        if machine_options.parse_option(arg, &mut args)? {
+            continue;
+        }
+        match arg {
+            "--from" => from = args.number(arg)?,
+            "--frames" => frames = args.number(arg)?,
+            "--input" => input_script = Some(args.value(arg)?),
+            "--strict" => strict = true,
+            "--out" => out_path = Some(args.value(arg)?),
+            _ => positionals.push(positional(arg)?),
+        }
+    }
+    let [rom_path] = expect_positionals(positionals, "a ROM")?;
+    machine_options.rom_path = rom_path;
+
+    let script = load_script(input_script)?;
+    let mut machine = machine_options.create_machine()?;
+    machine.cpu.strict = strict;
+    let (mut out, out_name): (Box<dyn Write>, &str) = match out_path {
+        Some(file_path) => {
+            let file = File::create(file_path).map_err(|err| FileError::new(file_path, err))?;
+            (Box::new(BufWriter::new(file)), file_path)
+        }
+        None => (Box::new(BufWriter::new(io::stdout().lock())), "stdout"),
+    };
+    debugger::trace(&mut machine, &script, from, frames, &mut out).map_err(|err| match err {
+        TraceError::Io(err) => CliError::File(FileError::new(out_name, err)),
+        err => CliError::Failed(err.to_string()),
+    })
+}
+
+fn command_debug(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!(
+            "{}\n{}\n{}\n{}",
+            DEBUG_USAGE,
+            DEBUG_OPTIONS,
+            MACHINE_OPTIONS,
+            debugger::COMMANDS
+        );
+        return Ok(());
+    }
+    DebugOptions::parse(args)?.run()
+}
+
+fn command_replay(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}\n{}", REPLAY_USAGE, MACHINE_OPTIONS);
+        return Ok(());
+    }
+    let mut machine_options = MachineOptions::default();
+    let mut positionals = Vec::new();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        if !machine_options.parse_option(arg, &mut args)? {
+            positionals.push(positional(arg)?);
+        }
+    }
+    let [rom_path, movie_path] = expect_positionals(positionals, "a ROM and a movie")?;
+    machine_options.rom_path = rom_path;
+
+    let bytes = io_spainem::read_from_file(&movie_path)?;
+    let movie = Movie::from_bytes(&bytes).map_err(|err| CliError::invalid(&movie_path, err))?;
+    let mut machine = machine_options.create_machine()?;
+    movie.replay(&mut machine).map_err(|err| match err {
+        MovieError::Desync { .. } => CliError::Failed(format!("{}: {}", movie_path, err)),
+        err => CliError::invalid(&movie_path, err),
+    })?;
+    println!(
+        "{}: {} frames replayed, frame hash {:016x}",
+        movie_path,
+        movie.frames.len(),
+        machine.frame_hash()
+    );
+    Ok(())
+}
+
+fn command_disasm(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}", DISASM_USAGE);
+        return Ok(());
+    }
+    let mut patch_path = None;
+    let mut positionals = Vec::new();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--patch" => patch_path = Some(args.value(arg)?),
+            _ => positionals.push(positional(arg)?),
+        }
+    }
+    let [file_path] = expect_positionals(positionals, "a file")?;
+    let buffer = io_spainem::read_from_file(&file_path)?;
+    match patch_path {
+        Some(patch_path) => {
+            let patched = apply_patch(patch_path, &buffer)?;
+            if disassembler::disassemble_diff(&buffer, &patched) == 0 {
+                println!("{}: the patch changes nothing", patch_path);
+            }
+        }
+        None => disassembler::disassemble(&buffer),
+    }
+    Ok(())
+}
+
+fn command_verify_rom(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}", VERIFY_ROM_USAGE);
+        return Ok(());
+    }
+    let mut game = None;
+    let mut positionals = Vec::new();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--game" => game = Some(args.value(arg)?),
+            _ => positionals.push(positional(arg)?),
+        }
+    }
+    let [rom_path] = expect_positionals(positionals, "a ROM")?;
+
+    let dump = io_spainem::read_rom_dump(&rom_path)?;
+    let identified = romdb::identify(&dump);
+    let game = match game {
+        Some(name) => find_game(Some(name))?,
+        None => identified.unwrap_or(&games::INVADERS),
+    };
+    let report = romdb::check(game, &dump);
+    for check in &report.chips {
+        println!("{}", check);
+    }
+    if report.extra_bytes > 0 {
+        println!("{} bytes past the last chip", report.extra_bytes);
+    }
+    if let Some(other) = identified.filter(|&other| other != game) {
+        println!(
+            "the chips look like {} ({}), try --game {}",
+            other.title, other.name, other.name
+        );
+    }
+
+    let parent = match game.parent {
+        Some(parent) => format!(", clone of {}", parent),
+        None => String::new(),
+    };
+    let summary = format!(
+        "{}: {} ({}{}): {}",
+        rom_path,
+        game.title,
+        game.name,
+        parent,
+        report.verdict()
+    );
+    match report.verdict() {
+        Verdict::Good | Verdict::Unverified | Verdict::Modified => {
+            println!("{}", summary);
+            Ok(())
+        }
+        Verdict::BadDump | Verdict::WrongLayout => Err(CliError::Invalid(summary)),
+    }
+}
+
+fn command_games(args: &[String]) -> Result<(), CliError> {
+    if wants_help(args) {
+        print!("{}", GAMES_USAGE);
+        return Ok(());
+    }
+    if let Some(arg) = args.first() {
+        return Err(CliError::Usage(format!("unexpected argument `{}`", arg)));
+    }
+    for game in games::GAMES {
+        println!("{:<10}{} ({})", game.name, game.title, game.year);
+        let chips: Vec<String> = game
+            .roms
+            .iter()
+            .map(|chip| format!("{} at {:04x}", chip.file, chip.address))
+            .collect();
+        println!("          chips: {}", chips.join(", "));
+        for setting in game.dip_switches {
+            println!("          --dip {}=<{}>", setting.name, setting.expected);
+        }
+    }
+    Ok(())
+}
+
+fn run_command(args: &[String]) -> Result<(), CliError> {
+    let Some(command) = args.first() else {
+        eprint!("{}", USAGE);
+        return Err(CliError::Usage("no command given".to_owned()));
+    };
+    let args = &args[1..];
+    match command.as_str() {
+        "run" => command_run(args),
+        "screenshot" => command_screenshot(args),
+        "trace" => command_trace(args),
+        "debug" => command_debug(args),
+        "replay" => command_replay(args),
+        "disasm" => command_disasm(args),
+        "verify-rom" => command_verify_rom(args),
+        "games" => command_games(args),
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(CliError::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    cli::exit(run_command(&args))
 }
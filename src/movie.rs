@@ -0,0 +1,324 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::machine::SpaceInvaders;
+use crate::snapshot::{self, SaveState, StateError};
+
+/// Magic bytes at the start of a movie file.
+const MAGIC: &[u8; 4] = b"SIMV";
+/// Version of the movie file layout.
+const VERSION: u8 = 2;
+
+/// Error returned when a movie can not be loaded or does not replay.
+#[derive(Debug)]
+pub enum MovieError {
+    Io(io::Error),
+    /// The file does not start with the movie magic bytes.
+    NotAMovie,
+    /// The file was written by a newer version of the emulator.
+    UnsupportedVersion(u8),
+    /// The file ended in the middle of a field.
+    Truncated,
+    /// The start kind byte is neither power on nor save state.
+    UnknownStart(u8),
+    /// The embedded start state could not be restored.
+    State(StateError),
+    /// The machine state after the last frame differs from the recording.
+    Desync {
+        frames: usize,
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Io(err) => write!(f, "{}", err),
+            MovieError::NotAMovie => write!(f, "not a movie file"),
+            MovieError::UnsupportedVersion(version) => {
+                write!(f, "unsupported movie version {}", version)
+            }
+            MovieError::Truncated => write!(f, "movie file is truncated"),
+            MovieError::UnknownStart(kind) => write!(f, "unknown movie start kind {}", kind),
+            MovieError::State(err) => write!(f, "bad start state: {}", err),
+            MovieError::Desync {
+                frames,
+                expected,
+                found,
+            } => write!(
+                f,
+                "replay desynced after {} frames: expected hash {:016x}, got {:016x}",
+                frames, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl From<io::Error> for MovieError {
+    fn from(err: io::Error) -> Self {
+        MovieError::Io(err)
+    }
+}
+
+impl From<StateError> for MovieError {
+    fn from(err: StateError) -> Self {
+        MovieError::State(err)
+    }
+}
+
+/// The input port values held during one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameInput {
+    pub port1: u8,
+    pub port2: u8,
+}
+
+/// Where the recording of a movie started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPoint {
+    /// A freshly powered on machine.
+    PowerOn,
+    /// A save state of the machine.
+    SaveState(Vec<u8>),
+}
+
+/// A recording of the inputs of a run, replayable frame by frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub start: StartPoint,
+    /// The DIP switches of the recording, see `DipSwitches::port2_bits`.
+    pub dip_switches: u8,
+    pub frames: Vec<FrameInput>,
+    /// `SpaceInvaders::frame_hash` after the last frame.
+    pub final_hash: u64,
+}
+
+impl Movie {
+    /// Replays the movie on `machine` and checks that it ends up in the
+    /// recorded state.
+    ///
+    /// The machine must have the same ROM loaded as when recording; its
+    /// DIP switches are set to the recorded ones.
+    pub fn replay(&self, machine: &mut SpaceInvaders) -> Result<(), MovieError> {
+        machine.io.dip_switches.set_port2_bits(self.dip_switches);
+        match &self.start {
+            StartPoint::PowerOn => machine.power_on(),
+            StartPoint::SaveState(state) => {
+                machine.load_state(state)?;
+            }
+        }
+        for input in &self.frames {
+            machine.io.port1 = input.port1;
+            machine.io.port2 = input.port2;
+            machine.run_frame();
+        }
+        let found = machine.frame_hash();
+        if found != self.final_hash {
+            return Err(MovieError::Desync {
+                frames: self.frames.len(),
+                expected: self.final_hash,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    /// Serializes the movie.
+    ///
+    /// Layout (little endian): magic, version, DIP switches, start kind
+    /// (0 power on, 1 save state followed by its u32 length and bytes),
+    /// u32 frame count, port 1 and port 2 for every frame, and the u64
+    /// final hash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 + self.frames.len() * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.dip_switches);
+        match &self.start {
+            StartPoint::PowerOn => bytes.push(0),
+            StartPoint::SaveState(state) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(state.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(state);
+            }
+        }
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for input in &self.frames {
+            bytes.push(input.port1);
+            bytes.push(input.port2);
+        }
+        bytes.extend_from_slice(&self.final_hash.to_le_bytes());
+        bytes
+    }
+
+    /// Parses a movie written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(MovieError::NotAMovie);
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+        let dip_switches = reader.take(1)?[0];
+        let start = match reader.take(1)?[0] {
+            0 => StartPoint::PowerOn,
+            1 => {
+                let length = reader.u32()? as usize;
+                StartPoint::SaveState(reader.take(length)?.to_vec())
+            }
+            kind => return Err(MovieError::UnknownStart(kind)),
+        };
+        let frame_count = reader.u32()? as usize;
+        let frames = reader
+            .take(frame_count * 2)?
+            .chunks_exact(2)
+            .map(|ports| FrameInput {
+                port1: ports[0],
+                port2: ports[1],
+            })
+            .collect();
+        let final_hash = u64::from_le_bytes(reader.take(8)?.try_into().expect("took 8 bytes"));
+        Ok(Self {
+            start,
+            dip_switches,
+            frames,
+            final_hash,
+        })
+    }
+
+    /// Writes the movie to `file_path`.
+    pub fn save(&self, file_path: &str) -> io::Result<()> {
+        fs::write(file_path, self.to_bytes())
+    }
+
+    /// Reads a movie from `file_path`.
+    pub fn load(file_path: &str) -> Result<Self, MovieError> {
+        Movie::from_bytes(&fs::read(file_path)?)
+    }
+}
+
+/// Cursor over the bytes of a movie file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], MovieError> {
+        let end = self.pos.checked_add(length).ok_or(MovieError::Truncated)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(MovieError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, MovieError> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("took 4 bytes"),
+        ))
+    }
+}
+
+/// Records the inputs of a machine frame by frame.
+pub struct MovieRecorder {
+    start: StartPoint,
+    dip_switches: u8,
+    frames: Vec<FrameInput>,
+}
+
+impl MovieRecorder {
+    /// Powers `machine` on and starts recording from there.
+    pub fn from_power_on(machine: &mut SpaceInvaders) -> Self {
+        machine.power_on();
+        Self {
+            start: StartPoint::PowerOn,
+            dip_switches: machine.io.dip_switches.port2_bits(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Starts recording from the current state of `machine`.
+    pub fn from_current_state(machine: &SpaceInvaders) -> Self {
+        Self {
+            start: StartPoint::SaveState(snapshot::snapshot(machine)),
+            dip_switches: machine.io.dip_switches.port2_bits(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Runs one frame of `machine` with the inputs currently set on its
+    /// ports, recording them.
+    pub fn run_frame(&mut self, machine: &mut SpaceInvaders) {
+        self.frames.push(FrameInput {
+            port1: machine.io.port1,
+            port2: machine.io.port2,
+        });
+        machine.run_frame();
+    }
+
+    /// Stops recording, storing the hash of the current state of `machine`
+    /// to check replays against.
+    pub fn finish(self, machine: &SpaceInvaders) -> Movie {
+        Movie {
+            start: self.start,
+            dip_switches: self.dip_switches,
+            frames: self.frames,
+            final_hash: machine.frame_hash(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movie(start: StartPoint) -> Movie {
+        Movie {
+            start,
+            dip_switches: 0x03,
+            frames: vec![
+                FrameInput {
+                    port1: 0x09,
+                    port2: 0x00,
+                },
+                FrameInput {
+                    port1: 0x18,
+                    port2: 0x03,
+                },
+            ],
+            final_hash: 0x0123_4567_89ab_cdef,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for start in [StartPoint::PowerOn, StartPoint::SaveState(vec![1, 2, 3])] {
+            let movie = movie(start);
+            assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_start_kind() {
+        let mut bytes = movie(StartPoint::PowerOn).to_bytes();
+        bytes[MAGIC.len() + 2] = 2;
+        assert!(matches!(
+            Movie::from_bytes(&bytes),
+            Err(MovieError::UnknownStart(2))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let bytes = movie(StartPoint::SaveState(vec![1, 2, 3])).to_bytes();
+        assert!(matches!(
+            Movie::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(MovieError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+
+use crate::hash;
+
+/// Magic at the start of an IPS patch.
+const IPS_MAGIC: &[u8] = b"PATCH";
+/// Record offset that marks the end of an IPS patch.
+const IPS_EOF: &[u8] = b"EOF";
+/// Magic at the start of a BPS patch.
+const BPS_MAGIC: &[u8] = b"BPS1";
+/// Size of the three CRC-32s at the end of a BPS patch.
+const BPS_FOOTER_SIZE: usize = 12;
+/// Largest image a patch may produce, far more than any 8080 ROM.
+const MAX_TARGET_SIZE: usize = 0x100_0000;
+
+/// Error returned for a patch that can not be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// Neither an IPS nor a BPS patch.
+    UnknownFormat,
+    /// The patch ends in the middle of a record or command.
+    Truncated,
+    /// A BPS command reads outside the source or target.
+    OutOfBounds,
+    TooBig {
+        size: usize,
+    },
+    /// The ROM is not the one the BPS patch was made for.
+    WrongSource {
+        size: usize,
+        expected_size: usize,
+        crc32: u32,
+        expected_crc32: u32,
+    },
+    /// The BPS patch itself is damaged.
+    PatchChecksum,
+    /// The patched ROM does not match the checksum in the BPS patch.
+    TargetChecksum,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "not an IPS or BPS patch"),
+            PatchError::Truncated => write!(f, "patch ends too early"),
+            PatchError::OutOfBounds => write!(f, "patch copies from outside the ROM"),
+            PatchError::TooBig { size } => write!(f, "patch makes a {} byte image", size),
+            PatchError::WrongSource {
+                size,
+                expected_size,
+                crc32,
+                expected_crc32,
+            } => write!(
+                f,
+                "patch is for a {} byte ROM with crc32 {:08x}, not this {} byte ROM with crc32 {:08x}",
+                expected_size, expected_crc32, size, crc32
+            ),
+            PatchError::PatchChecksum => write!(f, "patch does not match its checksum"),
+            PatchError::TargetChecksum => {
+                write!(f, "patched ROM does not match the checksum in the patch")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Applies the IPS or BPS `patch` to `rom` and returns the patched ROM.
+pub fn apply(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(patch, rom)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(patch, rom)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+/// Applies an IPS patch: records of bytes to write at an offset, with a
+/// run length encoded form, up to an `EOF` marker optionally followed by
+/// the size to truncate the ROM to.
+pub fn apply_ips(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = Reader::new(patch);
+    if reader.take(IPS_MAGIC.len())? != IPS_MAGIC {
+        return Err(PatchError::UnknownFormat);
+    }
+    let mut out = rom.to_vec();
+    loop {
+        let offset = reader.take(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+        let size = reader.u16_be()? as usize;
+        let (size, bytes) = if size == 0 {
+            let count = reader.u16_be()? as usize;
+            (count, None)
+        } else {
+            (size, Some(reader.take(size)?))
+        };
+        let end = offset + size;
+        if end > MAX_TARGET_SIZE {
+            return Err(PatchError::TooBig { size: end });
+        }
+        if out.len() < end {
+            out.resize(end, 0);
+        }
+        match bytes {
+            Some(bytes) => out[offset..end].copy_from_slice(bytes),
+            None => out[offset..end].fill(reader.take(1)?[0]),
+        }
+    }
+    if let Ok(size) = reader.take(3) {
+        out.truncate(u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize);
+    }
+    Ok(out)
+}
+
+/// Applies a BPS patch, checking the CRC-32s of the ROM, of the patched
+/// ROM and of the patch.
+pub fn apply_bps(patch: &[u8], rom: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.len() < BPS_MAGIC.len() + BPS_FOOTER_SIZE {
+        return Err(PatchError::Truncated);
+    }
+    let (body, footer) = patch.split_at(patch.len() - BPS_FOOTER_SIZE);
+    let footer_crc = |index: usize| {
+        u32::from_le_bytes(
+            footer[index * 4..index * 4 + 4]
+                .try_into()
+                .expect("slice is 4 bytes"),
+        )
+    };
+    if hash::crc32(&patch[..patch.len() - 4]) != footer_crc(2) {
+        return Err(PatchError::PatchChecksum);
+    }
+
+    let mut reader = Reader::new(body);
+    if reader.take(BPS_MAGIC.len())? != BPS_MAGIC {
+        return Err(PatchError::UnknownFormat);
+    }
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.take(metadata_size)?;
+    let crc32 = hash::crc32(rom);
+    if source_size != rom.len() || crc32 != footer_crc(0) {
+        return Err(PatchError::WrongSource {
+            size: rom.len(),
+            expected_size: source_size,
+            crc32,
+            expected_crc32: footer_crc(0),
+        });
+    }
+    if target_size > MAX_TARGET_SIZE {
+        return Err(PatchError::TooBig { size: target_size });
+    }
+
+    let mut out = Vec::with_capacity(target_size);
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+    while !reader.is_empty() {
+        let command = reader.varint()?;
+        let length = (command >> 2) + 1;
+        if out.len() + length > target_size {
+            return Err(PatchError::OutOfBounds);
+        }
+        match command & 3 {
+            // source read: the bytes at the same offset of the ROM
+            0 => {
+                let start = out.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or(PatchError::OutOfBounds)?;
+                out.extend_from_slice(bytes);
+            }
+            // target read: bytes stored in the patch
+            1 => out.extend_from_slice(reader.take(length)?),
+            // source copy: bytes from anywhere in the ROM
+            2 => {
+                source_offset = reader.relative(source_offset)?;
+                let bytes = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or(PatchError::OutOfBounds)?;
+                out.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // target copy: bytes already written, which may overlap the
+            // bytes being written
+            _ => {
+                target_offset = reader.relative(target_offset)?;
+                if target_offset >= out.len() {
+                    return Err(PatchError::OutOfBounds);
+                }
+                for _ in 0..length {
+                    out.push(out[target_offset]);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if out.len() != target_size || hash::crc32(&out) != footer_crc(1) {
+        return Err(PatchError::TargetChecksum);
+    }
+    Ok(out)
+}
+
+/// Reads the fields of a patch.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], PatchError> {
+        if self.data.len() < count {
+            return Err(PatchError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// A BPS number: 7 bits per byte, least significant first, the last
+    /// byte with bit 7 set. Every byte but the first also adds one to the
+    /// value, so that there is only one encoding of a number.
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.take(1)?[0];
+            value = (byte as usize & 0x7f)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or(PatchError::OutOfBounds)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or(PatchError::OutOfBounds)?;
+            value = value.checked_add(shift).ok_or(PatchError::OutOfBounds)?;
+        }
+    }
+
+    /// `offset` moved by a BPS relative offset: a number whose lowest bit
+    /// is the sign.
+    fn relative(&mut self, offset: usize) -> Result<usize, PatchError> {
+        let value = self.varint()?;
+        let distance = value >> 1;
+        let moved = if value & 1 != 0 {
+            offset.checked_sub(distance)
+        } else {
+            offset.checked_add(distance)
+        };
+        moved.ok_or(PatchError::OutOfBounds)
+    }
+}
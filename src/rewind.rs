@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+
+use crate::snapshot::{SaveState, StateError};
+
+/// Unchanged bytes needed before a literal run in a delta is closed.
+const MIN_SKIP_RUN: usize = 4;
+
+/// Ring buffer of per-frame save states used to step the game backwards.
+///
+/// Only the newest state is kept in full. Every older frame is stored as a
+/// compressed delta (XOR against the frame after it), so a frame where only
+/// a handful of bytes changed costs a few bytes of history. The oldest
+/// frames are dropped once either `max_frames` or `max_bytes` is exceeded.
+pub struct RewindBuffer {
+    /// The most recently captured state.
+    head: Vec<u8>,
+    /// Deltas from each frame to the frame after it, oldest first.
+    deltas: VecDeque<Vec<u8>>,
+    /// Bytes used by the deltas.
+    delta_bytes: usize,
+    /// Maximum number of frames that can be rewound.
+    max_frames: usize,
+    /// Maximum memory used by the head and deltas combined.
+    max_bytes: usize,
+    /// Reused buffer for the state being captured.
+    scratch: Vec<u8>,
+}
+
+impl RewindBuffer {
+    /// Creates an empty buffer holding at most `max_frames` frames of history
+    /// in at most `max_bytes` bytes.
+    pub fn new(max_frames: usize, max_bytes: usize) -> Self {
+        Self {
+            head: Vec::new(),
+            deltas: VecDeque::new(),
+            delta_bytes: 0,
+            max_frames,
+            max_bytes,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Number of frames held, the newest one included. One less than this
+    /// can be rewound.
+    pub fn len(&self) -> usize {
+        if self.head.is_empty() {
+            0
+        } else {
+            self.deltas.len() + 1
+        }
+    }
+
+    /// Returns true if nothing has been captured yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Memory currently used by the captured history, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.head.len() + self.delta_bytes
+    }
+
+    /// Drops all captured history.
+    pub fn clear(&mut self) {
+        self.head.clear();
+        self.deltas.clear();
+        self.delta_bytes = 0;
+    }
+
+    /// Captures the state of `source` as the newest frame.
+    ///
+    /// Meant to be called once per emulated frame.
+    pub fn capture<S: SaveState>(&mut self, source: &S) {
+        self.scratch.clear();
+        source.save_state(&mut self.scratch);
+
+        if self.head.len() == self.scratch.len() {
+            let mut delta = Vec::new();
+            encode_delta(&self.head, &self.scratch, &mut delta);
+            self.delta_bytes += delta.len();
+            self.deltas.push_back(delta);
+        } else {
+            // the layout changed (or this is the first frame), older deltas
+            // can not be applied to the new state
+            self.deltas.clear();
+            self.delta_bytes = 0;
+        }
+        std::mem::swap(&mut self.head, &mut self.scratch);
+        self.evict();
+    }
+
+    /// Steps `target` back `frames` frames from the newest captured frame.
+    ///
+    /// The rewound frames are discarded, so emulation resumes from the
+    /// restored frame and captures continue from there. If fewer frames
+    /// are available, rewinds as far as possible. Returns the number of
+    /// frames actually rewound. Rewinding 0 frames restores the newest frame.
+    ///
+    /// If `target` fails to load the state, neither it nor the buffer is
+    /// changed.
+    pub fn rewind<S: SaveState>(
+        &mut self,
+        frames: usize,
+        target: &mut S,
+    ) -> Result<usize, StateError> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+        let frames = frames.min(self.len() - 1);
+        // the state is rebuilt in the scratch buffer and the deltas are only
+        // dropped once it has been loaded
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&self.head);
+        for delta in self.deltas.iter().rev().take(frames) {
+            apply_delta(&mut self.scratch, delta);
+        }
+        target.load_state(&self.scratch)?;
+        for _ in 0..frames {
+            let delta = self.deltas.pop_back().expect("delta count checked above");
+            self.delta_bytes -= delta.len();
+        }
+        std::mem::swap(&mut self.head, &mut self.scratch);
+        Ok(frames)
+    }
+
+    /// Drops the oldest deltas until the buffer is within its limits.
+    fn evict(&mut self) {
+        while self.deltas.len() > self.max_frames
+            || (!self.deltas.is_empty() && self.memory_usage() > self.max_bytes)
+        {
+            if let Some(delta) = self.deltas.pop_front() {
+                self.delta_bytes -= delta.len();
+            }
+        }
+    }
+}
+
+/// Writes `value` as a LEB128 varint.
+fn push_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a LEB128 varint from `delta` at `pos`, advancing `pos`.
+fn read_varint(delta: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = delta[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Encodes `previous ^ current` as a sequence of `(skip, length, bytes)`
+/// records, where `skip` unchanged bytes are followed by `length` XOR bytes.
+fn encode_delta(previous: &[u8], current: &[u8], out: &mut Vec<u8>) {
+    let len = current.len();
+    let mut pos = 0;
+    while pos < len {
+        let skip_start = pos;
+        while pos < len && previous[pos] == current[pos] {
+            pos += 1;
+        }
+        if pos == len {
+            break;
+        }
+        let literal_start = pos;
+        let mut unchanged = 0;
+        while pos < len && unchanged < MIN_SKIP_RUN {
+            if previous[pos] == current[pos] {
+                unchanged += 1;
+            } else {
+                unchanged = 0;
+            }
+            pos += 1;
+        }
+        let literal_end = pos - unchanged;
+        push_varint(out, literal_start - skip_start);
+        push_varint(out, literal_end - literal_start);
+        out.extend(
+            previous[literal_start..literal_end]
+                .iter()
+                .zip(&current[literal_start..literal_end])
+                .map(|(a, b)| a ^ b),
+        );
+        pos = literal_end;
+    }
+}
+
+/// Applies a delta made by `encode_delta` to `state` in place.
+fn apply_delta(state: &mut [u8], delta: &[u8]) {
+    let mut pos = 0;
+    let mut offset = 0;
+    while pos < delta.len() {
+        offset += read_varint(delta, &mut pos);
+        let length = read_varint(delta, &mut pos);
+        for (byte, change) in state[offset..offset + length]
+            .iter_mut()
+            .zip(&delta[pos..pos + length])
+        {
+            *byte ^= change;
+        }
+        offset += length;
+        pos += length;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A state of raw bytes, refusing to load any other length.
+    struct Bytes(Vec<u8>);
+
+    impl SaveState for Bytes {
+        fn save_state(&self, out: &mut Vec<u8>) {
+            out.extend_from_slice(&self.0);
+        }
+
+        fn load_state(&mut self, state: &[u8]) -> Result<usize, StateError> {
+            if state.len() != self.0.len() {
+                return Err(StateError::Truncated {
+                    needed: self.0.len(),
+                    available: state.len(),
+                });
+            }
+            self.0.copy_from_slice(state);
+            Ok(state.len())
+        }
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let previous: Vec<u8> = (0..200).map(|index| (index * 7) as u8).collect();
+        let mut current = previous.clone();
+        // a lone change, changes closer than MIN_SKIP_RUN, a long run and
+        // the last byte
+        current[0] ^= 0xff;
+        current[10] = 1;
+        current[12] = 2;
+        current[50..180].fill(0x55);
+        current[199] = 0;
+
+        let mut delta = Vec::new();
+        encode_delta(&previous, &current, &mut delta);
+        let mut state = previous.clone();
+        apply_delta(&mut state, &delta);
+        assert_eq!(state, current);
+
+        delta.clear();
+        encode_delta(&current, &current, &mut delta);
+        assert!(delta.is_empty());
+    }
+
+    #[test]
+    fn rewind_steps_back() {
+        let mut buffer = RewindBuffer::new(10, usize::MAX);
+        let mut game = Bytes(vec![0; 16]);
+        assert!(buffer.is_empty());
+        for frame in 1..=5 {
+            game.0[frame] = frame as u8;
+            buffer.capture(&game);
+        }
+        assert_eq!(buffer.len(), 5);
+
+        assert_eq!(buffer.rewind(2, &mut game), Ok(2));
+        assert_eq!(game.0[..6], [0, 1, 2, 3, 0, 0]);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.rewind(10, &mut game), Ok(2));
+        assert_eq!(game.0[..6], [0, 1, 0, 0, 0, 0]);
+        assert_eq!(buffer.len(), 1);
+        assert!(!buffer.is_empty());
+    }
+
+    #[test]
+    fn failed_load_keeps_history() {
+        let mut buffer = RewindBuffer::new(10, usize::MAX);
+        let mut game = Bytes(vec![0; 16]);
+        for frame in 1..=3 {
+            game.0[frame] = frame as u8;
+            buffer.capture(&game);
+        }
+        let mut other = Bytes(vec![9; 8]);
+        assert!(buffer.rewind(1, &mut other).is_err());
+        assert_eq!(other.0, [9; 8]);
+        assert_eq!(buffer.len(), 3);
+
+        assert_eq!(buffer.rewind(1, &mut game), Ok(1));
+        assert_eq!(game.0[..4], [0, 1, 2, 0]);
+    }
+}
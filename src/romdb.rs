@@ -0,0 +1,240 @@
+use std::fmt;
+
+use crate::games::{GameDefinition, RomChip, GAMES};
+use crate::hash;
+
+/// A ROM as read from disk, before it is matched to a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomDump {
+    /// A single image holding the chips one after the other.
+    Image(Vec<u8>),
+    /// Separate chip files, as (file name, bytes).
+    Chips(Vec<(String, Vec<u8>)>),
+}
+
+impl RomDump {
+    /// The bytes of the chip at `index` of `game`, with the name of the
+    /// file they were found in.
+    ///
+    /// Chip files are looked up by name, then by hash to find renamed
+    /// chips. An image is cut into the chips in order.
+    fn chip(&self, game: &GameDefinition, index: usize) -> Option<(Option<&str>, &[u8])> {
+        let chip = &game.roms[index];
+        match self {
+            RomDump::Image(image) => {
+                let offset: usize = game.roms[..index]
+                    .iter()
+                    .map(|chip| chip.size as usize)
+                    .sum();
+                let end = image.len().min(offset + chip.size as usize);
+                (offset < end).then(|| (None, &image[offset..end]))
+            }
+            RomDump::Chips(files) => files
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(chip.file))
+                .or_else(|| {
+                    let hash = chip.hash?;
+                    files
+                        .iter()
+                        .find(|(_, bytes)| hash::crc32(bytes) == hash.crc32)
+                })
+                .map(|(name, bytes)| (Some(name.as_str()), bytes.as_slice())),
+        }
+    }
+}
+
+/// What was found for one chip of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChipStatus {
+    /// Matches the hashes of the known good dump.
+    Good,
+    /// The right size, but the database has no hashes to check it with.
+    Unverified,
+    /// The right size with other hashes: a hack, a bug fix or a bad dump
+    /// that is not obviously broken.
+    Modified {
+        crc32: u32,
+    },
+    /// Looks like a failed read of the chip, for the reason given.
+    BadDump(&'static str),
+    WrongSize {
+        size: usize,
+    },
+    Missing,
+}
+
+/// The check of one chip of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipCheck {
+    pub chip: &'static RomChip,
+    /// The file the chip was found in when it has another name.
+    pub found_as: Option<String>,
+    pub status: ChipStatus,
+}
+
+impl fmt::Display for ChipCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.chip.file)?;
+        match self.status {
+            ChipStatus::Good => write!(f, "good")?,
+            ChipStatus::Unverified => write!(f, "{} bytes, no known hash", self.chip.size)?,
+            ChipStatus::Modified { crc32 } => write!(f, "modified, crc32 {:08x}", crc32)?,
+            ChipStatus::BadDump(reason) => write!(f, "bad dump, {}", reason)?,
+            ChipStatus::WrongSize { size } => {
+                write!(f, "{} bytes, expected {}", size, self.chip.size)?
+            }
+            ChipStatus::Missing => write!(f, "missing")?,
+        }
+        match &self.found_as {
+            Some(name) => write!(f, " (found as {})", name),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How usable a ROM is for a game, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    Good,
+    Unverified,
+    Modified,
+    BadDump,
+    /// Chips are missing or the wrong size, or the image is too big.
+    WrongLayout,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Verdict::Good => "good dump",
+            Verdict::Unverified => "right size, but no known hashes to check",
+            Verdict::Modified => "modified: hacked, patched or a bad dump",
+            Verdict::BadDump => "bad dump",
+            Verdict::WrongLayout => "chips are missing or the wrong size",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// The checks of every chip of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomReport {
+    pub game: &'static GameDefinition,
+    pub chips: Vec<ChipCheck>,
+    /// Bytes of an image past the last chip.
+    pub extra_bytes: usize,
+}
+
+impl RomReport {
+    /// The verdict of the worst chip.
+    pub fn verdict(&self) -> Verdict {
+        let worst = self
+            .chips
+            .iter()
+            .map(|check| match check.status {
+                ChipStatus::Good => Verdict::Good,
+                ChipStatus::Unverified => Verdict::Unverified,
+                ChipStatus::Modified { .. } => Verdict::Modified,
+                ChipStatus::BadDump(_) => Verdict::BadDump,
+                ChipStatus::WrongSize { .. } | ChipStatus::Missing => Verdict::WrongLayout,
+            })
+            .max()
+            .unwrap_or(Verdict::Good);
+        if self.extra_bytes > 0 {
+            Verdict::WrongLayout
+        } else {
+            worst
+        }
+    }
+}
+
+/// Checks `dump` against the chips of `game`.
+pub fn check(game: &'static GameDefinition, dump: &RomDump) -> RomReport {
+    let chips = game
+        .roms
+        .iter()
+        .enumerate()
+        .map(|(index, chip)| {
+            let Some((name, bytes)) = dump.chip(game, index) else {
+                return ChipCheck {
+                    chip,
+                    found_as: None,
+                    status: ChipStatus::Missing,
+                };
+            };
+            ChipCheck {
+                chip,
+                found_as: name
+                    .filter(|name| !name.eq_ignore_ascii_case(chip.file))
+                    .map(str::to_owned),
+                status: chip_status(chip, bytes),
+            }
+        })
+        .collect();
+    let extra_bytes = match dump {
+        RomDump::Image(image) => image.len().saturating_sub(game.rom_size()),
+        RomDump::Chips(_) => 0,
+    };
+    RomReport {
+        game,
+        chips,
+        extra_bytes,
+    }
+}
+
+fn chip_status(chip: &RomChip, bytes: &[u8]) -> ChipStatus {
+    if bytes.len() != chip.size as usize {
+        return ChipStatus::WrongSize { size: bytes.len() };
+    }
+    let crc32 = hash::crc32(bytes);
+    if let Some(hash) = chip.hash {
+        if crc32 == hash.crc32 && hash::to_hex(&hash::sha1(bytes)) == hash.sha1 {
+            return ChipStatus::Good;
+        }
+    }
+    if bytes.iter().all(|&byte| byte == bytes[0]) {
+        return ChipStatus::BadDump("every byte is the same");
+    }
+    let (low, high) = bytes.split_at(bytes.len() / 2);
+    if low == high {
+        return ChipStatus::BadDump("both halves are the same, an address line may be stuck");
+    }
+    match chip.hash {
+        Some(_) => ChipStatus::Modified { crc32 },
+        None => ChipStatus::Unverified,
+    }
+}
+
+/// The game `dump` looks most like, `None` if it looks like none.
+///
+/// Chips matching the hashes of a game count first, then chip files with
+/// the names and sizes of a game, or an image of the size of its chips.
+/// Parents come before their clones on a tie.
+pub fn identify(dump: &RomDump) -> Option<&'static GameDefinition> {
+    let mut best = None;
+    let mut best_score = (0, 0);
+    for &game in &GAMES {
+        let mut score = (0, 0);
+        for (index, chip) in game.roms.iter().enumerate() {
+            let Some((name, bytes)) = dump.chip(game, index) else {
+                continue;
+            };
+            if chip_status(chip, bytes) == ChipStatus::Good {
+                score.0 += 1;
+            }
+            if name.is_some_and(|name| name.eq_ignore_ascii_case(chip.file))
+                && bytes.len() == chip.size as usize
+            {
+                score.1 += 1;
+            }
+        }
+        if let RomDump::Image(image) = dump {
+            score.1 = (image.len() == game.rom_size()) as usize;
+        }
+        if score > best_score {
+            best = Some(game);
+            best_score = score;
+        }
+    }
+    best
+}
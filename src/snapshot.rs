@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// Error returned when a save state can not be restored.
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The state buffer ended before all fields were read.
+    Truncated { needed: usize, available: usize },
+    /// The state was saved with another layout.
+    Version { expected: u8, found: u8 },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Truncated { needed, available } => write!(
+                f,
+                "save state is truncated: needed {} bytes, got {}",
+                needed, available
+            ),
+            StateError::Version { expected, found } => {
+                write!(f, "save state has version {}, expected {}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Anything whose complete state can be written to and restored from bytes.
+///
+/// The encoding is only meant to be read back by the same build of the
+/// emulator, it is not a stable file format.
+pub trait SaveState {
+    /// Appends the serialized state to `out`.
+    fn save_state(&self, out: &mut Vec<u8>);
+
+    /// Restores the state from the start of `state`.
+    ///
+    /// Returns the number of bytes consumed, so that states can be nested.
+    fn load_state(&mut self, state: &[u8]) -> Result<usize, StateError>;
+}
+
+/// Takes a snapshot of `source` into a freshly allocated buffer.
+pub fn snapshot<S: SaveState>(source: &S) -> Vec<u8> {
+    let mut state = Vec::new();
+    source.save_state(&mut state);
+    state
+}
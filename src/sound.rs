@@ -0,0 +1,243 @@
+use std::path::Path;
+
+use crate::machine::{CLOCK_HZ, CYCLES_PER_FRAME};
+use crate::wav::{self, Pcm, WavError};
+
+// Sound port 1 (OUT 3)
+/// UFO flying, held for as long as it is on screen.
+pub const UFO: u8 = 0x01;
+/// Player shot.
+pub const SHOT: u8 = 0x02;
+/// Player ship exploding.
+pub const PLAYER_DIE: u8 = 0x04;
+/// Invader hit.
+pub const INVADER_DIE: u8 = 0x08;
+/// Extra ship awarded.
+pub const EXTENDED_PLAY: u8 = 0x10;
+/// Sound amplifier enable, cleared by the game in attract mode.
+pub const AMP_ENABLE: u8 = 0x20;
+
+// Sound port 2 (OUT 5)
+/// The four notes of the fleet movement.
+pub const FLEET: [u8; 4] = [0x01, 0x02, 0x04, 0x08];
+/// UFO hit.
+pub const UFO_HIT: u8 = 0x10;
+
+/// Number of sound effects, and of samples in a sample set.
+pub const SOUND_COUNT: usize = 10;
+
+/// Sample rate used for audio output.
+pub const OUTPUT_SAMPLE_RATE: u32 = 44_100;
+
+/// The sound latches as seen since the previous update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SoundPorts {
+    /// Current value of OUT 3.
+    pub port3: u8,
+    /// Current value of OUT 5.
+    pub port5: u8,
+    /// Bits of OUT 3 that went high since the previous update, even if
+    /// they have gone low again.
+    pub rose3: u8,
+    /// Bits of OUT 5 that went high since the previous update.
+    pub rose5: u8,
+}
+
+/// Decides how many samples each emulated frame lasts, keeping audio in
+/// step with emulated rather than wall-clock time.
+///
+/// A frame is not a whole number of samples, so the remainder is carried
+/// over to the next frame.
+#[derive(Debug, Clone)]
+pub struct SampleClock {
+    sample_rate: u32,
+    /// Fraction of a sample left over, in units of 1 / `CLOCK_HZ`.
+    remainder: u64,
+}
+
+impl SampleClock {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            remainder: 0,
+        }
+    }
+
+    /// Number of samples to render for the next frame.
+    pub fn samples_for_frame(&mut self) -> usize {
+        let total = self.sample_rate as u64 * CYCLES_PER_FRAME as u64 + self.remainder;
+        self.remainder = total % CLOCK_HZ as u64;
+        (total / CLOCK_HZ as u64) as usize
+    }
+}
+
+/// One sound effect and the latch bit that controls it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundEffect {
+    /// Which latch: false for the first sound port (OUT 3 on Space
+    /// Invaders), true for the second (OUT 5).
+    pub port5: bool,
+    pub bit: u8,
+    /// Whether the sound repeats for as long as the bit is held.
+    pub looping: bool,
+}
+
+impl SoundEffect {
+    fn held(&self, ports: &SoundPorts) -> bool {
+        let value = if self.port5 { ports.port5 } else { ports.port3 };
+        value & self.bit != 0
+    }
+
+    fn rose(&self, ports: &SoundPorts) -> bool {
+        let value = if self.port5 { ports.rose5 } else { ports.rose3 };
+        value & self.bit != 0
+    }
+}
+
+/// How a game drives its sounds through the two latches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundMap {
+    /// The effects in the order of the sample files `0.wav`, `1.wav` etc,
+    /// at most `SOUND_COUNT`.
+    pub effects: &'static [SoundEffect],
+    /// Bit of the first latch enabling the amplifier, 0 if the sound is
+    /// always on.
+    pub amp_enable: u8,
+    /// Whether `Synthesizer` approximates the sound circuits of the board.
+    pub synthesized: bool,
+}
+
+/// The effects of Space Invaders, in the order of the conventional `0.wav`
+/// to `9.wav` files.
+#[rustfmt::skip]
+pub const INVADERS_EFFECTS: [SoundEffect; SOUND_COUNT] = [
+    SoundEffect { port5: false, bit: UFO, looping: true },
+    SoundEffect { port5: false, bit: SHOT, looping: false },
+    SoundEffect { port5: false, bit: PLAYER_DIE, looping: false },
+    SoundEffect { port5: false, bit: INVADER_DIE, looping: false },
+    SoundEffect { port5: true, bit: FLEET[0], looping: false },
+    SoundEffect { port5: true, bit: FLEET[1], looping: false },
+    SoundEffect { port5: true, bit: FLEET[2], looping: false },
+    SoundEffect { port5: true, bit: FLEET[3], looping: false },
+    SoundEffect { port5: true, bit: UFO_HIT, looping: false },
+    SoundEffect { port5: false, bit: EXTENDED_PLAY, looping: false },
+];
+
+/// Anything that turns the sound latches into audio.
+pub trait SoundGenerator {
+    /// Takes in the latch changes of the last emulated stretch of time.
+    fn update(&mut self, ports: SoundPorts);
+
+    /// Renders the next `out.len()` mono samples.
+    fn render(&mut self, out: &mut [i16]);
+}
+
+/// Up to `SOUND_COUNT` sound samples, any of which may be missing.
+#[derive(Debug, Clone, Default)]
+pub struct SampleSet {
+    samples: [Option<Pcm>; SOUND_COUNT],
+}
+
+impl SampleSet {
+    /// Loads `0.wav` to `9.wav` from `dir`. Missing files are skipped, so
+    /// their effects stay silent.
+    pub fn load_dir(dir: &str) -> Result<Self, WavError> {
+        let mut set = SampleSet::default();
+        for (index, sample) in set.samples.iter_mut().enumerate() {
+            let path = Path::new(dir).join(format!("{}.wav", index));
+            if path.exists() {
+                *sample = Some(wav::read_wav(&path.to_string_lossy())?);
+            }
+        }
+        Ok(set)
+    }
+
+    /// Number of samples that were found.
+    pub fn len(&self) -> usize {
+        self.samples.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Plays the sample set, mixing all effects into one mono stream.
+pub struct SampleMixer {
+    sound: SoundMap,
+    /// Samples converted to the output rate.
+    samples: [Vec<i16>; SOUND_COUNT],
+    /// Play position of every effect, `None` if it is not playing.
+    voices: [Option<usize>; SOUND_COUNT],
+    /// Whether the looping effects are still held.
+    held: [bool; SOUND_COUNT],
+    amp_enabled: bool,
+    sample_rate: u32,
+}
+
+impl SampleMixer {
+    /// Creates a mixer producing audio at `sample_rate` for a game
+    /// driving its sounds as `sound` says.
+    pub fn new(set: &SampleSet, sound: SoundMap, sample_rate: u32) -> Self {
+        assert!(sound.effects.len() <= SOUND_COUNT, "too many sound effects");
+        let samples = std::array::from_fn(|index| match &set.samples[index] {
+            Some(pcm) => pcm.resample(sample_rate).samples,
+            None => Vec::new(),
+        });
+        Self {
+            sound,
+            samples,
+            voices: [None; SOUND_COUNT],
+            held: [false; SOUND_COUNT],
+            amp_enabled: false,
+            sample_rate,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl SoundGenerator for SampleMixer {
+    fn update(&mut self, ports: SoundPorts) {
+        self.amp_enabled = self.sound.amp_enable == 0 || ports.port3 & self.sound.amp_enable != 0;
+        for (index, effect) in self.sound.effects.iter().enumerate() {
+            self.held[index] = effect.held(&ports);
+            if effect.looping {
+                if !self.held[index] {
+                    self.voices[index] = None;
+                } else if self.voices[index].is_none() {
+                    self.voices[index] = Some(0);
+                }
+            } else if effect.rose(&ports) {
+                self.voices[index] = Some(0);
+            }
+        }
+    }
+
+    fn render(&mut self, out: &mut [i16]) {
+        for value in out.iter_mut() {
+            let mut mix: i32 = 0;
+            for (index, voice) in self.voices.iter_mut().enumerate() {
+                let Some(position) = voice else { continue };
+                let sample = &self.samples[index];
+                if *position >= sample.len() {
+                    if self.sound.effects[index].looping && self.held[index] && !sample.is_empty() {
+                        *position = 0;
+                    } else {
+                        *voice = None;
+                        continue;
+                    }
+                }
+                mix += sample[*position] as i32;
+                *position += 1;
+            }
+            *value = if self.amp_enabled {
+                mix.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+            } else {
+                0
+            };
+        }
+    }
+}
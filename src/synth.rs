@@ -0,0 +1,190 @@
+use std::f32::consts::TAU;
+
+use crate::sound::{
+    SoundGenerator, SoundPorts, AMP_ENABLE, EXTENDED_PLAY, FLEET, INVADER_DIE, PLAYER_DIE, SHOT,
+    UFO, UFO_HIT,
+};
+
+/// Approximate notes of the four fleet movement steps, in Hz.
+const FLEET_NOTES: [f32; 4] = [98.0, 87.0, 78.0, 73.0];
+
+/// The waveforms the discrete circuits of the board are approximated with.
+#[derive(Debug, Clone, Copy)]
+enum Wave {
+    /// Sine whose pitch swings `depth` Hz around `base` at `rate` Hz.
+    Warble { base: f32, depth: f32, rate: f32 },
+    /// Low-passed white noise, `cutoff` is the filter coefficient (0-1).
+    Noise { cutoff: f32 },
+    /// Square wave at a fixed pitch.
+    Square { frequency: f32 },
+    /// Sine beeping on and off at `rate` Hz.
+    Beep { frequency: f32, rate: f32 },
+}
+
+/// How one sound effect is triggered and shaped.
+#[derive(Debug, Clone, Copy)]
+struct Patch {
+    /// Which latch: false for OUT 3, true for OUT 5.
+    port5: bool,
+    bit: u8,
+    wave: Wave,
+    /// Length in seconds, 0 for sounds that play while the bit is held.
+    length: f32,
+    /// Time constant of the exponential decay in seconds, 0 for none.
+    decay: f32,
+    volume: f32,
+}
+
+/// The effects, in the same order as the sample files.
+#[rustfmt::skip]
+const PATCHES: [Patch; 10] = [
+    Patch { port5: false, bit: UFO, wave: Wave::Warble { base: 700.0, depth: 250.0, rate: 7.0 }, length: 0.0, decay: 0.0, volume: 0.20 },
+    Patch { port5: false, bit: SHOT, wave: Wave::Noise { cutoff: 0.6 }, length: 0.3, decay: 0.08, volume: 0.30 },
+    Patch { port5: false, bit: PLAYER_DIE, wave: Wave::Noise { cutoff: 0.15 }, length: 1.2, decay: 0.35, volume: 0.55 },
+    Patch { port5: false, bit: INVADER_DIE, wave: Wave::Noise { cutoff: 0.35 }, length: 0.35, decay: 0.1, volume: 0.40 },
+    Patch { port5: true, bit: FLEET[0], wave: Wave::Square { frequency: FLEET_NOTES[0] }, length: 0.12, decay: 0.05, volume: 0.35 },
+    Patch { port5: true, bit: FLEET[1], wave: Wave::Square { frequency: FLEET_NOTES[1] }, length: 0.12, decay: 0.05, volume: 0.35 },
+    Patch { port5: true, bit: FLEET[2], wave: Wave::Square { frequency: FLEET_NOTES[2] }, length: 0.12, decay: 0.05, volume: 0.35 },
+    Patch { port5: true, bit: FLEET[3], wave: Wave::Square { frequency: FLEET_NOTES[3] }, length: 0.12, decay: 0.05, volume: 0.35 },
+    Patch { port5: true, bit: UFO_HIT, wave: Wave::Warble { base: 1200.0, depth: 400.0, rate: 15.0 }, length: 1.0, decay: 0.4, volume: 0.25 },
+    Patch { port5: false, bit: EXTENDED_PLAY, wave: Wave::Beep { frequency: 2000.0, rate: 8.0 }, length: 1.0, decay: 0.0, volume: 0.15 },
+];
+
+/// Playback state of one effect.
+#[derive(Debug, Clone, Copy, Default)]
+struct Voice {
+    active: bool,
+    /// Seconds since the effect was triggered.
+    age: f32,
+    /// Oscillator phase, 0-1.
+    phase: f32,
+    /// Low-pass filter state for noise.
+    filtered: f32,
+}
+
+/// Procedural stand-in for the discrete sound circuits of the board, for
+/// when the sample files are not available.
+///
+/// Output only depends on the latch updates, so it is as deterministic as
+/// the emulation.
+pub struct Synthesizer {
+    sample_rate: u32,
+    voices: [Voice; PATCHES.len()],
+    amp_enabled: bool,
+    /// State of the noise generator, a 16 bit Galois LFSR.
+    lfsr: u16,
+}
+
+impl Synthesizer {
+    /// Creates a synthesizer producing audio at `sample_rate`.
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: [Voice::default(); PATCHES.len()],
+            amp_enabled: false,
+            lfsr: 0xace1,
+        }
+    }
+
+    /// Next white noise value, -1 to 1.
+    fn noise(&mut self) -> f32 {
+        let bit = self.lfsr & 1;
+        self.lfsr >>= 1;
+        if bit == 0 {
+            return -1.0;
+        }
+        self.lfsr ^= 0xb400;
+        1.0
+    }
+
+    /// Renders one sample of `voice` playing `patch`, -1 to 1.
+    fn voice_sample(voice: &mut Voice, patch: &Patch, noise: f32, dt: f32) -> f32 {
+        let value = match patch.wave {
+            Wave::Warble { base, depth, rate } => {
+                let frequency = base + depth * (TAU * rate * voice.age).sin();
+                voice.phase = (voice.phase + frequency * dt).fract();
+                (TAU * voice.phase).sin()
+            }
+            Wave::Noise { cutoff } => {
+                voice.filtered += cutoff * (noise - voice.filtered);
+                voice.filtered
+            }
+            Wave::Square { frequency } => {
+                voice.phase = (voice.phase + frequency * dt).fract();
+                if voice.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Wave::Beep { frequency, rate } => {
+                voice.phase = (voice.phase + frequency * dt).fract();
+                let gate = (voice.age * rate).fract() < 0.5;
+                if gate {
+                    (TAU * voice.phase).sin()
+                } else {
+                    0.0
+                }
+            }
+        };
+        let envelope = if patch.decay > 0.0 {
+            (-voice.age / patch.decay).exp()
+        } else {
+            1.0
+        };
+        voice.age += dt;
+        value * envelope * patch.volume
+    }
+}
+
+impl SoundGenerator for Synthesizer {
+    fn update(&mut self, ports: SoundPorts) {
+        self.amp_enabled = ports.port3 & AMP_ENABLE != 0;
+        for (index, patch) in PATCHES.iter().enumerate() {
+            let (value, rose) = if patch.port5 {
+                (ports.port5, ports.rose5)
+            } else {
+                (ports.port3, ports.rose3)
+            };
+            let voice = &mut self.voices[index];
+            if patch.length == 0.0 {
+                if value & patch.bit == 0 {
+                    voice.active = false;
+                } else if !voice.active {
+                    *voice = Voice {
+                        active: true,
+                        ..Voice::default()
+                    };
+                }
+            } else if rose & patch.bit != 0 {
+                *voice = Voice {
+                    active: true,
+                    ..Voice::default()
+                };
+            }
+        }
+    }
+
+    fn render(&mut self, out: &mut [i16]) {
+        let dt = 1.0 / self.sample_rate as f32;
+        for value in out.iter_mut() {
+            let noise = self.noise();
+            let mut mix = 0.0;
+            for (voice, patch) in self.voices.iter_mut().zip(&PATCHES) {
+                if !voice.active {
+                    continue;
+                }
+                if patch.length > 0.0 && voice.age >= patch.length {
+                    voice.active = false;
+                    continue;
+                }
+                mix += Synthesizer::voice_sample(voice, patch, noise, dt);
+            }
+            *value = if self.amp_enabled {
+                (mix.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+            } else {
+                0
+            };
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::io::{self, BufWriter, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::input::{Action, InputMap, Key};
+use crate::machine::SpaceInvaders;
+use crate::movie::FrameInput;
+use crate::timing::FrameLimiter;
+use crate::video::{self, OverlayColor, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Width of a braille cell, in pixels.
+const CELL_WIDTH: usize = 2;
+/// Height of a braille cell, in pixels.
+const CELL_HEIGHT: usize = 4;
+/// Terminal columns used by the screen.
+pub const COLUMNS: usize = SCREEN_WIDTH / CELL_WIDTH;
+/// Terminal rows used by the screen, without the status line.
+pub const ROWS: usize = SCREEN_HEIGHT / CELL_HEIGHT;
+
+/// Frames a button stays pressed after its key was last seen.
+///
+/// Terminals only report key presses and auto-repeats, not releases, so a
+/// button is released when its key stops repeating.
+const HOLD_FRAMES: u8 = 8;
+
+/// Splits the bytes read from the terminal into keys.
+///
+/// Arrow keys arrive as `ESC [ A` to `ESC [ D`; an escape that does not
+/// start such a sequence is the escape key itself.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos] == 0x1b && bytes.get(pos + 1) == Some(&b'[') {
+            let key = match bytes.get(pos + 2) {
+                Some(b'A') => Some(Key::Up),
+                Some(b'B') => Some(Key::Down),
+                Some(b'C') => Some(Key::Right),
+                Some(b'D') => Some(Key::Left),
+                _ => None,
+            };
+            keys.extend(key);
+            pos += 3;
+            continue;
+        }
+        keys.push(match bytes[pos] {
+            0x1b => Key::Escape,
+            byte => Key::Char((byte as char).to_ascii_lowercase()),
+        });
+        pos += 1;
+    }
+    keys
+}
+
+/// Puts the terminal in raw mode with `stty`, restoring it when dropped.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other("stdin is not a terminal"));
+        }
+        let saved = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        Command::new("stty")
+            .args(["raw", "-echo"])
+            .stdin(Stdio::inherit())
+            .status()?;
+        Ok(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Command::new("stty")
+            .arg(&self.saved)
+            .stdin(Stdio::inherit())
+            .status();
+    }
+}
+
+/// Forwards the bytes typed on stdin, so the game loop can poll them.
+fn spawn_key_reader() -> mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        let mut stdin = io::stdin();
+        while let Ok(count @ 1..) = stdin.read(&mut buffer) {
+            if sender.send(buffer[..count].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// ANSI foreground color code for an overlay color.
+fn ansi_color(color: OverlayColor) -> u8 {
+    match color {
+        OverlayColor::White => 37,
+        OverlayColor::Red => 31,
+        OverlayColor::Green => 32,
+    }
+}
+
+/// Draws the screen into the terminal with braille characters, one
+/// character per 2x4 pixels.
+pub struct TerminalScreen {
+    pixels: Vec<u8>,
+    /// Pixels and status line last drawn, to skip drawing unchanged frames.
+    drawn: Vec<u8>,
+    drawn_status: String,
+    text: String,
+}
+
+impl TerminalScreen {
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            drawn: Vec::new(),
+            drawn_status: String::new(),
+            text: String::new(),
+        }
+    }
+
+    /// Draws the screen shown by `vram` followed by `status`, if it changed.
+    pub fn draw(&mut self, out: &mut impl Write, vram: &[u8], status: &str) -> io::Result<()> {
+        video::render_into(vram, &mut self.pixels);
+        if self.pixels == self.drawn && status == self.drawn_status {
+            return Ok(());
+        }
+        self.text.clear();
+        // cursor home
+        self.text.push_str("\x1b[H");
+        let mut current_color = None;
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let x = column * CELL_WIDTH;
+                let y = row * CELL_HEIGHT;
+                let dots = self.braille_dots(x, y);
+                if dots == 0 {
+                    self.text.push(' ');
+                    continue;
+                }
+                let color = video::overlay_color(x, y + CELL_HEIGHT / 2);
+                if current_color != Some(color) {
+                    self.text.push_str(&format!("\x1b[{}m", ansi_color(color)));
+                    current_color = Some(color);
+                }
+                self.text
+                    .push(char::from_u32(0x2800 + dots as u32).expect("braille is valid"));
+            }
+            self.text.push_str("\r\n");
+        }
+        self.text.push_str("\x1b[0m\x1b[K");
+        self.text.push_str(status);
+        out.write_all(self.text.as_bytes())?;
+        out.flush()?;
+        self.drawn.clone_from(&self.pixels);
+        self.drawn_status.clear();
+        self.drawn_status.push_str(status);
+        Ok(())
+    }
+
+    /// The braille dot pattern for the cell with its top left corner at
+    /// (`x`, `y`).
+    fn braille_dots(&self, x: usize, y: usize) -> u8 {
+        // dot bits in braille order: left column 1 2 3 7, right column 4 5 6 8
+        const DOTS: [[u8; CELL_HEIGHT]; CELL_WIDTH] =
+            [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+        let mut dots = 0;
+        for (dx, column) in DOTS.iter().enumerate() {
+            for (dy, dot) in column.iter().enumerate() {
+                if self.pixels[(y + dy) * SCREEN_WIDTH + x + dx] != 0 {
+                    dots |= dot;
+                }
+            }
+        }
+        dots
+    }
+}
+
+impl Default for TerminalScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The first key bound to `action`, for the status line.
+fn key_hint(input_map: &InputMap, action: Action) -> String {
+    match input_map.keys_for(action).next() {
+        Some(key) => key.to_string(),
+        None => "-".to_owned(),
+    }
+}
+
+/// Plays the game in the terminal until the player quits.
+///
+/// The cabinet buttons follow `input_map`. `p` pauses, `n` advances a
+/// frame while paused, `+`/`-` change the speed and `q` or escape quits;
+/// these take precedence over the map. Needs a terminal and `stty`.
+pub fn run(
+    machine: &mut SpaceInvaders,
+    limiter: &mut FrameLimiter,
+    input_map: &InputMap,
+) -> io::Result<()> {
+    let _raw_mode = RawMode::enable()?;
+    let keys = spawn_key_reader();
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut screen = TerminalScreen::new();
+    // frames left for every action
+    let mut held = [0u8; Action::ALL.len()];
+    let hints = format!(
+        "{} coin  {} start  p pause  q quit",
+        key_hint(input_map, Action::Coin),
+        key_hint(input_map, Action::P1Start)
+    );
+    // hide the cursor and clear the screen
+    write!(out, "\x1b[?25l\x1b[2J")?;
+
+    'game: loop {
+        while let Ok(bytes) = keys.try_recv() {
+            for key in parse_keys(&bytes) {
+                match key {
+                    Key::Char('q') | Key::Escape => break 'game,
+                    Key::Char('p') => limiter.set_paused(!limiter.is_paused()),
+                    Key::Char('n') => limiter.advance_frame(),
+                    Key::Char('+') => limiter.set_speed(limiter.speed() * 2.0),
+                    Key::Char('-') => limiter.set_speed(limiter.speed() / 2.0),
+                    _ => {
+                        for action in input_map.actions_for(key) {
+                            held[action as usize] = HOLD_FRAMES;
+                        }
+                    }
+                }
+            }
+        }
+
+        if limiter.wait() {
+            let mut input = FrameInput::default();
+            for (action, counter) in Action::ALL.into_iter().zip(held.iter_mut()) {
+                if *counter > 0 {
+                    action.press(&mut input);
+                    *counter -= 1;
+                }
+            }
+            machine.io.port1 = input.port1;
+            machine.io.port2 = input.port2;
+            machine.run_frame();
+        }
+
+        let status = format!(
+            "{:.0} fps  {}x{}  {}",
+            limiter.fps(),
+            limiter.speed(),
+            if limiter.is_paused() { " paused" } else { "" },
+            hints
+        );
+        screen.draw(&mut out, machine.vram(), &status)?;
+    }
+
+    // reset colors, show the cursor again
+    write!(out, "\x1b[0m\x1b[?25h\r\n")?;
+    out.flush()
+}
@@ -0,0 +1,133 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::machine::FRAMES_PER_SECOND;
+
+/// Slowest supported emulation speed, as a multiple of real time.
+pub const MIN_SPEED: f64 = 0.25;
+/// Fastest supported emulation speed, as a multiple of real time.
+pub const MAX_SPEED: f64 = 8.0;
+
+/// How far emulation may fall behind before the limiter stops trying to
+/// catch up and resyncs to the wall clock.
+const MAX_LAG: Duration = Duration::from_millis(250);
+/// Interval over which the emulated frame rate is measured.
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Paces emulation to the 60 Hz of the real machine using the wall clock.
+///
+/// Every frame gets a deadline one frame period after the previous one,
+/// rather than after the moment the frame finished, so sleep inaccuracies
+/// do not add up over time.
+pub struct FrameLimiter {
+    /// Emulation speed as a multiple of real time.
+    speed: f64,
+    paused: bool,
+    /// Frames still to be run while paused.
+    pending_advance: u32,
+    /// When the next frame is due.
+    deadline: Instant,
+    /// Frames emulated since `window_start`.
+    window_frames: u32,
+    window_start: Instant,
+    /// Emulated frames per second measured over the last full window.
+    fps: f64,
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            speed: 1.0,
+            paused: false,
+            pending_advance: 0,
+            deadline: now,
+            window_frames: 0,
+            window_start: now,
+            fps: 0.0,
+        }
+    }
+
+    /// Current emulation speed, as a multiple of real time.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Sets the emulation speed, clamped to `MIN_SPEED..=MAX_SPEED`.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.deadline = Instant::now();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes emulation.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_advance = 0;
+        self.deadline = Instant::now();
+    }
+
+    /// Lets exactly one more frame run while paused.
+    pub fn advance_frame(&mut self) {
+        if self.paused {
+            self.pending_advance += 1;
+        }
+    }
+
+    /// Emulated frames per second, measured over the last second.
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// Duration of one frame at the current speed.
+    fn frame_period(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / (FRAMES_PER_SECOND as f64 * self.speed))
+    }
+
+    /// Waits until the next frame is due.
+    ///
+    /// Returns true if a frame should be emulated now. While paused this
+    /// returns false after one frame period, unless a frame advance is
+    /// pending, so callers can keep polling input.
+    pub fn wait(&mut self) -> bool {
+        let now = Instant::now();
+        if now < self.deadline {
+            thread::sleep(self.deadline - now);
+        } else if now - self.deadline > MAX_LAG {
+            // too far behind (or just resumed), drop the backlog
+            self.deadline = now;
+        }
+        self.deadline += self.frame_period();
+
+        let run = if !self.paused {
+            true
+        } else if self.pending_advance > 0 {
+            self.pending_advance -= 1;
+            true
+        } else {
+            false
+        };
+        self.count_frame(run);
+        run
+    }
+
+    /// Updates the measured frame rate, counting a frame if `emulated`.
+    fn count_frame(&mut self, emulated: bool) {
+        self.window_frames += emulated as u32;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= FPS_WINDOW {
+            self.fps = self.window_frames as f64 / elapsed.as_secs_f64();
+            self.window_frames = 0;
+            self.window_start = Instant::now();
+        }
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
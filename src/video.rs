@@ -0,0 +1,92 @@
+use std::fs;
+use std::io;
+
+/// Width of the screen as seen on the cabinet, in pixels.
+pub const SCREEN_WIDTH: usize = 224;
+/// Height of the screen as seen on the cabinet, in pixels.
+pub const SCREEN_HEIGHT: usize = 256;
+
+/// Colors of the gel overlay on the cabinet's screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayColor {
+    White,
+    Red,
+    Green,
+}
+
+/// The overlay color at screen position (`x`, `y`).
+///
+/// The UFO band near the top is red; the shields and the player's ship
+/// are green, and so are the spare ships in the bottom left.
+pub fn overlay_color(x: usize, y: usize) -> OverlayColor {
+    match y {
+        32..=63 => OverlayColor::Red,
+        184..=239 => OverlayColor::Green,
+        240.. if (16..134).contains(&x) => OverlayColor::Green,
+        _ => OverlayColor::White,
+    }
+}
+
+/// Converts the video RAM into a row-major bitmap of the screen as seen on
+/// the cabinet, with one byte (0 or 1) per pixel.
+///
+/// The monitor is mounted rotated 90 degrees counter-clockwise, so every
+/// 32 byte column of the video RAM becomes a column of the screen, drawn
+/// bottom to top, least significant bit first.
+pub fn render_into(vram: &[u8], pixels: &mut [u8]) {
+    for (x, column) in vram.chunks_exact(SCREEN_HEIGHT / 8).enumerate() {
+        for (byte_index, &byte) in column.iter().enumerate() {
+            for bit in 0..8 {
+                let y = SCREEN_HEIGHT - 1 - (byte_index * 8 + bit);
+                pixels[y * SCREEN_WIDTH + x] = (byte >> bit) & 1;
+            }
+        }
+    }
+}
+
+/// Same as `render_into`, into a newly allocated bitmap.
+pub fn render(vram: &[u8]) -> Vec<u8> {
+    let mut pixels = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+    render_into(vram, &mut pixels);
+    pixels
+}
+
+/// Encodes a bitmap made by `render` as a 24 bit BMP image, white on black.
+pub fn encode_bmp(pixels: &[u8]) -> Vec<u8> {
+    const HEADER_SIZE: u32 = 54;
+    // rows are padded to 4 bytes, 224 * 3 already is
+    let row_size = SCREEN_WIDTH * 3;
+    let image_size = (row_size * SCREEN_HEIGHT) as u32;
+
+    let mut bmp = Vec::with_capacity((HEADER_SIZE + image_size) as usize);
+    // file header
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(HEADER_SIZE + image_size).to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    bmp.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    // info header
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.extend_from_slice(&(SCREEN_WIDTH as i32).to_le_bytes());
+    bmp.extend_from_slice(&(SCREEN_HEIGHT as i32).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&24u16.to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    bmp.extend_from_slice(&image_size.to_le_bytes());
+    bmp.extend_from_slice(&2835i32.to_le_bytes());
+    bmp.extend_from_slice(&2835i32.to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    // pixel rows, bottom up
+    for row in pixels.chunks_exact(SCREEN_WIDTH).rev() {
+        for &pixel in row {
+            let value = if pixel != 0 { 0xff } else { 0x00 };
+            bmp.extend_from_slice(&[value, value, value]);
+        }
+    }
+    bmp
+}
+
+/// Writes the screen shown by `vram` to `file_path` as a BMP image.
+pub fn write_screenshot(file_path: &str, vram: &[u8]) -> io::Result<()> {
+    fs::write(file_path, encode_bmp(&render(vram)))
+}
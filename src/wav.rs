@@ -0,0 +1,198 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+/// Error returned when a WAV file can not be decoded.
+#[derive(Debug)]
+pub enum WavError {
+    Io(io::Error),
+    /// The file is not a RIFF WAVE file.
+    NotAWav,
+    /// The file uses an encoding other than 8 or 16 bit PCM.
+    Unsupported(String),
+    /// A chunk runs past the end of the file.
+    Truncated,
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavError::Io(err) => write!(f, "{}", err),
+            WavError::NotAWav => write!(f, "not a WAV file"),
+            WavError::Unsupported(what) => write!(f, "unsupported WAV file: {}", what),
+            WavError::Truncated => write!(f, "WAV file is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for WavError {}
+
+impl From<io::Error> for WavError {
+    fn from(err: io::Error) -> Self {
+        WavError::Io(err)
+    }
+}
+
+/// Mono 16 bit PCM audio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcm {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+impl Pcm {
+    /// Converts the audio to `sample_rate` using linear interpolation.
+    pub fn resample(&self, sample_rate: u32) -> Pcm {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return Pcm {
+                sample_rate,
+                samples: self.samples.clone(),
+            };
+        }
+        let length =
+            (self.samples.len() as u64 * sample_rate as u64 / self.sample_rate as u64) as usize;
+        let step = self.sample_rate as f64 / sample_rate as f64;
+        let last = self.samples.len() - 1;
+        let samples = (0..length)
+            .map(|index| {
+                let position = index as f64 * step;
+                let left = (position as usize).min(last);
+                let right = (left + 1).min(last);
+                let fraction = position - left as f64;
+                let value = self.samples[left] as f64 * (1.0 - fraction)
+                    + self.samples[right] as f64 * fraction;
+                value as i16
+            })
+            .collect();
+        Pcm {
+            sample_rate,
+            samples,
+        }
+    }
+}
+
+/// Decodes an 8 bit unsigned or 16 bit signed PCM WAV file, mixing all
+/// channels down to mono.
+pub fn decode_wav(bytes: &[u8]) -> Result<Pcm, WavError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(WavError::NotAWav);
+    }
+    let mut format = None;
+    let mut data = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size =
+            u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().expect("4 bytes")) as usize;
+        let body = bytes
+            .get(pos + 8..pos + 8 + size)
+            .ok_or(WavError::Truncated)?;
+        match id {
+            b"fmt " => format = Some(body),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // chunks are padded to an even size
+        pos += 8 + size + (size & 1);
+    }
+    let format = format.ok_or(WavError::NotAWav)?;
+    let data = data.ok_or(WavError::NotAWav)?;
+    if format.len() < 16 {
+        return Err(WavError::Truncated);
+    }
+
+    let encoding = u16::from_le_bytes([format[0], format[1]]);
+    let channels = u16::from_le_bytes([format[2], format[3]]) as usize;
+    let sample_rate = u32::from_le_bytes(format[4..8].try_into().expect("4 bytes"));
+    let bits = u16::from_le_bytes([format[14], format[15]]);
+    if encoding != 1 {
+        return Err(WavError::Unsupported(format!("encoding {}", encoding)));
+    }
+    if channels == 0 || sample_rate == 0 {
+        return Err(WavError::Unsupported("no channels".to_owned()));
+    }
+
+    let frame_size = channels * (bits as usize / 8);
+    let decode_frame = |frame: &[u8]| -> i16 {
+        let sum: i32 = match bits {
+            8 => frame.iter().map(|&s| ((s as i32) - 128) << 8).sum(),
+            _ => frame
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                .sum(),
+        };
+        (sum / channels as i32) as i16
+    };
+    let samples = match bits {
+        8 | 16 => data.chunks_exact(frame_size).map(decode_frame).collect(),
+        _ => return Err(WavError::Unsupported(format!("{} bit samples", bits))),
+    };
+    Ok(Pcm {
+        sample_rate,
+        samples,
+    })
+}
+
+/// Reads and decodes the WAV file at `file_path`.
+pub fn read_wav(file_path: &str) -> Result<Pcm, WavError> {
+    decode_wav(&fs::read(file_path)?)
+}
+
+/// Streams mono 16 bit PCM into a WAV file.
+///
+/// The sizes in the header are filled in by `finish`.
+pub struct WavWriter {
+    file: BufWriter<File>,
+    sample_rate: u32,
+    samples_written: u32,
+}
+
+impl WavWriter {
+    /// Creates `file_path` and writes a header for `sample_rate`.
+    pub fn create(file_path: &str, sample_rate: u32) -> io::Result<Self> {
+        let mut writer = Self {
+            file: BufWriter::new(File::create(file_path)?),
+            sample_rate,
+            samples_written: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    /// Writes the 44 byte RIFF header for the samples written so far.
+    fn write_header(&mut self) -> io::Result<()> {
+        let data_size = self.samples_written * 2;
+        let mut header = Vec::with_capacity(44);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(36 + data_size).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, mono
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        // block align, bits per sample
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_size.to_le_bytes());
+        self.file.write_all(&header)
+    }
+
+    /// Appends `samples` to the file.
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples_written += samples.len() as u32;
+        Ok(())
+    }
+
+    /// Fills in the header and flushes the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.file.flush()
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+use crate::hash;
+use crate::inflate::{self, InflateError};
+
+/// Signature of the end of central directory record.
+const END_SIGNATURE: u32 = 0x0605_4b50;
+/// Signature of a central directory file header.
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+/// Signature of a local file header.
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+/// Size of the end of central directory record, without the comment.
+const END_SIZE: usize = 22;
+/// Size of a central directory file header, without the variable fields.
+const CENTRAL_SIZE: usize = 46;
+/// Size of a local file header, without the variable fields.
+const LOCAL_SIZE: usize = 30;
+
+/// Compression method of stored files.
+const STORED: u16 = 0;
+/// Compression method of deflated files.
+const DEFLATED: u16 = 8;
+/// General purpose flag of encrypted files.
+const ENCRYPTED: u16 = 0x0001;
+
+/// Error returned for an archive that can not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ZipError {
+    /// No end of central directory record, or a record pointing outside
+    /// the file.
+    NotAnArchive,
+    /// A header is cut short or has the wrong signature.
+    Corrupt(String),
+    /// A file uses something other than store or deflate, encryption or
+    /// ZIP64.
+    Unsupported {
+        name: String,
+        feature: String,
+    },
+    Inflate {
+        name: String,
+        err: InflateError,
+    },
+    /// The CRC-32 of a file does not match the one in the archive.
+    Checksum {
+        name: String,
+    },
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZipError::NotAnArchive => write!(f, "not a zip archive"),
+            ZipError::Corrupt(what) => write!(f, "corrupt zip archive: {}", what),
+            ZipError::Unsupported { name, feature } => {
+                write!(
+                    f,
+                    "{} in the archive uses {}, which is not supported",
+                    name, feature
+                )
+            }
+            ZipError::Inflate { name, err } => write!(f, "{} in the archive: {}", name, err),
+            ZipError::Checksum { name } => {
+                write!(f, "{} in the archive does not match its CRC-32", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ZipError {}
+
+/// A file of a zip archive, as listed in its central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    /// Path of the file in the archive.
+    pub name: String,
+    pub crc32: u32,
+    /// Uncompressed size.
+    pub size: u32,
+    compressed_size: u32,
+    method: u16,
+    flags: u16,
+    /// Offset of the local file header.
+    offset: u32,
+}
+
+impl ZipEntry {
+    /// The name without the directories in front of it.
+    pub fn file_name(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or(&self.name)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// A zip archive held in memory. Files can be stored or deflated.
+#[derive(Debug, Clone)]
+pub struct ZipArchive {
+    data: Vec<u8>,
+    entries: Vec<ZipEntry>,
+}
+
+impl ZipArchive {
+    /// Reads the central directory of the archive in `data`.
+    pub fn new(data: Vec<u8>) -> Result<Self, ZipError> {
+        // the record is at the end, followed by a comment of up to 64K
+        let end = (0..=data.len().saturating_sub(END_SIZE))
+            .rev()
+            .take(END_SIZE + 0xffff)
+            .find(|&offset| read_u32(&data, offset) == Some(END_SIGNATURE))
+            .ok_or(ZipError::NotAnArchive)?;
+        let count = read_u16(&data, end + 10).ok_or(ZipError::NotAnArchive)?;
+        let mut offset = read_u32(&data, end + 16).ok_or(ZipError::NotAnArchive)? as usize;
+
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let header = data
+                .get(offset..offset + CENTRAL_SIZE)
+                .filter(|header| read_u32(header, 0) == Some(CENTRAL_SIGNATURE))
+                .ok_or_else(|| ZipError::Corrupt("central directory header".to_owned()))?;
+            let field16 = |at| read_u16(header, at).expect("header is long enough");
+            let field32 = |at| read_u32(header, at).expect("header is long enough");
+            let name_length = field16(28) as usize;
+            let variable_length = name_length + field16(30) as usize + field16(32) as usize;
+            let name = data
+                .get(offset + CENTRAL_SIZE..offset + CENTRAL_SIZE + name_length)
+                .ok_or_else(|| ZipError::Corrupt("file name".to_owned()))?;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                crc32: field32(16),
+                size: field32(24),
+                compressed_size: field32(20),
+                method: field16(10),
+                flags: field16(8),
+                offset: field32(42),
+            });
+            offset += CENTRAL_SIZE + variable_length;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// The files and directories of the archive, in the order of the
+    /// central directory.
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// Extracts `entry`, checking its CRC-32.
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, ZipError> {
+        let unsupported = |feature: &str| ZipError::Unsupported {
+            name: entry.name.clone(),
+            feature: feature.to_owned(),
+        };
+        if entry.flags & ENCRYPTED != 0 {
+            return Err(unsupported("encryption"));
+        }
+        if entry.size == u32::MAX || entry.compressed_size == u32::MAX {
+            return Err(unsupported("ZIP64"));
+        }
+        let offset = entry.offset as usize;
+        let corrupt = || ZipError::Corrupt(format!("local header of {}", entry.name));
+        if read_u32(&self.data, offset) != Some(LOCAL_SIGNATURE) {
+            return Err(corrupt());
+        }
+        let name_length = read_u16(&self.data, offset + 26).ok_or_else(corrupt)? as usize;
+        let extra_length = read_u16(&self.data, offset + 28).ok_or_else(corrupt)? as usize;
+        let start = offset + LOCAL_SIZE + name_length + extra_length;
+        let compressed = self
+            .data
+            .get(start..start + entry.compressed_size as usize)
+            .ok_or_else(corrupt)?;
+
+        let bytes = match entry.method {
+            STORED => compressed.to_vec(),
+            DEFLATED => inflate::inflate(compressed, entry.size as usize).map_err(|err| {
+                ZipError::Inflate {
+                    name: entry.name.clone(),
+                    err,
+                }
+            })?,
+            method => return Err(unsupported(&format!("compression method {}", method))),
+        };
+        if bytes.len() != entry.size as usize || hash::crc32(&bytes) != entry.crc32 {
+            return Err(ZipError::Checksum {
+                name: entry.name.clone(),
+            });
+        }
+        Ok(bytes)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(
+        bytes.try_into().expect("slice is 4 bytes"),
+    ))
+}
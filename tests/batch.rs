@@ -0,0 +1,30 @@
+//! Checks that the results of a `BatchRunner` do not depend on its
+//! threads.
+
+mod common;
+
+use space_invaders_emulator::batch::{BatchRunner, StackedObservations};
+use space_invaders_emulator::env::ObservationKind;
+
+use common::{actions, runner, INSTANCES};
+
+/// Runs 100 steps and returns the stacked state vectors of every step.
+fn play(runner: &mut BatchRunner) -> Vec<f32> {
+    runner.reset();
+    let mut states = Vec::new();
+    for step in 0..100 {
+        let result = runner.step(&actions(step));
+        match result.observations {
+            StackedObservations::State(values) => states.extend_from_slice(values),
+            StackedObservations::Screen(_) => panic!("asked for state vectors"),
+        }
+    }
+    states
+}
+
+#[test]
+fn results_do_not_depend_on_threads() {
+    let one = play(&mut runner(ObservationKind::State, 1));
+    assert_eq!(play(&mut runner(ObservationKind::State, 4)), one);
+    assert_eq!(play(&mut runner(ObservationKind::State, INSTANCES)), one);
+}
@@ -0,0 +1,51 @@
+//! Checks that stepping a `BatchRunner` does not allocate. The test is
+//! alone in its binary, so that the allocations it counts are its own.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use space_invaders_emulator::env::{ObservationKind, PlayerAction};
+
+use common::{actions, runner};
+
+/// Counts the allocations of every thread.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Steps after which a game is taken to never end.
+const MAX_STEPS: usize = 20_000;
+
+#[test]
+fn stepping_does_not_allocate() {
+    for observation in [ObservationKind::Screen, ObservationKind::State] {
+        let mut runner = runner(observation, 3);
+        runner.reset();
+        let actions: Vec<Vec<PlayerAction>> = (0..MAX_STEPS).map(actions).collect();
+        // play until an instance reaches game over and is reset, which
+        // reloads its start state
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let mut step = 0;
+        while !runner.step(&actions[step]).dones.contains(&true) {
+            step += 1;
+            assert!(step < MAX_STEPS, "no game ended");
+        }
+        assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), before);
+    }
+}
@@ -0,0 +1,87 @@
+//! Checks the memory map of the board: ROM at 0x0000-0x1fff, RAM at
+//! 0x2000-0x3fff and nothing elsewhere.
+
+use space_invaders_emulator::emulator::Access;
+use space_invaders_emulator::{CpuError, SpaceInvaders, StepOutcome};
+
+/// A board with `program` at 0 in its ROM.
+fn machine(program: &[u8]) -> SpaceInvaders {
+    SpaceInvaders::new(program)
+}
+
+#[test]
+fn ram_is_writable() {
+    // MVI A 0x55, STA 0x2400, LDA 0x2400
+    let mut machine = machine(&[0x3e, 0x55, 0x32, 0x00, 0x24, 0x3a, 0x00, 0x24]);
+    for _ in 0..3 {
+        assert!(matches!(machine.step(), Ok(StepOutcome::Executed { .. })));
+    }
+    assert_eq!(machine.cpu.ra, 0x55);
+}
+
+#[test]
+fn rom_writes_are_faults() {
+    // MVI A 0x55, STA 0x0100
+    let mut machine = machine(&[0x3e, 0x55, 0x32, 0x00, 0x01]);
+    machine.step().expect("MVI runs");
+    assert_eq!(
+        machine.step(),
+        Err(CpuError::BusFault {
+            pc: 0x0002,
+            address: 0x0100,
+            access: Access::Write,
+            cycles: 13,
+        })
+    );
+    assert_eq!(machine.cpu.read_byte(0x0100), 0x00);
+    // the instruction ran
+    assert_eq!(machine.cpu.pc, 0x0005);
+}
+
+#[test]
+fn accesses_past_the_map_are_faults() {
+    // LDA 0x4000, STA 0x8000
+    let mut machine = machine(&[0x3a, 0x00, 0x40, 0x32, 0x00, 0x80]);
+    assert_eq!(
+        machine.step(),
+        Err(CpuError::BusFault {
+            pc: 0x0000,
+            address: 0x4000,
+            access: Access::Read,
+            cycles: 13,
+        })
+    );
+    assert_eq!(machine.cpu.ra, 0xff);
+    assert!(matches!(
+        machine.step(),
+        Err(CpuError::BusFault {
+            address: 0x8000,
+            access: Access::Write,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn faults_keep_the_frame_timing() {
+    // LDA 0x4000 forever
+    let mut machine = machine(&[0x3a, 0x00, 0x40, 0xc3, 0x00, 0x00]);
+    machine.step().expect_err("reads outside the map");
+    assert_eq!(machine.frame_cycles(), 13);
+    machine.step().expect("JMP runs");
+    assert_eq!(machine.frame_cycles(), 23);
+}
+
+#[test]
+fn power_on_keeps_the_map() {
+    // STA 0x0100
+    let mut machine = machine(&[0x32, 0x00, 0x01]);
+    machine.power_on();
+    assert!(matches!(
+        machine.step(),
+        Err(CpuError::BusFault {
+            access: Access::Write,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,32 @@
+//! Helpers shared by the `BatchRunner` tests.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::batch::BatchRunner;
+use space_invaders_emulator::env::{EnvConfig, ObservationKind, PlayerAction};
+
+/// Instances of the runners.
+pub const INSTANCES: usize = 6;
+
+/// A runner on the real ROM with sticky actions and a fixed seed.
+pub fn runner(observation: ObservationKind, threads: usize) -> BatchRunner {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("rom/invaders")).expect("can not read rom/invaders");
+    let config = EnvConfig {
+        sticky_action_probability: 0.25,
+        observation,
+        seed: 7,
+        ..EnvConfig::default()
+    };
+    let mut runner = BatchRunner::from_rom(&rom, INSTANCES, config);
+    runner.set_threads(threads);
+    runner
+}
+
+/// The actions of every instance at `step`.
+pub fn actions(step: usize) -> Vec<PlayerAction> {
+    (0..INSTANCES)
+        .map(|index| PlayerAction::ALL[(step / 8 + index) % PlayerAction::ALL.len()])
+        .collect()
+}
@@ -0,0 +1,101 @@
+//! Parses config files and the key bindings of their `[input]` section.
+
+use space_invaders_emulator::cli::CONFIG_SECTIONS;
+use space_invaders_emulator::config::{Config, ConfigError};
+use space_invaders_emulator::input::{Action, InputMap, Key};
+
+fn error(line: usize, message: &str) -> ConfigError {
+    ConfigError {
+        line,
+        message: message.to_owned(),
+    }
+}
+
+#[test]
+fn parses_sections_and_entries() {
+    let config = Config::parse(
+        "# keys\n\
+         [Input]\n\
+         Coin = c, 5  # two keys\n\
+         \n\
+         [dip]\n\
+         ships = 5\n",
+    )
+    .unwrap();
+    let input: Vec<_> = config.section("input").collect();
+    assert_eq!(input.len(), 1);
+    assert_eq!(
+        (
+            input[0].line,
+            input[0].name.as_str(),
+            input[0].value.as_str()
+        ),
+        (3, "coin", "c, 5")
+    );
+    assert_eq!(config.section("dip").count(), 1);
+    assert_eq!(config.check_sections(CONFIG_SECTIONS), Ok(()));
+}
+
+#[test]
+fn rejects_malformed_lines() {
+    assert_eq!(
+        Config::parse("[input\ncoin = c"),
+        Err(error(1, "section header is missing `]`"))
+    );
+    assert_eq!(
+        Config::parse("[input]\ncoin c"),
+        Err(error(2, "expected `<name> = <value>`"))
+    );
+    assert_eq!(
+        Config::parse("[input]\n = c"),
+        Err(error(2, "setting has no name"))
+    );
+}
+
+#[test]
+fn rejects_unknown_sections() {
+    let config = Config::parse("[input]\ncoin = c\n[inptu]\ntilt = t").unwrap();
+    assert_eq!(
+        config.check_sections(CONFIG_SECTIONS),
+        Err(error(4, "unknown section `[inptu]`"))
+    );
+    let config = Config::parse("coin = c").unwrap();
+    assert_eq!(
+        config.check_sections(CONFIG_SECTIONS),
+        Err(error(1, "`coin` is not in a section"))
+    );
+}
+
+#[test]
+fn input_section_replaces_the_keys_of_listed_actions() {
+    let config = Config::parse("[input]\ncoin = 5, space\ntilt =").unwrap();
+    let map = InputMap::from_config(&config).unwrap();
+    assert_eq!(
+        map.keys_for(Action::Coin).collect::<Vec<_>>(),
+        [Key::Char('5'), Key::Char(' ')]
+    );
+    assert_eq!(map.keys_for(Action::Tilt).count(), 0);
+    // space presses fire as well as the coin
+    assert_eq!(
+        map.actions_for(Key::Char(' ')).collect::<Vec<_>>(),
+        [Action::P1Fire, Action::Coin]
+    );
+    assert_eq!(
+        map.keys_for(Action::P1Start).collect::<Vec<_>>(),
+        [Key::Char('1')]
+    );
+}
+
+#[test]
+fn input_section_rejects_unknown_actions_and_keys() {
+    let config = Config::parse("[input]\nfire = space").unwrap();
+    assert_eq!(
+        InputMap::from_config(&config),
+        Err(error(2, "unknown action `fire`"))
+    );
+    let config = Config::parse("[input]\ncoin = c, insert").unwrap();
+    assert_eq!(
+        InputMap::from_config(&config),
+        Err(error(2, "unknown key `insert`"))
+    );
+}
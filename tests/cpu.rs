@@ -0,0 +1,293 @@
+//! Instruction level tests of the 8080 core, with the examples of the
+//! Intel 8080 programmer's manual where it gives them.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::emulator::RegPair;
+use space_invaders_emulator::{Emulator8080, IoDevices, StepOutcome};
+
+const S: u8 = 0x80;
+const Z: u8 = 0x40;
+const AC: u8 = 0x10;
+const P: u8 = 0x04;
+/// Always set in the 8080 PSW.
+const ONE: u8 = 0x02;
+const CY: u8 = 0x01;
+
+struct NoIo;
+
+impl IoDevices for NoIo {
+    fn input(&mut self, _port: u8) -> u8 {
+        0
+    }
+
+    fn output(&mut self, _port: u8, _value: u8) {}
+}
+
+/// An 8080 with `program` at 0 and the stack at 0x1000.
+fn cpu(program: &[u8]) -> Emulator8080 {
+    let mut cpu = Emulator8080::empty();
+    cpu.memory[..program.len()].copy_from_slice(program);
+    cpu.sp = 0x1000;
+    cpu
+}
+
+/// Runs one instruction and returns its cycles.
+fn step(cpu: &mut Emulator8080) -> u32 {
+    match cpu.step(&mut NoIo) {
+        Ok(StepOutcome::Executed { cycles }) => cycles,
+        other => panic!("unexpected step result {:?}", other),
+    }
+}
+
+#[test]
+fn add_sets_every_flag() {
+    // ADD D
+    let mut cpu = cpu(&[0x82]);
+    cpu.ra = 0x6c;
+    cpu.rd = 0x2e;
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x9a);
+    assert_eq!(cpu.psw(), S | AC | P | ONE);
+
+    // ADD B, carrying out to zero
+    let mut cpu = self::cpu(&[0x80]);
+    cpu.ra = 0x80;
+    cpu.rb = 0x80;
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x00);
+    assert_eq!(cpu.psw(), Z | P | ONE | CY);
+}
+
+#[test]
+fn adc_adds_the_carry() {
+    // STC, ACI 0x0f
+    let mut cpu = cpu(&[0x37, 0xce, 0x0f]);
+    cpu.ra = 0x30;
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x40);
+    assert_eq!(cpu.psw(), AC | ONE);
+}
+
+#[test]
+fn sub_and_cmp_borrow() {
+    // SUB A
+    let mut cpu = cpu(&[0x97]);
+    cpu.ra = 0x3e;
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x00);
+    assert_eq!(cpu.psw(), Z | AC | P | ONE);
+
+    // CMP E leaves A alone and borrows
+    let mut cpu = self::cpu(&[0xbb]);
+    cpu.ra = 0x02;
+    cpu.re = 0x05;
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x02);
+    assert_eq!(cpu.psw(), S | ONE | CY);
+
+    // STC, SBI 0x01
+    let mut cpu = self::cpu(&[0x37, 0xde, 0x01]);
+    cpu.ra = 0x04;
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x02);
+    assert_eq!(cpu.psw(), AC | ONE);
+}
+
+#[test]
+fn inr_and_dcr_keep_the_carry() {
+    // STC, INR A
+    let mut cpu = cpu(&[0x37, 0x3c]);
+    cpu.ra = 0xff;
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x00);
+    assert_eq!(cpu.psw(), Z | AC | P | ONE | CY);
+
+    // DCR M
+    let mut cpu = self::cpu(&[0x35]);
+    cpu.set_reg_pair(0x0800, RegPair::HL);
+    cpu.memory[0x0800] = 0x40;
+    step(&mut cpu);
+    assert_eq!(cpu.memory[0x0800], 0x3f);
+    assert_eq!(cpu.psw(), P | ONE);
+}
+
+#[test]
+fn daa_adjusts_both_digits() {
+    let mut cpu = cpu(&[0x27]);
+    cpu.ra = 0x9b;
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x01);
+    assert_eq!(cpu.psw(), AC | ONE | CY);
+
+    // MVI A 0x38, ADI 0x45, DAA: 38 + 45 = 83 in decimal
+    let mut cpu = self::cpu(&[0x3e, 0x38, 0xc6, 0x45, 0x27]);
+    step(&mut cpu);
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x83);
+    assert_eq!(cpu.psw() & CY, 0);
+}
+
+#[test]
+fn logical_operations_clear_the_carry() {
+    // STC, ANI 0x0f: AC is the OR of bit 3 of the operands
+    let mut cpu = cpu(&[0x37, 0xe6, 0x0f]);
+    cpu.ra = 0xfc;
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x0c);
+    assert_eq!(cpu.psw(), AC | P | ONE);
+
+    // STC, XRA A
+    let mut cpu = self::cpu(&[0x37, 0xaf]);
+    cpu.ra = 0x5a;
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x00);
+    assert_eq!(cpu.psw(), Z | P | ONE);
+
+    // STC, ORI 0x80
+    let mut cpu = self::cpu(&[0x37, 0xf6, 0x80]);
+    cpu.ra = 0x01;
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(cpu.ra, 0x81);
+    assert_eq!(cpu.psw(), S | P | ONE);
+}
+
+#[test]
+fn rotates_go_through_the_carry() {
+    // RAL, RAR
+    let mut cpu = cpu(&[0x17, 0x1f]);
+    cpu.ra = 0xb5;
+    step(&mut cpu);
+    assert_eq!((cpu.ra, cpu.psw() & CY), (0x6a, CY));
+    step(&mut cpu);
+    assert_eq!((cpu.ra, cpu.psw() & CY), (0xb5, 0));
+
+    // RLC, RRC
+    let mut cpu = self::cpu(&[0x07, 0x0f, 0x0f]);
+    cpu.ra = 0xf2;
+    step(&mut cpu);
+    assert_eq!((cpu.ra, cpu.psw() & CY), (0xe5, CY));
+    step(&mut cpu);
+    assert_eq!((cpu.ra, cpu.psw() & CY), (0xf2, CY));
+    step(&mut cpu);
+    assert_eq!((cpu.ra, cpu.psw() & CY), (0x79, 0));
+}
+
+#[test]
+fn dad_sets_only_the_carry() {
+    // DAD B, DAD D
+    let mut cpu = cpu(&[0x09, 0x19]);
+    cpu.set_reg_pair(0xa17b, RegPair::HL);
+    cpu.set_reg_pair(0x339f, RegPair::BC);
+    cpu.set_reg_pair(0x2ae6, RegPair::DE);
+    step(&mut cpu);
+    assert_eq!(cpu.get_reg_pair(RegPair::HL), 0xd51a);
+    assert_eq!(cpu.psw(), ONE);
+    step(&mut cpu);
+    assert_eq!(cpu.get_reg_pair(RegPair::HL), 0x0000);
+    assert_eq!(cpu.psw(), ONE | CY);
+}
+
+#[test]
+fn pop_psw_fixes_the_unused_bits() {
+    // LXI B 0x00ff, PUSH B, POP PSW, PUSH PSW
+    let mut cpu = cpu(&[0x01, 0xff, 0x00, 0xc5, 0xf1, 0xf5]);
+    for _ in 0..4 {
+        step(&mut cpu);
+    }
+    assert_eq!(cpu.ra, 0x00);
+    assert_eq!(cpu.psw(), S | Z | AC | P | ONE | CY);
+    assert_eq!(cpu.memory[0x0ffe], S | Z | AC | P | ONE | CY);
+}
+
+#[test]
+fn conditional_calls_and_returns_take_longer_when_taken() {
+    // CNZ 0x0010 not taken, CZ 0x0010 taken; RNZ not taken, RZ taken
+    let mut cpu = cpu(&[0xaf, 0xc4, 0x10, 0x00, 0xcc, 0x10, 0x00]);
+    cpu.memory[0x10] = 0xc0;
+    cpu.memory[0x11] = 0xc8;
+    assert_eq!(step(&mut cpu), 4);
+    assert_eq!(step(&mut cpu), 11);
+    assert_eq!(cpu.pc, 0x0004);
+    assert_eq!(step(&mut cpu), 17);
+    assert_eq!(cpu.pc, 0x0010);
+    assert_eq!(step(&mut cpu), 5);
+    assert_eq!(step(&mut cpu), 11);
+    assert_eq!(cpu.pc, 0x0007);
+    assert_eq!(cpu.sp, 0x1000);
+}
+
+#[test]
+fn xthl_and_xchg_swap_pairs() {
+    // XCHG, XTHL
+    let mut cpu = cpu(&[0xeb, 0xe3]);
+    cpu.set_reg_pair(0x1234, RegPair::HL);
+    cpu.set_reg_pair(0xabcd, RegPair::DE);
+    cpu.sp = 0x0800;
+    cpu.write_word(0x0800, 0x5678);
+    step(&mut cpu);
+    assert_eq!(cpu.get_reg_pair(RegPair::HL), 0xabcd);
+    assert_eq!(cpu.get_reg_pair(RegPair::DE), 0x1234);
+    assert_eq!(step(&mut cpu), 18);
+    assert_eq!(cpu.get_reg_pair(RegPair::HL), 0x5678);
+    assert_eq!(cpu.read_word(0x0800), 0xabcd);
+}
+
+/// Runs a CP/M program at 0x100, with BDOS calls 2 and 9 printing to the
+/// returned string, until it jumps back to 0.
+fn run_cpm(program: &[u8]) -> String {
+    let mut cpu = Emulator8080::empty();
+    cpu.memory[0x100..0x100 + program.len()].copy_from_slice(program);
+    // warm boot halts, BDOS returns at once
+    cpu.memory[0x0000] = 0x76;
+    cpu.memory[0x0005] = 0xc9;
+    cpu.pc = 0x100;
+    let mut output = String::new();
+    while !cpu.halted {
+        if cpu.pc == 0x0005 {
+            match cpu.rc {
+                2 => output.push(cpu.re as char),
+                9 => {
+                    let mut address = cpu.get_reg_pair(RegPair::DE);
+                    while cpu.read_byte(address) != b'$' {
+                        output.push(cpu.read_byte(address) as char);
+                        address = address.wrapping_add(1);
+                    }
+                }
+                _ => {}
+            }
+        }
+        cpu.emulate_instruction(&mut NoIo);
+    }
+    output
+}
+
+/// Runs the CPU exercisers in the directory `SPAINEM_CPU_TESTS` names:
+/// CPUTEST.COM (SuperSoft diagnostics) and 8080EX1.COM (Ian
+/// Bartholomew's exerciser). They are not distributed with the emulator.
+///
+/// `SPAINEM_CPU_TESTS=dir cargo test --release -- --ignored exercisers`
+#[test]
+#[ignore = "needs the exerciser programs, takes minutes"]
+fn exercisers() {
+    let dir = env::var("SPAINEM_CPU_TESTS").expect("SPAINEM_CPU_TESTS is not set");
+    for name in ["CPUTEST.COM", "8080EX1.COM"] {
+        let program = fs::read(Path::new(&dir).join(name)).expect("can not read the program");
+        let output = run_cpm(&program);
+        println!("{}:\n{}", name, output);
+        assert!(
+            !output.contains("ERROR") && !output.contains("FAIL"),
+            "{} failed",
+            name
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Drives the debugger and the tracer with the real ROM.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::debugger::{self, Debugger};
+use space_invaders_emulator::golden::InputScript;
+use space_invaders_emulator::SpaceInvaders;
+
+fn machine() -> SpaceInvaders {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("rom/invaders")).expect("can not read rom/invaders");
+    SpaceInvaders::new(&rom)
+}
+
+#[test]
+fn continue_stops_at_breakpoint() {
+    let mut out = Vec::new();
+    let mut debugger = Debugger::new(machine(), InputScript::new(), &mut out);
+    let input = "b 1a5f\nc\nd 1a5f 1\nq\nr\n";
+    debugger
+        .run(input.as_bytes())
+        .expect("writing to a Vec succeeds");
+    assert_eq!(debugger.machine.cpu.pc, 0x1a5f);
+
+    let out = String::from_utf8(out).expect("output is UTF-8");
+    assert!(out.contains("breakpoint at 1a5f set\n"));
+    assert!(out.contains("breakpoint at 1a5f\n"));
+    assert!(out.contains("1a5f:    MVI   M, #$00\n"));
+    // nothing runs after q
+    assert!(out.ends_with("(1a5f) "));
+}
+
+#[test]
+fn trace_writes_a_line_per_instruction() {
+    let mut machine = machine();
+    let mut out = Vec::new();
+    debugger::trace(&mut machine, &InputScript::new(), 0, 1, &mut out)
+        .expect("the game runs a frame");
+    let out = String::from_utf8(out).expect("output is UTF-8");
+    let mut lines = out.lines();
+    assert_eq!(
+        lines.next(),
+        Some("     0 0000  NOP                a=00 f=02 bc=0000 de=0000 hl=0000 sp=0000")
+    );
+    assert!(lines.all(|line| line.starts_with("     0 ")));
+    assert_eq!(machine.frame, 1);
+}
@@ -0,0 +1,100 @@
+//! Sets the DIP switches from the config file and the command line.
+
+use space_invaders_emulator::cli::{Args, CliError, MachineOptions};
+use space_invaders_emulator::config::{Config, ConfigError};
+use space_invaders_emulator::dip::{DipError, DipSwitches};
+use space_invaders_emulator::games;
+
+/// Parses `args` as machine options, failing on anything else.
+fn parse(args: &[&str]) -> Result<MachineOptions, CliError> {
+    let args: Vec<String> = args.iter().map(|&arg| arg.to_owned()).collect();
+    let mut options = MachineOptions::default();
+    let mut args = Args::new(&args);
+    while let Some(arg) = args.next() {
+        assert!(
+            options.parse_option(arg, &mut args)?,
+            "`{}` is not a machine option",
+            arg
+        );
+    }
+    Ok(options)
+}
+
+#[test]
+fn settings_set_their_bits() {
+    let mut switches = DipSwitches::new(games::INVADERS.dip_switches);
+    assert_eq!(switches.port2_bits(), 0);
+    assert_eq!(switches.get("ships"), Some("3"));
+    switches.set("ships", "6").unwrap();
+    switches.set("bonus", "1000").unwrap();
+    switches.set("coin_info", "off").unwrap();
+    assert_eq!(switches.port2_bits(), 0x8b);
+    switches.set("ships", "4").unwrap();
+    assert_eq!(switches.port2_bits(), 0x89);
+    assert_eq!(switches.get("ships"), Some("4"));
+}
+
+#[test]
+fn set_rejects_unknown_settings_and_values() {
+    let mut switches = DipSwitches::new(games::INVADERS.dip_switches);
+    assert_eq!(
+        switches.set("lives", "3"),
+        Err(DipError::UnknownSetting("lives".to_owned()))
+    );
+    assert_eq!(
+        switches.set("ships", "7"),
+        Err(DipError::InvalidValue {
+            setting: "ships",
+            value: "7".to_owned(),
+            expected: "3 to 6",
+        })
+    );
+    assert_eq!(switches.port2_bits(), 0);
+}
+
+#[test]
+fn from_config_reports_the_line() {
+    let config = Config::parse("[dip]\nships = 5\nbonus = 2000").unwrap();
+    assert_eq!(
+        DipSwitches::from_config(games::INVADERS.dip_switches, &config),
+        Err(ConfigError {
+            line: 3,
+            message: "invalid value `2000` for bonus, expected 1000 or 1500".to_owned(),
+        })
+    );
+    let config = Config::parse("[dip]\nlives = 5").unwrap();
+    assert_eq!(
+        DipSwitches::from_config(games::INVADERS.dip_switches, &config)
+            .unwrap_err()
+            .line,
+        2
+    );
+}
+
+#[test]
+fn dip_without_equals_is_a_usage_error() {
+    assert!(matches!(
+        parse(&["--dip", "ships"]),
+        Err(CliError::Usage(_))
+    ));
+    assert!(matches!(parse(&["--dip"]), Err(CliError::Usage(_))));
+}
+
+#[test]
+fn out_of_range_ships_is_a_usage_error() {
+    let options = parse(&["--ships", "7"]).unwrap();
+    assert!(matches!(
+        options.dip_switches(&games::INVADERS, &Config::default()),
+        Err(CliError::Usage(message)) if message == "invalid value `7` for ships, expected 3 to 6"
+    ));
+}
+
+#[test]
+fn command_line_overrides_the_config_file() {
+    let config = Config::parse("[dip]\nships = 5\nbonus = 1000").unwrap();
+    let options = parse(&["--ships", "6", "--dip", "coin_info=off"]).unwrap();
+    let switches = options.dip_switches(&games::INVADERS, &config).unwrap();
+    assert_eq!(switches.get("ships"), Some("6"));
+    assert_eq!(switches.get("bonus"), Some("1000"));
+    assert_eq!(switches.get("coin_info"), Some("off"));
+}
@@ -0,0 +1,76 @@
+//! Plays whole episodes of the environment on the real ROM.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::env::{Env, EnvConfig, ObservationKind, PlayerAction};
+use space_invaders_emulator::game_state::{GameMode, GameState};
+use space_invaders_emulator::SpaceInvaders;
+
+/// Steps after which an episode is taken to never end.
+const MAX_STEPS: usize = 20_000;
+
+fn env() -> Env {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders");
+    Env::new(
+        SpaceInvaders::new(&rom),
+        EnvConfig {
+            observation: ObservationKind::State,
+            ..EnvConfig::default()
+        },
+    )
+}
+
+/// Fires every other step, the game needs the button released between
+/// shots, while sweeping left and right.
+fn action(step: usize) -> PlayerAction {
+    match (step % 50 < 25, step.is_multiple_of(2)) {
+        (true, true) => PlayerAction::LeftFire,
+        (true, false) => PlayerAction::Left,
+        (false, true) => PlayerAction::RightFire,
+        (false, false) => PlayerAction::Right,
+    }
+}
+
+#[test]
+fn episode_scores_and_ends_when_the_lives_run_out() {
+    let mut env = env();
+    let first = env.reset();
+    let state = GameState::read(env.machine());
+    assert_eq!((state.mode, state.scores[0]), (GameMode::Playing, 0));
+
+    let mut lives = Vec::new();
+    let mut total = 0.0;
+    let mut scored = false;
+    let mut steps = 0;
+    loop {
+        assert!(steps < MAX_STEPS, "the episode did not end");
+        let (_, reward, done) = env.step(action(steps));
+        steps += 1;
+        assert!(reward >= 0.0);
+        scored |= reward > 0.0;
+        total += reward;
+        if done {
+            break;
+        }
+        let state = GameState::read(env.machine());
+        if lives.last() != Some(&state.lives) {
+            lives.push(state.lives);
+        }
+    }
+    assert!(scored, "no invader was ever hit");
+    // the game sets up the ships shortly after it starts
+    assert_eq!(lives[lives.len() - 3..], [3, 2, 1]);
+    let state = GameState::read(env.machine());
+    assert_eq!(state.mode, GameMode::Attract);
+    // the score stays on screen after game over
+    assert_eq!(total, state.scores[0] as f32);
+
+    // reset starts a fresh game, with the same observation as the first
+    assert_eq!(env.reset(), first);
+    let state = GameState::read(env.machine());
+    assert_eq!((state.mode, state.scores[0]), (GameMode::Playing, 0));
+    let (_, reward, done) = env.step(PlayerAction::Noop);
+    assert_eq!((reward, done), (0.0, false));
+}
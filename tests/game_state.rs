@@ -0,0 +1,52 @@
+//! Reads the game state of a real game and checks it against the RAM.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::game_state::{GameMode, GameState, Position};
+use space_invaders_emulator::machine::{self, P1_FIRE, P1_LEFT, P1_RIGHT};
+use space_invaders_emulator::SpaceInvaders;
+
+/// Frames to run: the UFO is flying over the second ship of the game.
+const FRAMES: u64 = 2581;
+
+/// Port 1 during `frame`: a coin, 1P start, then firing all along while
+/// moving left and right.
+fn port1(frame: u64) -> u8 {
+    match frame {
+        60..=65 => machine::COIN,
+        120..=125 => machine::P1_START,
+        200.. => {
+            let fire = if frame % 16 < 8 { P1_FIRE } else { 0 };
+            let direction = if frame % 200 < 100 { P1_LEFT } else { P1_RIGHT };
+            fire | direction
+        }
+        _ => 0,
+    }
+}
+
+#[test]
+fn state_matches_the_ram() {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders");
+    let mut machine = SpaceInvaders::new(&rom);
+    for frame in 0..FRAMES {
+        machine.io.port1 = port1(frame);
+        machine.run_frame();
+    }
+    let ram = |address: usize| machine.cpu.memory[address];
+    let state = GameState::read(&machine);
+
+    assert_eq!(state.mode, GameMode::Playing);
+    assert_eq!(state.current_player, 1);
+    assert_eq!(state.player.x, ram(0x201b));
+    assert_eq!(state.player, Position { x: 99, y: 32 });
+    // the reserve ships of player 1, plus the one in play
+    assert_eq!(state.lives, ram(0x21ff) + 1);
+    assert_eq!(state.lives, 2);
+    assert_eq!([ram(0x20f8), ram(0x20f9)], [0x60, 0x01]);
+    assert_eq!(state.scores, [160, 0]);
+    assert_ne!(ram(0x2084), 0);
+    assert_eq!([ram(0x2089), ram(0x208a)], [208, 88]);
+    assert_eq!(state.ufo, Some(Position { x: 88, y: 208 }));
+}
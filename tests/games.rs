@@ -0,0 +1,55 @@
+//! Checks the registry of the games of the board.
+
+use space_invaders_emulator::games::{self, GAMES};
+use space_invaders_emulator::SpaceInvaders;
+
+#[test]
+fn every_game_is_found_by_name() {
+    for name in [
+        "invaders", "invadpt2", "invaddlx", "lrescue", "ballbomb", "spaceatt",
+    ] {
+        let game = games::find(name).unwrap_or_else(|| panic!("{} is not registered", name));
+        assert_eq!(game.name, name);
+    }
+    assert_eq!(games::find("galaxian"), None);
+    assert_eq!(GAMES.len(), 6);
+}
+
+#[test]
+fn clones_name_a_registered_parent() {
+    for game in GAMES {
+        if let Some(parent) = game.parent {
+            let parent = games::find(parent).expect("parent is registered");
+            assert_eq!(parent.parent, None, "{} is a clone of a clone", game.name);
+        }
+    }
+}
+
+#[test]
+fn chips_fit_the_board_without_overlapping() {
+    for game in GAMES {
+        let mut ranges: Vec<(usize, usize)> = game
+            .roms
+            .iter()
+            .map(|chip| (chip.address as usize, (chip.address + chip.size) as usize))
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            assert!(
+                pair[0].1 <= pair[1].0,
+                "{} has overlapping chips",
+                game.name
+            );
+        }
+        for &(start, end) in &ranges {
+            assert!(
+                end <= 0x2000 || (start >= 0x4000 && end <= 0x10000),
+                "{} maps a chip over RAM",
+                game.name
+            );
+        }
+        // every game boots, from a blank image of its size
+        let mut machine = SpaceInvaders::with_game(game, &vec![0; game.rom_size()]);
+        machine.run_frame();
+    }
+}
@@ -0,0 +1,60 @@
+//! Checks the emulation against golden frame hashes, so that any change
+//! to the CPU or the board shows up as the first frame that differs.
+//!
+//! After a change that is meant to alter the emulation, regenerate the
+//! hashes and commit them:
+//!
+//! `SPAINEM_BLESS=1 cargo test --test golden`
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::game_state::GameState;
+use space_invaders_emulator::golden::{self, InputScript};
+use space_invaders_emulator::SpaceInvaders;
+
+/// Frames the scripted game runs for.
+const FRAMES: u64 = 1200;
+
+#[test]
+fn scripted_game_matches_golden_hashes() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("rom/invaders")).expect("can not read rom/invaders");
+    let script = InputScript::parse(
+        &fs::read_to_string(root.join("tests/golden/play.txt")).expect("can not read the script"),
+    )
+    .expect("invalid script");
+
+    let mut machine = SpaceInvaders::new(&rom);
+    let hashes = golden::frame_hashes(&mut machine, &script, FRAMES);
+    let golden_path = root.join("tests/golden/play.hashes");
+    golden::assert_golden(golden_path.to_str().expect("path is UTF-8"), &hashes);
+}
+
+#[test]
+fn script_frames_count_from_the_start_of_the_run() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("rom/invaders")).expect("can not read rom/invaders");
+    let script = InputScript::parse(
+        &fs::read_to_string(root.join("tests/golden/play.txt")).expect("can not read the script"),
+    )
+    .expect("invalid script");
+
+    let mut machine = SpaceInvaders::new(&rom);
+    for _ in 0..500 {
+        machine.run_frame();
+    }
+    let mut applied = machine.clone();
+    let hashes = golden::frame_hashes(&mut machine, &script, 100);
+
+    let applied_hashes: Vec<u64> = (0..100)
+        .map(|frame| {
+            script.apply(&mut applied, frame);
+            applied.run_frame();
+            applied.frame_hash()
+        })
+        .collect();
+    assert_eq!(applied_hashes, hashes);
+    // the coin inserted on frame 60 of the script was counted
+    assert_eq!(GameState::read(&applied).credits, 1);
+}
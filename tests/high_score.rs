@@ -0,0 +1,63 @@
+//! Restores the high score into the running game and reads it from files.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::io_spainem::{self, HighScoreError};
+use space_invaders_emulator::SpaceInvaders;
+
+/// A path in the temporary directory unique to this test process.
+fn temp_path(name: &str) -> String {
+    env::temp_dir()
+        .join(format!("spainem-{}-{}", std::process::id(), name))
+        .to_str()
+        .expect("path is UTF-8")
+        .to_owned()
+}
+
+#[test]
+fn restored_score_is_written_once_attract_mode_starts() {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders");
+    let mut machine = SpaceInvaders::new(&rom);
+    machine.restore_high_score(1234);
+    while !machine.in_attract_mode() {
+        machine.run_frame();
+    }
+    machine.run_frame();
+    assert_eq!(machine.cpu.memory[0x20f4..=0x20f5], [0x34, 0x12]);
+
+    // the game keeps it through the demo
+    for _ in 0..600 {
+        machine.run_frame();
+    }
+    assert_eq!(machine.cpu.memory[0x20f4..=0x20f5], [0x34, 0x12]);
+    assert_eq!(machine.high_score(), Some(1234));
+}
+
+#[test]
+fn score_file_round_trip() {
+    let path = temp_path("round-trip");
+    assert!(matches!(io_spainem::read_high_score(&path), Ok(None)));
+    io_spainem::write_high_score(&path, 9990).unwrap();
+    let score = io_spainem::read_high_score(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(score, Ok(Some(9990))));
+}
+
+#[test]
+fn invalid_score_file_is_an_error() {
+    let path = temp_path("invalid");
+    for contents in ["12a\n", "10000\n", "-1\n", ""] {
+        fs::write(&path, contents).unwrap();
+        let result = io_spainem::read_high_score(&path);
+        assert!(
+            matches!(&result, Err(HighScoreError::NotAScore { path: found }) if *found == path),
+            "{:?} gave {:?}",
+            contents,
+            result
+        );
+    }
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,66 @@
+//! Decompresses one block of every type, made with zlib's raw deflate.
+
+use space_invaders_emulator::inflate::{inflate, InflateError};
+
+/// "SPACE INVADERS" in a stored block.
+const STORED: [u8; 19] = [
+    0x01, 0x0e, 0x00, 0xf1, 0xff, 0x53, 0x50, 0x41, 0x43, 0x45, 0x20, 0x49, 0x4e, 0x56, 0x41, 0x44,
+    0x45, 0x52, 0x53,
+];
+
+/// "INVADERS INVADERS INVADERS!" in a fixed Huffman block, with a match.
+const FIXED: [u8; 15] = [
+    0xf3, 0xf4, 0x0b, 0x73, 0x74, 0x71, 0x0d, 0x0a, 0x56, 0xf0, 0x44, 0x67, 0x28, 0x02, 0x00,
+];
+
+/// `dynamic_text()` in a dynamic Huffman block.
+const DYNAMIC: [u8; 64] = [
+    0xdd, 0xcc, 0x01, 0x09, 0x00, 0x40, 0x08, 0x03, 0xc0, 0x6c, 0x82, 0x03, 0x05, 0x51, 0xd8, 0xb4,
+    0x7f, 0x95, 0xef, 0xf1, 0x17, 0xe0, 0xb0, 0xe9, 0xb9, 0xb0, 0xae, 0xb6, 0x35, 0x9d, 0xc6, 0x46,
+    0x88, 0x9c, 0x0c, 0xb0, 0xb3, 0xb9, 0x54, 0xcb, 0xcd, 0xa5, 0xa8, 0xa9, 0x10, 0x2f, 0x8f, 0x41,
+    0x34, 0x9c, 0x0e, 0x6d, 0x79, 0xad, 0xec, 0xea, 0x2c, 0x0c, 0x87, 0xe1, 0x00, 0x9f, 0x9e, 0x0f,
+];
+
+fn dynamic_text() -> Vec<u8> {
+    (0..300)
+        .map(|i| b"ETAOINSHRDLU"[(i * i + i / 7) % 12])
+        .collect()
+}
+
+/// The three blocks with what they decompress to.
+fn blocks() -> [(&'static [u8], Vec<u8>); 3] {
+    [
+        (&STORED, b"SPACE INVADERS".to_vec()),
+        (&FIXED, b"INVADERS INVADERS INVADERS!".to_vec()),
+        (&DYNAMIC, dynamic_text()),
+    ]
+}
+
+#[test]
+fn block_types_are_read() {
+    for (index, (data, expected)) in blocks().into_iter().enumerate() {
+        assert_eq!(data[0] >> 1 & 3, index as u8, "block type");
+        assert_eq!(inflate(data, expected.len()), Ok(expected));
+    }
+}
+
+#[test]
+fn output_is_capped() {
+    for (data, expected) in blocks() {
+        assert_eq!(inflate(data, expected.len() - 1), Err(InflateError::TooBig));
+    }
+}
+
+#[test]
+fn truncated_data_is_an_error() {
+    for (data, expected) in blocks() {
+        for len in 0..data.len() {
+            let result = inflate(&data[..len], expected.len());
+            assert!(result.is_err(), "{} of {} bytes inflated", len, data.len());
+        }
+    }
+    assert_eq!(
+        inflate(&STORED[..10], 100),
+        Err(InflateError::UnexpectedEnd)
+    );
+}
@@ -0,0 +1,93 @@
+//! Records movies of the real ROM and replays them on fresh machines.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::golden::InputScript;
+use space_invaders_emulator::movie::{Movie, MovieError, MovieRecorder};
+use space_invaders_emulator::SpaceInvaders;
+
+/// Frames the recordings run for, long enough to start a game.
+const FRAMES: u64 = 600;
+
+fn rom() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders")
+}
+
+fn script() -> InputScript {
+    InputScript::parse(
+        &fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/play.txt"))
+            .expect("can not read the script"),
+    )
+    .expect("invalid script")
+}
+
+/// Records `machine` from power on for `FRAMES` frames, returning the
+/// movie and the hash after every frame.
+fn record(machine: &mut SpaceInvaders) -> (Movie, Vec<u64>) {
+    let script = script();
+    let mut recorder = MovieRecorder::from_power_on(machine);
+    let hashes = (0..FRAMES)
+        .map(|frame| {
+            script.apply(machine, frame);
+            recorder.run_frame(machine);
+            machine.frame_hash()
+        })
+        .collect();
+    (recorder.finish(machine), hashes)
+}
+
+#[test]
+fn replay_matches_every_recorded_frame() {
+    let rom = rom();
+    let (movie, hashes) = record(&mut SpaceInvaders::new(&rom));
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+
+    let mut machine = SpaceInvaders::new(&rom);
+    machine.power_on();
+    for (frame, input) in movie.frames.iter().enumerate() {
+        machine.io.port1 = input.port1;
+        machine.io.port2 = input.port2;
+        machine.run_frame();
+        assert_eq!(machine.frame_hash(), hashes[frame], "frame {}", frame);
+    }
+
+    movie.replay(&mut SpaceInvaders::new(&rom)).unwrap();
+}
+
+#[test]
+fn replay_uses_the_recorded_dip_switches() {
+    let rom = rom();
+    let mut machine = SpaceInvaders::new(&rom);
+    machine.io.dip_switches.set("ships", "5").unwrap();
+    let (movie, _) = record(&mut machine);
+
+    let mut machine = SpaceInvaders::new(&rom);
+    movie.replay(&mut machine).unwrap();
+    assert_eq!(machine.io.dip_switches.get("ships"), Some("5"));
+}
+
+#[test]
+fn replay_drops_a_pending_high_score() {
+    let rom = rom();
+    let (movie, _) = record(&mut SpaceInvaders::new(&rom));
+
+    let mut machine = SpaceInvaders::new(&rom);
+    machine.restore_high_score(5000);
+    movie.replay(&mut machine).unwrap();
+}
+
+#[test]
+fn replay_with_other_inputs_desyncs() {
+    let rom = rom();
+    let (mut movie, _) = record(&mut SpaceInvaders::new(&rom));
+    // never insert the coin, so no game starts
+    for input in &mut movie.frames {
+        input.port1 = 0;
+    }
+    assert!(matches!(
+        movie.replay(&mut SpaceInvaders::new(&rom)),
+        Err(MovieError::Desync { frames, .. }) if frames == FRAMES as usize
+    ));
+}
@@ -0,0 +1,226 @@
+//! Builds IPS and BPS patches and checks that applying them gives the
+//! image they were made for, and that damaged patches are rejected.
+
+use space_invaders_emulator::hash::crc32;
+use space_invaders_emulator::patch::{self, PatchError};
+
+/// A ROM with no two neighbouring bytes alike.
+fn rom(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 + i / 256) as u8).collect()
+}
+
+/// Appends an IPS record writing `bytes` at `offset`.
+fn ips_record(patch: &mut Vec<u8>, offset: u32, bytes: &[u8]) {
+    patch.extend_from_slice(&offset.to_be_bytes()[1..]);
+    patch.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    patch.extend_from_slice(bytes);
+}
+
+/// Appends an IPS run length record writing `count` times `byte`.
+fn ips_run(patch: &mut Vec<u8>, offset: u32, count: u16, byte: u8) {
+    patch.extend_from_slice(&offset.to_be_bytes()[1..]);
+    patch.extend_from_slice(&[0, 0]);
+    patch.extend_from_slice(&count.to_be_bytes());
+    patch.push(byte);
+}
+
+#[test]
+fn ips_records_and_runs() {
+    let rom = rom(0x100);
+    let mut patch = b"PATCH".to_vec();
+    ips_record(&mut patch, 0x10, b"INVADERS");
+    ips_run(&mut patch, 0x80, 0x20, 0xaa);
+    // past the end, which grows the image
+    ips_record(&mut patch, 0x104, &[1, 2]);
+    patch.extend_from_slice(b"EOF");
+
+    let mut expected = rom.clone();
+    expected[0x10..0x18].copy_from_slice(b"INVADERS");
+    expected[0x80..0xa0].fill(0xaa);
+    expected.extend_from_slice(&[0, 0, 0, 0, 1, 2]);
+    assert_eq!(patch::apply(&patch, &rom), Ok(expected));
+}
+
+#[test]
+fn ips_truncation_extension() {
+    let rom = rom(0x100);
+    let mut patch = b"PATCH".to_vec();
+    ips_record(&mut patch, 0, &[0xff]);
+    patch.extend_from_slice(b"EOF");
+    patch.extend_from_slice(&[0x00, 0x00, 0x40]);
+
+    let mut expected = rom[..0x40].to_vec();
+    expected[0] = 0xff;
+    assert_eq!(patch::apply(&patch, &rom), Ok(expected));
+}
+
+#[test]
+fn ips_errors() {
+    let rom = rom(0x100);
+    assert_eq!(patch::apply(b"PATCX", &rom), Err(PatchError::UnknownFormat));
+    // no EOF
+    assert_eq!(patch::apply(b"PATCH", &rom), Err(PatchError::Truncated));
+
+    let mut patch = b"PATCH".to_vec();
+    ips_record(&mut patch, 0x10, b"INVADERS");
+    patch.extend_from_slice(b"EOF");
+    for len in b"PATCH".len()..=patch.len() - 3 {
+        assert_eq!(
+            patch::apply(&patch[..len], &rom),
+            Err(PatchError::Truncated),
+            "{} bytes applied",
+            len
+        );
+    }
+}
+
+/// Appends `value` as a BPS number.
+fn varint(patch: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let bits = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(0x80 | bits);
+            return;
+        }
+        patch.push(bits);
+        value -= 1;
+    }
+}
+
+/// A BPS command, with the relative offsets of the copies.
+enum Command<'a> {
+    SourceRead(usize),
+    TargetRead(&'a [u8]),
+    SourceCopy(usize, isize),
+    TargetCopy(usize, isize),
+}
+
+/// Encodes a patch from `source` to `target` made of `commands`, with
+/// the three CRC-32s at the end.
+fn bps(source: &[u8], target: &[u8], commands: &[Command]) -> Vec<u8> {
+    let mut patch = b"BPS1".to_vec();
+    varint(&mut patch, source.len());
+    varint(&mut patch, target.len());
+    varint(&mut patch, 0);
+    let relative = |patch: &mut Vec<u8>, offset: isize| {
+        varint(patch, offset.unsigned_abs() << 1 | (offset < 0) as usize)
+    };
+    for command in commands {
+        match *command {
+            Command::SourceRead(len) => varint(&mut patch, (len - 1) << 2),
+            Command::TargetRead(bytes) => {
+                varint(&mut patch, (bytes.len() - 1) << 2 | 1);
+                patch.extend_from_slice(bytes);
+            }
+            Command::SourceCopy(len, offset) => {
+                varint(&mut patch, (len - 1) << 2 | 2);
+                relative(&mut patch, offset);
+            }
+            Command::TargetCopy(len, offset) => {
+                varint(&mut patch, (len - 1) << 2 | 3);
+                relative(&mut patch, offset);
+            }
+        }
+    }
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+    patch
+}
+
+/// Replaces the patch checksum of `patch` after it was changed.
+fn fix_patch_crc(patch: &mut Vec<u8>) {
+    patch.truncate(patch.len() - 4);
+    let crc = crc32(patch);
+    patch.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// A 20000 byte source, so that sizes and offsets take up to three bytes,
+/// and a target using every command.
+fn bps_example() -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let source = rom(20000);
+    let mut target = source[..10].to_vec();
+    target.extend_from_slice(b"INVADERS");
+    target.extend_from_slice(&source[16500..16520]);
+    target.extend_from_slice(&source[120..125]);
+    target.extend_from_within(10..22);
+    // a copy overlapping its own output repeats the last byte
+    let last = *target.last().expect("target is not empty");
+    target.extend_from_slice(&[last; 6]);
+
+    let patch = bps(
+        &source,
+        &target,
+        &[
+            Command::SourceRead(10),
+            Command::TargetRead(b"INVADERS"),
+            Command::SourceCopy(20, 16500),
+            Command::SourceCopy(5, 120 - 16520),
+            Command::TargetCopy(12, 10),
+            Command::TargetCopy(6, 54 - 22),
+        ],
+    );
+    (source, target, patch)
+}
+
+#[test]
+fn bps_commands() {
+    let (source, target, patch) = bps_example();
+    assert_eq!(patch::apply(&patch, &source), Ok(target));
+}
+
+#[test]
+fn bps_checksums() {
+    let (source, _, patch) = bps_example();
+
+    let mut other = source.clone();
+    other[19999] ^= 1;
+    assert!(matches!(
+        patch::apply(&patch, &other),
+        Err(PatchError::WrongSource { .. })
+    ));
+    assert!(matches!(
+        patch::apply(&patch, &source[1..]),
+        Err(PatchError::WrongSource { .. })
+    ));
+
+    let mut damaged = patch.clone();
+    damaged[10] ^= 1;
+    assert_eq!(
+        patch::apply(&damaged, &source),
+        Err(PatchError::PatchChecksum)
+    );
+
+    // the target CRC is the second of the three
+    let mut wrong_target = patch.clone();
+    let target_crc = wrong_target.len() - 8;
+    wrong_target[target_crc] ^= 1;
+    fix_patch_crc(&mut wrong_target);
+    assert_eq!(
+        patch::apply(&wrong_target, &source),
+        Err(PatchError::TargetChecksum)
+    );
+}
+
+#[test]
+fn bps_truncated() {
+    let (source, _, patch) = bps_example();
+    assert_eq!(patch::apply(b"BPS1", &source), Err(PatchError::Truncated));
+
+    // cut in the middle of the "INVADERS" of the target read
+    let body_end = patch.len() - 12;
+    let mut truncated = patch[..body_end].to_vec();
+    let cut = truncated
+        .windows(8)
+        .position(|window| window == b"INVADERS")
+        .expect("the patch holds the target read")
+        + 4;
+    truncated.truncate(cut);
+    truncated.extend_from_slice(&patch[body_end..]);
+    fix_patch_crc(&mut truncated);
+    assert_eq!(
+        patch::apply(&truncated, &source),
+        Err(PatchError::Truncated)
+    );
+}
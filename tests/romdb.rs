@@ -0,0 +1,164 @@
+//! Identifies and checks ROM dumps against the known sets.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::games::{self, GameDefinition};
+use space_invaders_emulator::romdb::{self, ChipStatus, RomDump, Verdict};
+
+fn rom() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders")
+}
+
+/// `size` bytes that look like code: neither blank nor mirrored.
+fn noise(size: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(2_654_435_761) | 1;
+    (0..size)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+/// Chip files of `game` under their names, cut from `image`.
+fn chip_files(game: &GameDefinition, image: &[u8]) -> RomDump {
+    let mut offset = 0;
+    RomDump::Chips(
+        game.roms
+            .iter()
+            .map(|chip| {
+                let bytes = image[offset..offset + chip.size as usize].to_vec();
+                offset += chip.size as usize;
+                (chip.file.to_owned(), bytes)
+            })
+            .collect(),
+    )
+}
+
+fn statuses(game: &'static GameDefinition, dump: &RomDump) -> Vec<ChipStatus> {
+    romdb::check(game, dump)
+        .chips
+        .iter()
+        .map(|check| check.status)
+        .collect()
+}
+
+#[test]
+fn good_dump() {
+    let image = RomDump::Image(rom());
+    assert_eq!(romdb::identify(&image), Some(&games::INVADERS));
+    assert_eq!(
+        romdb::check(&games::INVADERS, &image).verdict(),
+        Verdict::Good
+    );
+
+    let files = chip_files(&games::INVADERS, &rom());
+    assert_eq!(romdb::identify(&files), Some(&games::INVADERS));
+    assert_eq!(statuses(&games::INVADERS, &files), [ChipStatus::Good; 4]);
+}
+
+#[test]
+fn renamed_chip_is_found_by_hash() {
+    let RomDump::Chips(mut files) = chip_files(&games::INVADERS, &rom()) else {
+        unreachable!();
+    };
+    files[2].0 = "sv03.bin".to_owned();
+    let report = romdb::check(&games::INVADERS, &RomDump::Chips(files));
+    assert_eq!(report.verdict(), Verdict::Good);
+    assert_eq!(report.chips[2].found_as.as_deref(), Some("sv03.bin"));
+    assert_eq!(
+        report.chips[2].to_string(),
+        "invaders.f: good (found as sv03.bin)"
+    );
+}
+
+#[test]
+fn bad_and_modified_dumps() {
+    let mut blank = rom();
+    blank[0x1800..].fill(0xff);
+    let report = romdb::check(&games::INVADERS, &RomDump::Image(blank));
+    assert_eq!(report.verdict(), Verdict::BadDump);
+    assert_eq!(
+        report.chips[3].status,
+        ChipStatus::BadDump("every byte is the same")
+    );
+
+    let mut mirrored = rom();
+    mirrored.copy_within(0x1000..0x1400, 0x1400);
+    assert!(matches!(
+        statuses(&games::INVADERS, &RomDump::Image(mirrored))[2],
+        ChipStatus::BadDump(_)
+    ));
+
+    let mut patched = rom();
+    patched[0x0800] ^= 0xff;
+    let patched = RomDump::Image(patched);
+    // the other three chips still match
+    assert_eq!(romdb::identify(&patched), Some(&games::INVADERS));
+    assert!(matches!(
+        statuses(&games::INVADERS, &patched)[1],
+        ChipStatus::Modified { .. }
+    ));
+}
+
+#[test]
+fn wrong_size() {
+    let mut short = rom();
+    short.truncate(0x1c00);
+    let statuses = statuses(&games::INVADERS, &RomDump::Image(short));
+    assert_eq!(statuses[3], ChipStatus::WrongSize { size: 0x400 });
+
+    let mut long = rom();
+    long.push(0);
+    let report = romdb::check(&games::INVADERS, &RomDump::Image(long));
+    assert_eq!(report.extra_bytes, 1);
+    assert_eq!(report.verdict(), Verdict::WrongLayout);
+
+    let RomDump::Chips(mut files) = chip_files(&games::INVADERS, &rom()) else {
+        unreachable!();
+    };
+    files.pop();
+    let report = romdb::check(&games::INVADERS, &RomDump::Chips(files));
+    assert_eq!(report.chips[3].status, ChipStatus::Missing);
+    assert_eq!(report.verdict(), Verdict::WrongLayout);
+}
+
+#[test]
+fn unknown_set() {
+    let dump = RomDump::Chips(vec![
+        ("galaxian.u".to_owned(), noise(0x800, 1)),
+        ("galaxian.v".to_owned(), noise(0x800, 2)),
+    ]);
+    assert_eq!(romdb::identify(&dump), None);
+    assert_eq!(romdb::identify(&RomDump::Image(noise(0x1234, 3))), None);
+}
+
+#[test]
+fn sets_without_hashes_are_unverified() {
+    let image = noise(games::LRESCUE.rom_size(), 4);
+    let files = chip_files(&games::LRESCUE, &image);
+    assert_eq!(romdb::identify(&files), Some(&games::LRESCUE));
+    let report = romdb::check(&games::LRESCUE, &files);
+    assert_eq!(report.verdict(), Verdict::Unverified);
+    assert_eq!(
+        report.chips[0].to_string(),
+        "lrescue.1: 2048 bytes, no known hash"
+    );
+}
+
+#[test]
+fn clone_is_told_from_its_parent_by_chip_names() {
+    let image = noise(games::SPACEATT.rom_size(), 5);
+    let files = chip_files(&games::SPACEATT, &image);
+    let game = romdb::identify(&files).expect("identified");
+    assert_eq!((game.name, game.parent), ("spaceatt", Some("invaders")));
+    assert_eq!(romdb::check(game, &files).verdict(), Verdict::Unverified);
+
+    // a plain image of the same size is taken for the parent
+    assert_eq!(
+        romdb::identify(&RomDump::Image(image)),
+        Some(&games::INVADERS)
+    );
+}
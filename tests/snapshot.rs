@@ -0,0 +1,87 @@
+//! Checks that save states of the board restore the state a running
+//! machine keeps between frames.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::machine::Watchdog;
+use space_invaders_emulator::snapshot::{self, SaveState, StateError};
+use space_invaders_emulator::SpaceInvaders;
+
+/// Runs `count` instructions of `machine`.
+fn step(machine: &mut SpaceInvaders, count: usize) {
+    for _ in 0..count {
+        machine.step().unwrap();
+    }
+}
+
+#[test]
+fn restores_the_watchdog_kick_of_the_frame() {
+    // 0000: OUT 6; 0002: JMP 0002
+    let rom = [0xd3, 0x06, 0xc3, 0x02, 0x00];
+    let mut machine = SpaceInvaders::new(&rom);
+    step(&mut machine, 1);
+    let state = snapshot::snapshot(&machine);
+
+    let mut restored = SpaceInvaders::new(&rom);
+    restored.watchdog = Some(Watchdog::new(1));
+    restored.load_state(&state).unwrap();
+    restored.run_frame();
+    assert_eq!(restored.watchdog.as_ref().unwrap().resets(), 0);
+    restored.run_frame();
+    assert_eq!(restored.watchdog.as_ref().unwrap().resets(), 1);
+}
+
+#[test]
+fn restores_the_sound_bits_that_rose() {
+    // 0000: MVI A,05; OUT 3; HLT
+    let rom = [0x3e, 0x05, 0xd3, 0x03, 0x76];
+    let mut machine = SpaceInvaders::new(&rom);
+    step(&mut machine, 2);
+    let state = snapshot::snapshot(&machine);
+
+    let mut restored = SpaceInvaders::new(&rom);
+    restored.load_state(&state).unwrap();
+    let ports = restored.io.take_sound_ports();
+    assert_eq!((ports.port3, ports.rose3), (0x05, 0x05));
+}
+
+#[test]
+fn restores_the_pending_high_score() {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders");
+    let mut machine = SpaceInvaders::new(&rom);
+    machine.restore_high_score(1234);
+    let state = snapshot::snapshot(&machine);
+
+    let mut restored = SpaceInvaders::new(&rom);
+    restored.load_state(&state).unwrap();
+    for _ in 0..10 {
+        restored.run_frame();
+    }
+    assert_eq!(restored.high_score(), Some(1234));
+
+    // and a state without one clears it
+    let state = snapshot::snapshot(&SpaceInvaders::new(&rom));
+    let mut restored = SpaceInvaders::new(&rom);
+    restored.restore_high_score(1234);
+    restored.load_state(&state).unwrap();
+    for _ in 0..10 {
+        restored.run_frame();
+    }
+    assert_eq!(restored.high_score(), Some(0));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut state = snapshot::snapshot(&SpaceInvaders::new(&[]));
+    let version = state[0];
+    state[0] = version.wrapping_add(1);
+    assert_eq!(
+        SpaceInvaders::new(&[]).load_state(&state),
+        Err(StateError::Version {
+            expected: version,
+            found: version.wrapping_add(1),
+        })
+    );
+}
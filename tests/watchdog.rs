@@ -0,0 +1,52 @@
+//! Checks that the watchdog resets a hung game and leaves a running one
+//! alone.
+
+use std::fs;
+use std::path::Path;
+
+use space_invaders_emulator::machine::{Watchdog, WatchdogReset, WATCHDOG_FRAMES};
+use space_invaders_emulator::SpaceInvaders;
+
+/// Runs `machine` for `frames` frames.
+fn run(machine: &mut SpaceInvaders, frames: u64) {
+    for _ in 0..frames {
+        machine.run_frame();
+    }
+}
+
+#[test]
+fn hung_game_is_reset() {
+    // 0000: JMP 0000, never writing to OUT 6
+    let mut machine = SpaceInvaders::new(&[0xc3, 0x00, 0x00]);
+    machine.watchdog = Some(Watchdog::new(10));
+    run(&mut machine, 9);
+    assert_eq!(machine.watchdog.as_ref().unwrap().resets(), 0);
+    run(&mut machine, 16);
+    let watchdog = machine.watchdog.as_ref().unwrap();
+    assert_eq!(watchdog.resets(), 2);
+    assert_eq!(
+        watchdog.last_reset(),
+        Some(WatchdogReset { frame: 20, pc: 0 })
+    );
+}
+
+#[test]
+fn kicking_game_is_not_reset() {
+    // 0000: OUT 6; JMP 0000
+    let mut machine = SpaceInvaders::new(&[0xd3, 0x06, 0xc3, 0x00, 0x00]);
+    machine.watchdog = Some(Watchdog::new(10));
+    run(&mut machine, 100);
+    let watchdog = machine.watchdog.as_ref().unwrap();
+    assert_eq!(watchdog.resets(), 0);
+    assert_eq!(watchdog.last_reset(), None);
+}
+
+#[test]
+fn space_invaders_kicks_the_watchdog() {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("rom/invaders"))
+        .expect("can not read rom/invaders");
+    let mut machine = SpaceInvaders::new(&rom);
+    machine.watchdog = Some(Watchdog::default());
+    run(&mut machine, 4 * WATCHDOG_FRAMES as u64);
+    assert_eq!(machine.watchdog.as_ref().unwrap().resets(), 0);
+}
@@ -0,0 +1,66 @@
+//! Reads a small archive made by Python's zipfile, whole and damaged.
+
+use space_invaders_emulator::inflate::InflateError;
+use space_invaders_emulator::zip::{ZipArchive, ZipError};
+
+/// `invaders.h` deflated: 16 zero bytes and "INVADERS" 8 times.
+const ARCHIVE: [u8; 133] = [
+    0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x76, 0xb3, 0x52, 0x5d, 0x7a, 0xde,
+    0x8b, 0xd9, 0x0f, 0x00, 0x00, 0x00, 0x50, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x69, 0x6e,
+    0x76, 0x61, 0x64, 0x65, 0x72, 0x73, 0x2e, 0x68, 0x63, 0x60, 0x40, 0x05, 0x9e, 0x7e, 0x61, 0x8e,
+    0x2e, 0xae, 0x41, 0xc1, 0xe4, 0xd2, 0x00, 0x50, 0x4b, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00,
+    0x00, 0x08, 0x00, 0x76, 0xb3, 0x52, 0x5d, 0x7a, 0xde, 0x8b, 0xd9, 0x0f, 0x00, 0x00, 0x00, 0x50,
+    0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x69, 0x6e, 0x76, 0x61, 0x64, 0x65, 0x72, 0x73, 0x2e, 0x68, 0x50,
+    0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x38, 0x00, 0x00, 0x00, 0x37,
+    0x00, 0x00, 0x00, 0x00, 0x00,
+];
+/// Offsets of the compressed and uncompressed sizes in the central
+/// directory, which starts at 0x37.
+const COMPRESSED_SIZE: usize = 0x37 + 20;
+const SIZE: usize = 0x37 + 24;
+
+fn read(archive: Vec<u8>) -> Result<Vec<u8>, ZipError> {
+    let archive = ZipArchive::new(archive)?;
+    archive.read(&archive.entries()[0])
+}
+
+#[test]
+fn file_is_read() {
+    let mut expected = vec![0; 16];
+    expected.extend(b"INVADERS".repeat(8));
+    assert_eq!(read(ARCHIVE.to_vec()), Ok(expected));
+}
+
+#[test]
+fn truncated_archive_is_an_error() {
+    for len in 0..ARCHIVE.len() {
+        assert!(read(ARCHIVE[..len].to_vec()).is_err(), "{} bytes read", len);
+    }
+}
+
+#[test]
+fn truncated_file_is_an_error() {
+    let mut archive = ARCHIVE.to_vec();
+    archive[COMPRESSED_SIZE] -= 4;
+    assert_eq!(
+        read(archive),
+        Err(ZipError::Inflate {
+            name: "invaders.h".to_owned(),
+            err: InflateError::UnexpectedEnd,
+        })
+    );
+}
+
+#[test]
+fn file_bigger_than_its_size_is_an_error() {
+    let mut archive = ARCHIVE.to_vec();
+    archive[SIZE] = 0x20;
+    assert_eq!(
+        read(archive),
+        Err(ZipError::Inflate {
+            name: "invaders.h".to_owned(),
+            err: InflateError::TooBig,
+        })
+    );
+}